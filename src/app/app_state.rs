@@ -1,15 +1,24 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::db::Database;
+use crate::db::connection::{ConnectionManager, ConnectionState};
+use crate::db::storage::Storage;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub db: Arc<RwLock<Option<Database>>>,
+    pub db: Arc<RwLock<Option<Arc<dyn Storage>>>>,
+    pub connection: Arc<ConnectionManager>,
 }
 
 impl AppState {
-    pub fn new(db: Arc<RwLock<Option<Database>>>) -> Self {
-        Self { db }
+    pub fn new(connection: Arc<ConnectionManager>) -> Self {
+        Self {
+            db: connection.storage(),
+            connection,
+        }
     }
-}
\ No newline at end of file
+
+    pub fn connection_state(&self) -> ConnectionState {
+        self.connection.state()
+    }
+}
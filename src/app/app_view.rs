@@ -1,19 +1,79 @@
 use gpui::*;
+use gpui_component::ActiveTheme;
+
 use crate::app::AppState;
-use crate::components::sidebar::Sidebar;
+use crate::components::scanner::Scanner;
+use crate::db::connection::ConnectionState;
 
 pub struct AppView {
     app_state: AppState,
-    sidebar: View<Sidebar>,
+    scanner: Entity<Scanner>,
+    _scan_keys: Subscription,
 }
 
 impl AppView {
-    pub fn new(app_state: AppState) -> Self {
-        Self { 
+    pub fn new(app_state: AppState, cx: &mut Context<Self>) -> Self {
+        // Re-render whenever the database connection changes state
+        let mut connection = app_state.connection.subscribe();
+        cx.spawn(async move |this, cx| {
+            while connection.changed().await.is_ok() {
+                if this.update(cx, |_, cx| cx.notify()).is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        // Key bindings resolve before even capture-phase listeners run, so
+        // only an interceptor can keep a scan's Enter from reaching them
+        let scanner = cx.new(|_| Scanner::new());
+        let _scan_keys = cx.intercept_keystrokes({
+            let scanner = scanner.clone();
+            move |event, _window, cx| {
+                let scanned =
+                    scanner.update(cx, |scanner, cx| scanner.handle_key(&event.keystroke, cx));
+                if scanned {
+                    cx.stop_propagation();
+                }
+            }
+        });
+
+        Self {
             app_state,
-            sidebar: View::new(Sidebar::new()),
+            scanner,
+            _scan_keys,
         }
     }
+
+    /// Screens subscribe to this for barcode scans.
+    pub fn scanner(&self) -> Entity<Scanner> {
+        self.scanner.clone()
+    }
+
+    fn render_connection_status(&self) -> impl IntoElement {
+        let state = self.app_state.connection_state();
+        let color = match state {
+            ConnectionState::Connected => hsla(142.0 / 360.0, 0.71, 0.45, 1.0), // #22c55e
+            ConnectionState::Degraded => hsla(38.0 / 360.0, 0.92, 0.50, 1.0),   // #f59e0b
+            ConnectionState::Offline | ConnectionState::Failed => {
+                hsla(0.0, 0.84, 0.60, 1.0) // #ef4444
+            }
+        };
+        let label = match self.app_state.connection.last_error() {
+            Some(reason) if state != ConnectionState::Connected => {
+                SharedString::from(format!("{}: {}", state.label(), reason))
+            }
+            _ => SharedString::from(state.label()),
+        };
+
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .text_xs()
+            .child(div().size_2().rounded_full().bg(color))
+            .child(label)
+    }
 }
 
 impl Render for AppView {
@@ -26,12 +86,24 @@ impl Render for AppView {
             .flex_row()
             .bg(bg)
             .text_color(cx.theme().foreground)
-            .child(self.sidebar.clone())
             .child(
                 div()
                     .flex_1()
-                    .p_4()
-                    .child("Main Content Area")
+                    .flex()
+                    .flex_col()
+                    .child(div().flex_1().p_4().child("Main Content Area"))
+                    .child(
+                        // Status bar
+                        div()
+                            .h(px(28.0))
+                            .px_4()
+                            .flex()
+                            .items_center()
+                            .justify_end()
+                            .border_t_1()
+                            .border_color(cx.theme().border)
+                            .child(self.render_connection_status()),
+                    ),
             )
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,5 @@
-mod app_view;
 mod app_state;
+mod app_view;
 
+pub use app_state::AppState;
 pub use app_view::AppView;
-pub use app_state::AppState;
\ No newline at end of file
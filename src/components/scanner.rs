@@ -0,0 +1,235 @@
+use std::time::{Duration, Instant};
+
+use gpui::prelude::FluentBuilder;
+use gpui::*;
+
+/// Longest pause between the keystrokes of one scan. Keyboard-wedge
+/// scanners type far faster than people do.
+const MAX_KEY_GAP: Duration = Duration::from_millis(50);
+/// Shorter bursts are taken as typing.
+const MIN_SCAN_LENGTH: usize = 4;
+
+/// Tells a scanner's burst of keystrokes ending in Enter apart from typing.
+#[derive(Debug, Default)]
+pub struct ScanDetector {
+    buffer: String,
+    last_key: Option<Instant>,
+}
+
+impl ScanDetector {
+    pub fn key(&mut self, text: &str, at: Instant) {
+        if !self.is_burst(at) {
+            self.buffer.clear();
+        }
+        self.buffer.push_str(text);
+        self.last_key = Some(at);
+    }
+
+    /// Ends the burst; returns the code when it came fast enough to be a
+    /// scan.
+    pub fn enter(&mut self, at: Instant) -> Option<String> {
+        let burst = self.is_burst(at);
+        let code = std::mem::take(&mut self.buffer);
+        self.last_key = None;
+        (burst && code.trim().len() >= MIN_SCAN_LENGTH).then(|| code.trim().to_string())
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.last_key = None;
+    }
+
+    fn is_burst(&self, at: Instant) -> bool {
+        self.last_key
+            .is_some_and(|last| at.saturating_duration_since(last) <= MAX_KEY_GAP)
+    }
+}
+
+/// A completed scan.
+#[derive(Debug, Clone)]
+pub struct Scanned(pub SharedString);
+
+/// Scanner input mode. The app view feeds it every keystroke before the
+/// focused element or any key binding sees it; order,
+/// receipt and count screens subscribe to `Scanned` and resolve codes with
+/// `CatalogService::resolve_scan`.
+pub struct Scanner {
+    detector: ScanDetector,
+    enabled: bool,
+}
+
+impl EventEmitter<Scanned> for Scanner {}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scanner {
+    pub fn new() -> Self {
+        Self {
+            detector: ScanDetector::default(),
+            enabled: true,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool, cx: &mut Context<Self>) {
+        self.enabled = enabled;
+        self.detector.reset();
+        cx.notify();
+    }
+
+    /// Returns `true` when the key completed a scan, so the caller can keep
+    /// the Enter from also submitting a focused form.
+    pub fn handle_key(&mut self, keystroke: &Keystroke, cx: &mut Context<Self>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let now = Instant::now();
+        if keystroke.modifiers.control || keystroke.modifiers.platform || keystroke.modifiers.alt {
+            self.detector.reset();
+            return false;
+        }
+
+        if keystroke.key == "enter" {
+            if let Some(code) = self.detector.enter(now) {
+                cx.emit(Scanned(code.into()));
+                return true;
+            }
+            return false;
+        }
+        match &keystroke.key_char {
+            Some(text) => self.detector.key(text, now),
+            None => self.detector.reset(),
+        }
+        false
+    }
+}
+
+type AssignHandler = Box<dyn Fn(&SharedString, &mut Window, &mut App) + 'static>;
+
+/// Shown for a scanned code no variant has, with an action to assign it.
+#[derive(IntoElement)]
+pub struct UnknownCode {
+    code: SharedString,
+    on_assign: Option<AssignHandler>,
+}
+
+impl UnknownCode {
+    pub fn new(code: impl Into<SharedString>) -> Self {
+        Self {
+            code: code.into(),
+            on_assign: None,
+        }
+    }
+
+    pub fn on_assign(
+        mut self,
+        handler: impl Fn(&SharedString, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_assign = Some(Box::new(handler));
+        self
+    }
+}
+
+impl RenderOnce for UnknownCode {
+    fn render(self, _window: &mut Window, _cx: &mut App) -> impl IntoElement {
+        let warning = hsla(38.0 / 360.0, 0.92, 0.50, 1.0); // #f59e0b
+        let code = self.code.clone();
+
+        div()
+            .flex()
+            .items_center()
+            .justify_between()
+            .gap_3()
+            .px_3()
+            .py_2()
+            .rounded_md()
+            .border_1()
+            .border_color(warning)
+            .text_sm()
+            .child(format!("Unknown code {}", self.code))
+            .when_some(self.on_assign, |this, on_assign| {
+                this.child(
+                    div()
+                        .id("assign-barcode")
+                        .cursor_pointer()
+                        .font_weight(FontWeight::SEMIBOLD)
+                        .text_color(warning)
+                        .child("Assign to variant")
+                        .on_click(move |_, window, cx| on_assign(&code, window, cx)),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::ScanDetector;
+
+    fn feed(detector: &mut ScanDetector, code: &str, start: Instant, gap: Duration) -> Instant {
+        let mut at = start;
+        for c in code.chars() {
+            detector.key(&c.to_string(), at);
+            at += gap;
+        }
+        at
+    }
+
+    #[test]
+    fn scanned_burst_is_a_scan() {
+        let mut detector = ScanDetector::default();
+        let end = feed(
+            &mut detector,
+            "4006381333931",
+            Instant::now(),
+            Duration::from_millis(5),
+        );
+        assert_eq!(detector.enter(end).as_deref(), Some("4006381333931"));
+    }
+
+    #[test]
+    fn slow_typing_is_not_a_scan() {
+        let mut detector = ScanDetector::default();
+        let end = feed(
+            &mut detector,
+            "4006381333931",
+            Instant::now(),
+            Duration::from_millis(200),
+        );
+        assert_eq!(detector.enter(end), None);
+    }
+
+    #[test]
+    fn burst_after_typing_keeps_only_the_burst() {
+        let mut detector = ScanDetector::default();
+        let start = Instant::now();
+        let typed = feed(&mut detector, "abc", start, Duration::from_millis(200));
+        let end = feed(
+            &mut detector,
+            "96385074",
+            typed + Duration::from_millis(500),
+            Duration::from_millis(5),
+        );
+        assert_eq!(detector.enter(end).as_deref(), Some("96385074"));
+    }
+
+    #[test]
+    fn short_bursts_are_typing() {
+        let mut detector = ScanDetector::default();
+        let end = feed(
+            &mut detector,
+            "ok",
+            Instant::now(),
+            Duration::from_millis(5),
+        );
+        assert_eq!(detector.enter(end), None);
+    }
+}
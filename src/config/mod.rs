@@ -4,10 +4,24 @@ use serde::Deserialize;
 pub struct Config {
     pub database: DatabaseConfig,
     pub app: AppConfig,
+    #[serde(default)]
+    pub inventory: InventoryConfig,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    #[default]
+    Postgres,
+    Sqlite,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct DatabaseConfig {
+    #[serde(default)]
+    pub backend: DatabaseBackend,
+    #[serde(default = "default_sqlite_path")]
+    pub sqlite_path: String,
     pub host: String,
     pub port: u16,
     pub username: String,
@@ -31,6 +45,10 @@ impl DatabaseConfig {
     }
 }
 
+fn default_sqlite_path() -> String {
+    "myapp.db".into()
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct AppConfig {
     pub title: String,
@@ -38,10 +56,30 @@ pub struct AppConfig {
     pub height: f32,
 }
 
+/// What stock posting does when an outgoing movement would take a
+/// warehouse below zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NegativeStockPolicy {
+    #[default]
+    Forbid,
+    /// Post the movement but report the shortfall.
+    Warn,
+    Allow,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InventoryConfig {
+    #[serde(default)]
+    pub negative_stock: NegativeStockPolicy,
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             database: DatabaseConfig {
+                backend: DatabaseBackend::Postgres,
+                sqlite_path: default_sqlite_path(),
                 host: "localhost".into(),
                 port: 5432,
                 username: "postgres".into(),
@@ -53,6 +91,7 @@ impl Default for Config {
                 width: 800.0,
                 height: 600.0,
             },
+            inventory: InventoryConfig::default(),
         }
     }
 }
@@ -0,0 +1,107 @@
+use sqlx::query::Query;
+use sqlx::{Database, Postgres, QueryBuilder, Sqlite};
+
+use crate::config::DatabaseBackend;
+use crate::db::migrations::{Migration, POSTGRES_MIGRATIONS, SQLITE_MIGRATIONS};
+use crate::db::repositories::SqlValue;
+
+/// The dialect differences between the databases the repositories run on.
+///
+/// Generic code over a backend also needs these bounds, which Rust cannot
+/// carry on the trait itself:
+///
+/// ```ignore
+/// for<'e> &'e mut DB::Connection: Executor<'e, Database = DB>,
+/// for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+/// ```
+pub trait Backend: Database {
+    const KIND: DatabaseBackend;
+    const MIGRATIONS: &'static [Migration];
+    const MIGRATIONS_TABLE: &'static str;
+    /// Statements run around a migration so two app instances never
+    /// migrate the same database at once.
+    const MIGRATION_LOCK: Option<(&'static str, &'static str)>;
+
+    fn push_value(query: &mut QueryBuilder<'_, Self>, value: SqlValue);
+
+    /// `QueryBuilder::build` with the arguments narrowed to the borrow, which
+    /// generic code cannot do itself because `Arguments<'q>` is invariant there.
+    fn build<'q>(query: &'q mut QueryBuilder<'_, Self>) -> Query<'q, Self, Self::Arguments<'q>>;
+}
+
+impl Backend for Postgres {
+    const KIND: DatabaseBackend = DatabaseBackend::Postgres;
+    const MIGRATIONS: &'static [Migration] = POSTGRES_MIGRATIONS;
+    const MIGRATIONS_TABLE: &'static str = r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum BYTEA NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            execution_ms BIGINT NOT NULL
+        )
+    "#;
+    const MIGRATION_LOCK: Option<(&'static str, &'static str)> = Some((
+        "SELECT pg_advisory_lock(7888443357865665634)",
+        "SELECT pg_advisory_unlock(7888443357865665634)",
+    ));
+
+    fn push_value(query: &mut QueryBuilder<'_, Self>, value: SqlValue) {
+        match value {
+            SqlValue::Uuid(v) => query.push_bind(v),
+            SqlValue::Text(v) => query.push_bind(v),
+            SqlValue::Bool(v) => query.push_bind(v),
+            SqlValue::Int(v) => query.push_bind(v),
+            SqlValue::Decimal(v) => query.push_bind(v),
+            SqlValue::Date(v) => query.push_bind(v),
+            SqlValue::Json(v) => query.push_bind(v),
+            SqlValue::Bytes(v) => query.push_bind(v),
+            // Text parameters are not implicitly converted to enum types
+            SqlValue::Enum(type_name, v) => query
+                .push("CAST(")
+                .push_bind(v)
+                .push(" AS ")
+                .push(type_name)
+                .push(")"),
+        };
+    }
+
+    fn build<'q>(query: &'q mut QueryBuilder<'_, Self>) -> Query<'q, Self, Self::Arguments<'q>> {
+        query.build()
+    }
+}
+
+impl Backend for Sqlite {
+    const KIND: DatabaseBackend = DatabaseBackend::Sqlite;
+    const MIGRATIONS: &'static [Migration] = SQLITE_MIGRATIONS;
+    const MIGRATIONS_TABLE: &'static str = r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum BLOB NOT NULL,
+            applied_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            execution_ms INTEGER NOT NULL
+        )
+    "#;
+    // The database file belongs to a single app instance
+    const MIGRATION_LOCK: Option<(&'static str, &'static str)> = None;
+
+    fn push_value(query: &mut QueryBuilder<'_, Self>, value: SqlValue) {
+        match value {
+            SqlValue::Uuid(v) => query.push_bind(v),
+            SqlValue::Text(v) => query.push_bind(v),
+            SqlValue::Bool(v) => query.push_bind(v),
+            SqlValue::Int(v) => query.push_bind(v),
+            // No decimal type; column affinity converts the text
+            SqlValue::Decimal(v) => query.push_bind(v.map(|d| d.to_string())),
+            SqlValue::Date(v) => query.push_bind(v),
+            SqlValue::Json(v) => query.push_bind(v),
+            SqlValue::Bytes(v) => query.push_bind(v),
+            SqlValue::Enum(_, v) => query.push_bind(v),
+        };
+    }
+
+    fn build<'q>(query: &'q mut QueryBuilder<'_, Self>) -> Query<'q, Self, Self::Arguments<'q>> {
+        query.build()
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::error::{AppError, Result};
+
+/// Retail barcode formats whose check digit is verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Symbology {
+    Ean13,
+    Ean8,
+    UpcA,
+}
+
+impl fmt::Display for Symbology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Symbology::Ean13 => "EAN-13",
+            Symbology::Ean8 => "EAN-8",
+            Symbology::UpcA => "UPC-A",
+        })
+    }
+}
+
+/// Checks a barcode before it is saved and returns it trimmed. All-digit
+/// codes must be a valid EAN-13, EAN-8 or UPC-A; codes with other
+/// characters (Code 128 and the like) carry no check digit and pass as is.
+pub fn validate(code: &str) -> Result<String> {
+    let code = code.trim();
+    if code.is_empty() || code.chars().any(char::is_whitespace) {
+        return Err(AppError::App(format!("invalid barcode {:?}", code)));
+    }
+    if !code.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(code.to_string());
+    }
+
+    let symbology = symbology(code).ok_or_else(|| {
+        AppError::App(format!(
+            "{} digits is not an EAN-13, EAN-8 or UPC-A barcode",
+            code.len()
+        ))
+    })?;
+    let (body, check) = code.split_at(code.len() - 1);
+    let expected = check_digit(body);
+    if check.as_bytes()[0] - b'0' != expected {
+        return Err(AppError::App(format!(
+            "{} {} has a wrong check digit, expected {}",
+            symbology, code, expected
+        )));
+    }
+    Ok(code.to_string())
+}
+
+/// Format of an all-digit code, judged by its length.
+pub fn symbology(code: &str) -> Option<Symbology> {
+    match code.len() {
+        13 => Some(Symbology::Ean13),
+        12 => Some(Symbology::UpcA),
+        8 => Some(Symbology::Ean8),
+        _ => None,
+    }
+}
+
+/// GS1 mod-10 check digit of the digits before it: weights 3 and 1
+/// alternate from the rightmost digit.
+pub fn check_digit(body: &str) -> u8 {
+    let sum: u32 = body
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| u32::from(b - b'0') * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+/// Other spellings a scanner may report for the same item: a UPC-A is an
+/// EAN-13 with a leading zero.
+pub fn equivalents(code: &str) -> Vec<String> {
+    let code = code.trim();
+    let mut codes = vec![code.to_string()];
+    if code.bytes().all(|b| b.is_ascii_digit()) {
+        match code.len() {
+            12 => codes.push(format!("0{}", code)),
+            13 if code.starts_with('0') => codes.push(code[1..].to_string()),
+            _ => {}
+        }
+    }
+    codes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Symbology, equivalents, symbology, validate};
+
+    #[test]
+    fn accepts_valid_check_digits() {
+        for (code, expected) in [
+            ("4006381333931", Symbology::Ean13),
+            ("96385074", Symbology::Ean8),
+            ("036000291452", Symbology::UpcA),
+        ] {
+            assert_eq!(validate(&format!(" {} ", code)).unwrap(), code);
+            assert_eq!(symbology(code), Some(expected));
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_check_digits_and_lengths() {
+        for code in ["4006381333932", "96385075", "036000291453"] {
+            let err = validate(code).unwrap_err().to_string();
+            assert!(err.contains("wrong check digit"), "{}", err);
+        }
+        assert!(validate("1234567").is_err());
+        assert!(validate("12 34").is_err());
+        assert_eq!(validate("ABC-123").unwrap(), "ABC-123");
+    }
+
+    #[test]
+    fn upc_a_is_ean_13_with_a_leading_zero() {
+        assert_eq!(validate("0036000291452").unwrap(), "0036000291452");
+        assert_eq!(
+            equivalents("036000291452"),
+            vec!["036000291452", "0036000291452"]
+        );
+        assert_eq!(
+            equivalents("0036000291452"),
+            vec!["0036000291452", "036000291452"]
+        );
+        assert_eq!(equivalents("4006381333931"), vec!["4006381333931"]);
+    }
+}
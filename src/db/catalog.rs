@@ -0,0 +1,723 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::db::barcode;
+use crate::db::enums::TrackingMode;
+use crate::db::models::{
+    CreateProductAttribute, CreateProductVariant, Product, ProductAttribute, ProductVariant,
+    UpdateProductVariant,
+};
+use crate::db::money::Money;
+use crate::db::repositories::{Entity, Repository};
+use crate::error::{AppError, Result};
+
+/// What `CatalogService::generate` gives each new variant. The SKU
+/// template names attributes in braces, e.g. `{product}-{size}-{color}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantMatrix {
+    pub sku_template: String,
+    pub cost_price: Money,
+    pub selling_price: Money,
+    pub tracking: TrackingMode,
+    #[serde(default)]
+    pub stock_unit_id: Option<Uuid>,
+}
+
+/// Outcome of a scan.
+#[derive(Debug, Clone, Serialize)]
+pub enum ScanResult {
+    Found(Box<ProductVariant>),
+    /// A well-formed code no variant has.
+    Unknown {
+        code: String,
+    },
+    /// Most likely a misread, e.g. a wrong check digit.
+    Invalid {
+        code: String,
+        reason: String,
+    },
+}
+
+/// Product attribute definitions and the variants built from them.
+/// Variants of a product with definitions must carry exactly one allowed
+/// value for each attribute. The database enforces that; checking here
+/// first gives clearer errors. Postgres only.
+pub struct CatalogService<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> CatalogService<'c> {
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+
+    pub async fn attributes(&mut self, product_id: Uuid) -> Result<Vec<ProductAttribute>> {
+        sqlx::query_as(
+            "SELECT id, product_id, name, position, allowed_values FROM product_attributes \
+             WHERE product_id = $1 ORDER BY position, name",
+        )
+        .bind(product_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Adds an attribute with its allowed values, trimmed and without
+    /// repeats. Refused once the product has variants, which would have no
+    /// value for it.
+    pub async fn define_attribute(
+        &mut self,
+        mut input: CreateProductAttribute,
+    ) -> Result<ProductAttribute> {
+        input.name = input.name.trim().to_string();
+        if input.name.is_empty() || input.name.eq_ignore_ascii_case("product") {
+            return Err(AppError::App(format!(
+                "invalid attribute name {:?}",
+                input.name
+            )));
+        }
+        let mut seen = HashSet::new();
+        input.allowed_values = input
+            .allowed_values
+            .iter()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty() && seen.insert(value.clone()))
+            .collect();
+        if input.allowed_values.is_empty() {
+            return Err(AppError::App(format!(
+                "attribute {} needs at least one value",
+                input.name
+            )));
+        }
+
+        Repository::<ProductAttribute>::new(&mut *self.conn)
+            .create(input)
+            .await
+    }
+
+    pub async fn create_variant(
+        &mut self,
+        mut input: CreateProductVariant,
+    ) -> Result<ProductVariant> {
+        let product = self.lock_product(input.product_id).await?;
+        if let Some(code) = &input.barcode {
+            input.barcode = Some(self.check_barcode(product.company_id, code, None).await?);
+        }
+        if let Some(attributes) = &input.attributes {
+            self.check(input.product_id, attributes, None).await?;
+        } else if !self.attributes(input.product_id).await?.is_empty() {
+            return Err(AppError::App(
+                "variants of this product need a value for each attribute".into(),
+            ));
+        }
+        Repository::<ProductVariant>::new(&mut *self.conn)
+            .create(input)
+            .await
+    }
+
+    pub async fn update_variant(
+        &mut self,
+        id: Uuid,
+        mut input: UpdateProductVariant,
+    ) -> Result<Option<ProductVariant>> {
+        let Some(variant) = Repository::<ProductVariant>::new(&mut *self.conn)
+            .get(id)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let product = self.lock_product(variant.product_id).await?;
+        if let Some(Some(code)) = &input.barcode {
+            input.barcode = Some(Some(
+                self.check_barcode(product.company_id, code, Some(id))
+                    .await?,
+            ));
+        }
+        if input
+            .stock_unit_id
+            .is_some_and(|unit| unit != variant.stock_unit_id)
+        {
+            let moved: bool = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM stock_movements WHERE variant_id = $1)",
+            )
+            .bind(id)
+            .fetch_one(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+            if moved {
+                return Err(AppError::App(
+                    "the stock unit cannot change once the variant has stock movements".into(),
+                ));
+            }
+        }
+        if input
+            .tracking
+            .is_some_and(|tracking| tracking != variant.tracking)
+        {
+            // Lot balances would no longer add up to the ledger once
+            // stock is held under the other mode
+            let stocked: bool = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM stock_ledger \
+                                WHERE variant_id = $1 AND (quantity <> 0 OR reserved <> 0)) \
+                     OR EXISTS (SELECT 1 FROM stock_lot_balances b \
+                                JOIN stock_lots l ON l.id = b.lot_id \
+                                WHERE l.variant_id = $1 AND b.quantity <> 0)",
+            )
+            .bind(id)
+            .fetch_one(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+            if stocked {
+                return Err(AppError::App(
+                    "tracking cannot change while the variant has stock, reservations or lots"
+                        .into(),
+                ));
+            }
+        }
+        if let Some(attributes) = &input.attributes {
+            match attributes {
+                Some(attributes) => self.check(variant.product_id, attributes, Some(id)).await?,
+                None if !self.attributes(variant.product_id).await?.is_empty() => {
+                    return Err(AppError::App(
+                        "variants of this product need a value for each attribute".into(),
+                    ));
+                }
+                None => {}
+            }
+        }
+        Repository::<ProductVariant>::new(&mut *self.conn)
+            .update(id, input)
+            .await
+    }
+
+    /// Checks a variant's attributes against the product's definitions
+    /// and its other variants. Products without definitions take any JSON
+    /// object, as long as no other variant has the same one.
+    pub async fn check(
+        &mut self,
+        product_id: Uuid,
+        attributes: &Value,
+        except: Option<Uuid>,
+    ) -> Result<()> {
+        let Value::Object(values) = attributes else {
+            return Err(AppError::App("variant attributes must be an object".into()));
+        };
+
+        let definitions = self.attributes(product_id).await?;
+        if !definitions.is_empty() {
+            for definition in &definitions {
+                match values.get(&definition.name) {
+                    Some(Value::String(value)) if definition.allowed_values.contains(value) => {}
+                    Some(value) => {
+                        return Err(AppError::App(format!(
+                            "{} is not an allowed {}",
+                            value, definition.name
+                        )));
+                    }
+                    None => {
+                        return Err(AppError::App(format!(
+                            "missing attribute {}",
+                            definition.name
+                        )));
+                    }
+                }
+            }
+            if let Some(extra) = values
+                .keys()
+                .find(|key| !definitions.iter().any(|d| &d.name == *key))
+            {
+                return Err(AppError::App(format!(
+                    "{} is not an attribute of this product",
+                    extra
+                )));
+            }
+        }
+
+        let taken: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM product_variants \
+             WHERE product_id = $1 AND attributes = $2 AND ($3::uuid IS NULL OR id <> $3))",
+        )
+        .bind(product_id)
+        .bind(attributes)
+        .bind(except)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+        if taken {
+            return Err(AppError::App(
+                "another variant of this product has the same attributes".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Looks up a scanned code. Codes nobody has are reported back so the
+    /// screen can offer to assign them to a variant.
+    pub async fn resolve_scan(&mut self, company_id: Uuid, code: &str) -> Result<ScanResult> {
+        let code = code.trim();
+        if let Some(variant) = Repository::<ProductVariant>::new(&mut *self.conn)
+            .find_by_code(company_id, code)
+            .await?
+        {
+            return Ok(ScanResult::Found(Box::new(variant)));
+        }
+        Ok(match barcode::validate(code) {
+            Ok(code) => ScanResult::Unknown { code },
+            Err(err) => ScanResult::Invalid {
+                code: code.to_string(),
+                reason: err.to_string(),
+            },
+        })
+    }
+
+    /// Gives a variant the barcode of an unknown scan.
+    pub async fn assign_barcode(&mut self, variant_id: Uuid, code: &str) -> Result<ProductVariant> {
+        self.update_variant(
+            variant_id,
+            UpdateProductVariant {
+                barcode: Some(Some(code.to_string())),
+                ..UpdateProductVariant::default()
+            },
+        )
+        .await?
+        .ok_or_else(|| AppError::App(format!("product variant {} not found", variant_id)))
+    }
+
+    /// Validates a barcode and checks no other variant of the company has
+    /// it under any spelling.
+    async fn check_barcode(
+        &mut self,
+        company_id: Uuid,
+        code: &str,
+        except: Option<Uuid>,
+    ) -> Result<String> {
+        let code = barcode::validate(code)?;
+        let owner: Option<Option<String>> = sqlx::query_scalar(
+            "SELECT v.sku FROM product_variants v JOIN products p ON p.id = v.product_id \
+             WHERE p.company_id = $1 AND v.barcode = ANY($2) \
+             AND ($3::uuid IS NULL OR v.id <> $3) LIMIT 1",
+        )
+        .bind(company_id)
+        .bind(barcode::equivalents(&code))
+        .bind(except)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+        if let Some(sku) = owner {
+            return Err(AppError::App(format!(
+                "barcode {} already belongs to {}",
+                code,
+                sku.as_deref().unwrap_or("another variant")
+            )));
+        }
+        Ok(code)
+    }
+
+    /// Creates a variant for every combination of attribute values the
+    /// product does not have yet, in attribute order. All SKUs are checked
+    /// before anything is written.
+    pub async fn generate(
+        &mut self,
+        product_id: Uuid,
+        matrix: &VariantMatrix,
+    ) -> Result<Vec<ProductVariant>> {
+        let product = self.lock_product(product_id).await?;
+        let definitions = self.attributes(product_id).await?;
+        if definitions.is_empty() {
+            return Err(AppError::App(
+                "define the product's attributes before generating variants".into(),
+            ));
+        }
+
+        let existing: HashSet<Value> = sqlx::query_scalar(
+            "SELECT attributes FROM product_variants \
+             WHERE product_id = $1 AND attributes IS NOT NULL",
+        )
+        .bind(product_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?
+        .into_iter()
+        .collect();
+
+        let mut inputs = Vec::new();
+        let mut skus = HashSet::new();
+        for combination in combinations(&definitions) {
+            let attributes = Value::Object(
+                combination
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), Value::from(*value)))
+                    .collect::<Map<_, _>>(),
+            );
+            if existing.contains(&attributes) {
+                continue;
+            }
+            let sku = render_sku(&matrix.sku_template, &product.name, &combination)?;
+            if !skus.insert(sku.clone()) {
+                return Err(AppError::App(format!(
+                    "SKU template gives {} to more than one variant; name every attribute in it",
+                    sku
+                )));
+            }
+            inputs.push(CreateProductVariant {
+                product_id,
+                sku: Some(sku),
+                barcode: None,
+                attributes: Some(attributes),
+                cost_price: matrix.cost_price,
+                selling_price: matrix.selling_price,
+                inventory_account_id: None,
+                cogs_account_id: None,
+                revenue_account_id: None,
+                tracking: matrix.tracking,
+                stock_unit_id: matrix.stock_unit_id,
+                weight: None,
+                volume: None,
+            });
+        }
+
+        let skus: Vec<String> = skus.into_iter().collect();
+        let taken: Vec<String> =
+            sqlx::query_scalar("SELECT sku FROM product_variants WHERE sku = ANY($1) ORDER BY sku")
+                .bind(&skus)
+                .fetch_all(&mut *self.conn)
+                .await
+                .map_err(AppError::Database)?;
+        if !taken.is_empty() {
+            return Err(AppError::App(format!(
+                "SKUs already in use: {}",
+                taken.join(", ")
+            )));
+        }
+
+        let mut repo = Repository::<ProductVariant>::new(&mut *self.conn);
+        let mut variants = Vec::new();
+        for input in inputs {
+            variants.push(repo.create(input).await?);
+        }
+        Ok(variants)
+    }
+
+    /// Serializes variant changes of one product.
+    async fn lock_product(&mut self, product_id: Uuid) -> Result<Product> {
+        sqlx::query_as(&format!(
+            "SELECT {} FROM products WHERE id = $1 FOR UPDATE",
+            Product::COLUMNS
+        ))
+        .bind(product_id)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::App(format!("product {} not found", product_id)))
+    }
+}
+
+/// Every combination of the definitions' values, first attribute slowest.
+fn combinations(definitions: &[ProductAttribute]) -> Vec<BTreeMap<&str, &str>> {
+    let mut combinations = vec![BTreeMap::new()];
+    for definition in definitions {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combination| {
+                definition.allowed_values.iter().map(move |value| {
+                    let mut next = combination.clone();
+                    next.insert(definition.name.as_str(), value.as_str());
+                    next
+                })
+            })
+            .collect();
+    }
+    combinations
+}
+
+/// Fills `{product}` and `{<attribute>}` placeholders, matched without
+/// regard to case. Values are upper-cased with runs of other characters
+/// turned into a single `-`.
+pub fn render_sku(
+    template: &str,
+    product: &str,
+    attributes: &BTreeMap<&str, &str>,
+) -> Result<String> {
+    let mut sku = String::new();
+    let mut rest = template.trim();
+    while let Some(start) = rest.find('{') {
+        sku.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            return Err(AppError::App(format!(
+                "unclosed {{ in SKU template {}",
+                template
+            )));
+        };
+        let name = rest[start + 1..start + len].trim();
+        let value = if name.eq_ignore_ascii_case("product") {
+            product
+        } else {
+            attributes
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| *value)
+                .ok_or_else(|| AppError::App(format!("unknown SKU placeholder {{{}}}", name)))?
+        };
+        sku.push_str(&sku_part(value));
+        rest = &rest[start + len + 1..];
+    }
+    sku.push_str(rest);
+
+    if sku.is_empty() {
+        return Err(AppError::App("SKU template gives an empty SKU".into()));
+    }
+    Ok(sku)
+}
+
+fn sku_part(value: &str) -> String {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+    use sqlx::postgres::PgConnection;
+    use uuid::Uuid;
+
+    use super::{CatalogService, VariantMatrix, combinations, render_sku};
+    use crate::config::NegativeStockPolicy;
+    use crate::db::enums::{MovementType, TrackingMode};
+    use crate::db::inventory::StockService;
+    use crate::db::models::{
+        CreateProductAttribute, CreateProductVariant, CreateStockMovement, ProductAttribute,
+        ProductVariant, UpdateProductVariant,
+    };
+    use crate::db::money::{Money, Quantity};
+    use crate::db::repositories::Repository;
+    use crate::db::testing;
+
+    fn definition(name: &str, values: &[&str]) -> ProductAttribute {
+        ProductAttribute {
+            id: Uuid::new_v4(),
+            product_id: Uuid::nil(),
+            name: name.into(),
+            position: 0,
+            allowed_values: sqlx::types::Json(values.iter().map(|v| v.to_string()).collect()),
+        }
+    }
+
+    #[test]
+    fn combinations_vary_the_last_attribute_fastest() {
+        let definitions = [
+            definition("size", &["S", "M"]),
+            definition("color", &["red", "blue"]),
+        ];
+        let rendered: Vec<String> = combinations(&definitions)
+            .iter()
+            .map(|combination| render_sku("{size}/{color}", "Shirt", combination).unwrap())
+            .collect();
+        assert_eq!(rendered, ["S/RED", "S/BLUE", "M/RED", "M/BLUE"]);
+        assert_eq!(combinations(&[]), [BTreeMap::new()]);
+    }
+
+    #[test]
+    fn sku_placeholders_ignore_case() {
+        let attributes = BTreeMap::from([("Size", "x large"), ("color", "Navy/Blue")]);
+        assert_eq!(
+            render_sku(" {PRODUCT}-{size}-{ Color } ", "Polo shirt", &attributes).unwrap(),
+            "POLO-SHIRT-X-LARGE-NAVY-BLUE"
+        );
+    }
+
+    #[test]
+    fn malformed_sku_templates_are_refused() {
+        let attributes = BTreeMap::from([("size", "S")]);
+        let unclosed = render_sku("{product}-{size", "Shirt", &attributes).unwrap_err();
+        assert!(unclosed.to_string().contains("unclosed {"));
+        let unknown = render_sku("{color}", "Shirt", &attributes).unwrap_err();
+        assert!(
+            unknown
+                .to_string()
+                .contains("unknown SKU placeholder {color}")
+        );
+        assert!(render_sku("{product}", "--", &attributes).is_err());
+    }
+
+    async fn define(conn: &mut PgConnection, product_id: Uuid, name: &str, values: &[&str]) {
+        CatalogService::new(conn)
+            .define_attribute(CreateProductAttribute {
+                product_id,
+                name: name.into(),
+                position: 0,
+                allowed_values: values.iter().map(|v| v.to_string()).collect(),
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn generated_skus_must_tell_variants_apart() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let product: Uuid = sqlx::query_scalar(
+            "INSERT INTO products (company_id, name) VALUES ($1, 'Shirt') RETURNING id",
+        )
+        .bind(company)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+        define(conn, product, "size", &["S", "M"]).await;
+        define(conn, product, "color", &["red", "blue"]).await;
+
+        let mut matrix = VariantMatrix {
+            sku_template: format!("{}-{{size}}", Uuid::new_v4()),
+            cost_price: Money::from(5),
+            selling_price: Money::from(9),
+            tracking: TrackingMode::Untracked,
+            stock_unit_id: None,
+        };
+        let mut catalog = CatalogService::new(conn);
+        let duplicate = catalog.generate(product, &matrix).await.unwrap_err();
+        assert!(duplicate.to_string().contains("more than one variant"));
+
+        matrix.sku_template.push_str("-{color}");
+        assert_eq!(catalog.generate(product, &matrix).await.unwrap().len(), 4);
+        // Nothing is left to generate
+        assert!(catalog.generate(product, &matrix).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn attributes_hold_however_variants_are_written() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let product: Uuid =
+            sqlx::query_scalar("SELECT product_id FROM product_variants WHERE id = $1")
+                .bind(variant)
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+
+        // The variant would have no size
+        let mut savepoint = sqlx::Connection::begin(&mut *conn).await.unwrap();
+        let late = CatalogService::new(&mut savepoint)
+            .define_attribute(CreateProductAttribute {
+                product_id: product,
+                name: "size".into(),
+                position: 0,
+                allowed_values: vec!["S".into()],
+            })
+            .await;
+        assert!(
+            late.unwrap_err()
+                .to_string()
+                .contains("once the product has variants")
+        );
+        savepoint.rollback().await.unwrap();
+
+        let empty: Uuid = sqlx::query_scalar(
+            "INSERT INTO products (company_id, name) VALUES ($1, 'Shirt') RETURNING id",
+        )
+        .bind(company)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+        define(conn, empty, "size", &["S", "M"]).await;
+        let input = |attributes| CreateProductVariant {
+            product_id: empty,
+            sku: None,
+            barcode: None,
+            attributes: Some(attributes),
+            cost_price: Money::from(5),
+            selling_price: Money::from(9),
+            inventory_account_id: None,
+            cogs_account_id: None,
+            revenue_account_id: None,
+            tracking: TrackingMode::Untracked,
+            stock_unit_id: None,
+            weight: None,
+            volume: None,
+        };
+        for attributes in [json!({"size": "XL"}), json!({"size": "S", "color": "red"})] {
+            let mut savepoint = sqlx::Connection::begin(&mut *conn).await.unwrap();
+            let bypass = Repository::<ProductVariant>::new(&mut savepoint)
+                .create(input(attributes))
+                .await;
+            assert!(bypass.is_err());
+            savepoint.rollback().await.unwrap();
+        }
+        let created = Repository::<ProductVariant>::new(&mut *conn)
+            .create(input(json!({"size": "M"})))
+            .await
+            .unwrap();
+        let mut savepoint = sqlx::Connection::begin(&mut *conn).await.unwrap();
+        let bypass = Repository::<ProductVariant>::new(&mut savepoint)
+            .update(
+                created.id,
+                UpdateProductVariant {
+                    attributes: Some(None),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(bypass.is_err());
+        savepoint.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn tracking_only_changes_without_stock() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let warehouse = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let tracking = |tracking| UpdateProductVariant {
+            tracking: Some(tracking),
+            ..Default::default()
+        };
+
+        let movement = |quantity: i64, movement_type| CreateStockMovement {
+            company_id: company,
+            variant_id: variant,
+            warehouse_id: warehouse,
+            quantity: Quantity::from(quantity),
+            movement_type,
+            reference_type: None,
+            reference_id: None,
+            unit_cost: Some(Money::from(10)),
+            location_id: None,
+            lots: Vec::new(),
+        };
+        StockService::new(conn, NegativeStockPolicy::Forbid)
+            .post(movement(3, MovementType::In))
+            .await
+            .unwrap();
+        let err = CatalogService::new(conn)
+            .update_variant(variant, tracking(TrackingMode::Lot))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("tracking cannot change"));
+
+        StockService::new(conn, NegativeStockPolicy::Forbid)
+            .post(movement(3, MovementType::Out))
+            .await
+            .unwrap();
+        let updated = CatalogService::new(conn)
+            .update_variant(variant, tracking(TrackingMode::Lot))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.tracking, TrackingMode::Lot);
+    }
+}
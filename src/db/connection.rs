@@ -0,0 +1,261 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::{Notify, RwLock, watch};
+
+use crate::config::DatabaseConfig;
+use crate::db::migrations::MigrationReport;
+use crate::db::storage::{self, Storage};
+use crate::db::transaction::BoxFuture;
+use crate::error::{AppError, Result};
+
+/// Time between health checks while the database answers.
+const HEALTH_INTERVAL: Duration = Duration::from_secs(10);
+/// A check slower than this marks the connection degraded.
+const SLOW_PING: Duration = Duration::from_millis(500);
+const PING_TIMEOUT: Duration = Duration::from_secs(3);
+/// Consecutive failed checks before the database counts as offline.
+const OFFLINE_AFTER: u32 = 3;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Times a read is retried after losing the connection.
+const READ_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    /// Reachable but slow, or a recent check failed.
+    Degraded,
+    Offline,
+    /// Reached, but unusable until someone intervenes, e.g. the schema is
+    /// newer than the app or a migration failed. No further retries.
+    Failed,
+}
+
+impl ConnectionState {
+    pub fn label(self) -> &'static str {
+        match self {
+            ConnectionState::Connected => "Connected",
+            ConnectionState::Degraded => "Degraded",
+            ConnectionState::Offline => "Offline",
+            ConnectionState::Failed => "Failed",
+        }
+    }
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Owns the storage handle for the lifetime of the app. A background task
+/// opens the database, reopens it with backoff when that fails and checks
+/// its health periodically, publishing the result as a `ConnectionState`.
+pub struct ConnectionManager {
+    config: DatabaseConfig,
+    storage: Arc<RwLock<Option<Arc<dyn Storage>>>>,
+    state: watch::Sender<ConnectionState>,
+    /// Why the last connect or check failed, cleared once connected.
+    last_error: watch::Sender<Option<String>>,
+    /// Wakes the background task early when a read hits a dead connection.
+    check_now: Notify,
+}
+
+impl ConnectionManager {
+    pub fn new(config: DatabaseConfig) -> Arc<Self> {
+        let (state, _) = watch::channel(ConnectionState::Offline);
+        let (last_error, _) = watch::channel(None);
+        Arc::new(Self {
+            config,
+            storage: Arc::new(RwLock::new(None)),
+            state,
+            last_error,
+            check_now: Notify::new(),
+        })
+    }
+
+    pub fn config(&self) -> &DatabaseConfig {
+        &self.config
+    }
+
+    /// The shared slot holding the open storage, `None` while offline
+    /// before the first successful connection.
+    pub fn storage(&self) -> Arc<RwLock<Option<Arc<dyn Storage>>>> {
+        self.storage.clone()
+    }
+
+    pub async fn current(&self) -> Option<Arc<dyn Storage>> {
+        self.storage.read().await.clone()
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        *self.state.borrow()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<ConnectionState> {
+        self.state.subscribe()
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.borrow().clone()
+    }
+
+    /// Opens the database and applies pending migrations. On success the
+    /// storage replaces any previous one and the state becomes connected.
+    pub async fn connect(&self) -> Result<(Arc<dyn Storage>, MigrationReport)> {
+        let storage = storage::open(&self.config).await?;
+        let report = storage.migrate().await?;
+
+        *self.storage.write().await = Some(storage.clone());
+        self.last_error.send_replace(None);
+        self.state.send_replace(ConnectionState::Connected);
+        Ok((storage, report))
+    }
+
+    /// Starts the background reconnect and health check loop.
+    pub fn spawn(self: &Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move { manager.supervise().await });
+    }
+
+    /// Reconnects and health checks until the app exits, or until a connect
+    /// fails for a reason retrying cannot fix.
+    async fn supervise(&self) {
+        let mut failures = 0u32;
+        loop {
+            if self.state() == ConnectionState::Failed {
+                return;
+            }
+            let healthy = match self.current().await {
+                None => match self.connect().await {
+                    Ok(_) => true,
+                    Err(e) if is_connection_error(&e) => {
+                        self.last_error.send_replace(Some(e.to_string()));
+                        false
+                    }
+                    Err(e) => {
+                        self.fail(&e);
+                        return;
+                    }
+                },
+                Some(storage) => self.check(storage.as_ref()).await,
+            };
+
+            if healthy {
+                failures = 0;
+            } else {
+                failures += 1;
+                let state = if failures >= OFFLINE_AFTER || self.current().await.is_none() {
+                    ConnectionState::Offline
+                } else {
+                    ConnectionState::Degraded
+                };
+                self.state.send_replace(state);
+            }
+
+            let delay = if failures == 0 {
+                HEALTH_INTERVAL
+            } else {
+                backoff(failures)
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.check_now.notified() => {}
+            }
+        }
+    }
+
+    /// Publishes an error retrying cannot fix; readers waiting for the
+    /// database give up with it.
+    pub fn fail(&self, err: &AppError) {
+        self.last_error.send_replace(Some(err.to_string()));
+        self.state.send_replace(ConnectionState::Failed);
+    }
+
+    /// Pings the storage and records the outcome; `false` when it failed.
+    async fn check(&self, storage: &dyn Storage) -> bool {
+        let started = Instant::now();
+        let state = match tokio::time::timeout(PING_TIMEOUT, storage.ping()).await {
+            Ok(Ok(())) if started.elapsed() <= SLOW_PING => ConnectionState::Connected,
+            Ok(Ok(())) => ConnectionState::Degraded,
+            Ok(Err(e)) => {
+                self.last_error.send_replace(Some(e.to_string()));
+                return false;
+            }
+            Err(_) => {
+                self.last_error
+                    .send_replace(Some("Health check timed out".to_string()));
+                return false;
+            }
+        };
+        self.state.send_replace(state);
+        true
+    }
+
+    /// Runs an idempotent read. While the database is offline the read waits
+    /// for it to come back; if it fails on a lost connection it is queued
+    /// again until the next reconnect. Other errors are returned as is.
+    pub async fn read<T, F>(&self, mut read: F) -> Result<T>
+    where
+        F: FnMut(Arc<dyn Storage>) -> BoxFuture<'static, Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let storage = self.wait_online().await?;
+            match read(storage).await {
+                Err(err) if is_connection_error(&err) && attempt < READ_ATTEMPTS => {
+                    if self.state() == ConnectionState::Connected {
+                        self.state.send_replace(ConnectionState::Degraded);
+                    }
+                    self.check_now.notify_one();
+                    tokio::time::sleep(backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn wait_online(&self) -> Result<Arc<dyn Storage>> {
+        let mut state = self.subscribe();
+        loop {
+            let current = *state.borrow_and_update();
+            if current == ConnectionState::Failed {
+                let reason = self.last_error().unwrap_or_default();
+                return Err(AppError::App(format!("Database unavailable: {}", reason)));
+            }
+            if current != ConnectionState::Offline
+                && let Some(storage) = self.current().await
+            {
+                return Ok(storage);
+            }
+            // The sender lives as long as `self`, so this only waits
+            let _ = state.changed().await;
+        }
+    }
+}
+
+/// 1s, 2s, 4s, ... capped at `MAX_BACKOFF`.
+fn backoff(failures: u32) -> Duration {
+    Duration::from_secs(1 << failures.saturating_sub(1).min(5)).min(MAX_BACKOFF)
+}
+
+/// Errors that mean the database could not be reached rather than that
+/// the query itself was wrong.
+pub fn is_connection_error(err: &AppError) -> bool {
+    match err {
+        AppError::Database(
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed,
+        ) => true,
+        // connection_exception class, admin_shutdown, crash_shutdown, cannot_connect_now
+        AppError::Database(sqlx::Error::Database(db_err)) => db_err.code().is_some_and(|code| {
+            code.starts_with("08") || matches!(code.as_ref(), "57P01" | "57P02" | "57P03")
+        }),
+        _ => false,
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::money::Quantity;
+use crate::db::repositories::SqlValue;
+use crate::error::AppError;
+
+/// Declares a Rust enum for a Postgres enum type. Each variant maps to the
+/// label used in the database, which is also its serde and display form.
+macro_rules! db_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident as $type_name:literal {
+            $($variant:ident => $label:literal,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+        #[sqlx(type_name = $type_name)]
+        pub enum $name {
+            $(
+                #[sqlx(rename = $label)]
+                #[serde(rename = $label)]
+                $variant,
+            )+
+        }
+
+        impl $name {
+            pub const ALL: &'static [Self] = &[$(Self::$variant),+];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $label,)+
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = AppError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($label => Ok(Self::$variant),)+
+                    _ => Err(AppError::App(format!("unknown {}: {}", $type_name, s))),
+                }
+            }
+        }
+
+        impl From<$name> for SqlValue {
+            fn from(value: $name) -> Self {
+                SqlValue::Enum($type_name, Some(value.as_str().to_string()))
+            }
+        }
+
+        impl From<Option<$name>> for SqlValue {
+            fn from(value: Option<$name>) -> Self {
+                SqlValue::Enum($type_name, value.map(|v| v.as_str().to_string()))
+            }
+        }
+    };
+}
+
+db_enum! {
+    pub enum UserRole as "user_role" {
+        Admin => "admin",
+        Accountant => "accountant",
+        Inventory => "inventory",
+        Sales => "sales",
+        Purchasing => "purchasing",
+    }
+}
+
+db_enum! {
+    /// Lifecycle of sales and purchase orders.
+    pub enum OrderStatus as "order_status" {
+        Draft => "DRAFT",
+        Pending => "PENDING",
+        Confirmed => "CONFIRMED",
+        Cancelled => "CANCELLED",
+        Completed => "COMPLETED",
+    }
+}
+
+db_enum! {
+    pub enum AccountType as "account_type" {
+        Asset => "ASSET",
+        Liability => "LIABILITY",
+        Equity => "EQUITY",
+        Income => "INCOME",
+        Expense => "EXPENSE",
+    }
+}
+
+db_enum! {
+    pub enum JournalStatus as "journal_status" {
+        Draft => "DRAFT",
+        Posted => "POSTED",
+        Cancelled => "CANCELLED",
+    }
+}
+
+db_enum! {
+    pub enum PaymentStatus as "payment_status" {
+        Unpaid => "UNPAID",
+        PartiallyPaid => "PARTIALLY_PAID",
+        Paid => "PAID",
+    }
+}
+
+db_enum! {
+    pub enum TransactionType as "transaction_type" {
+        PaymentReceived => "PAYMENT_RECEIVED",
+        PaymentMade => "PAYMENT_MADE",
+        RefundGiven => "REFUND_GIVEN",
+        RefundReceived => "REFUND_RECEIVED",
+    }
+}
+
+db_enum! {
+    pub enum PaymentMethod as "payment_method" {
+        Cash => "CASH",
+        BankTransfer => "BANK_TRANSFER",
+        Check => "CHECK",
+        CreditCard => "CREDIT_CARD",
+    }
+}
+
+db_enum! {
+    pub enum PartnerType as "partner_type_enum" {
+        Customer => "customer",
+        Vendor => "vendor",
+        Both => "both",
+    }
+}
+
+db_enum! {
+    pub enum MovementType as "movement_type_enum" {
+        In => "in",
+        Out => "out",
+        Adjustment => "adjustment",
+    }
+}
+
+db_enum! {
+    /// How amounts are rounded to a currency's minor unit.
+    pub enum RoundingMode as "rounding_mode" {
+        HalfUp => "HALF_UP",
+        HalfEven => "HALF_EVEN",
+        Down => "DOWN",
+        Up => "UP",
+    }
+}
+
+db_enum! {
+    /// How a company values stock leaving its warehouses.
+    pub enum CostingMethod as "costing_method" {
+        Fifo => "FIFO",
+        WeightedAverage => "WEIGHTED_AVERAGE",
+    }
+}
+
+db_enum! {
+    /// Lifecycle of a transfer between warehouses.
+    pub enum TransferStatus as "transfer_status" {
+        Draft => "DRAFT",
+        Shipped => "SHIPPED",
+        Received => "RECEIVED",
+        Cancelled => "CANCELLED",
+    }
+}
+
+db_enum! {
+    /// Lifecycle of a physical stock count.
+    pub enum CountStatus as "count_status" {
+        Open => "OPEN",
+        Submitted => "SUBMITTED",
+        Posted => "POSTED",
+        Cancelled => "CANCELLED",
+    }
+}
+
+db_enum! {
+    /// Whether a variant's stock is tracked by lot or by serial number.
+    pub enum TrackingMode as "tracking_mode" {
+        Untracked => "NONE",
+        Lot => "LOT",
+        Serial => "SERIAL",
+    }
+}
+
+db_enum! {
+    /// Level of a storage location, outermost first.
+    pub enum LocationKind as "location_kind" {
+        Zone => "ZONE",
+        Aisle => "AISLE",
+        Shelf => "SHELF",
+        Bin => "BIN",
+    }
+}
+
+db_enum! {
+    /// What a bill of materials is for. A kit is sold as a bundle and its
+    /// components ship in its place; a manufactured parent is assembled
+    /// ahead and stocked itself.
+    pub enum BomType as "bom_type" {
+        Kit => "KIT",
+        Manufacture => "MANUFACTURE",
+    }
+}
+
+db_enum! {
+    /// Lifecycle of a landed cost document.
+    pub enum LandedCostStatus as "landed_cost_status" {
+        Draft => "DRAFT",
+        Posted => "POSTED",
+        Cancelled => "CANCELLED",
+    }
+}
+
+db_enum! {
+    /// What a landed cost charge is shared out by across received lines.
+    pub enum AllocationMethod as "allocation_method" {
+        Quantity => "QUANTITY",
+        Value => "VALUE",
+        Weight => "WEIGHT",
+        Volume => "VOLUME",
+    }
+}
+
+impl OrderStatus {
+    /// Orders in these states can no longer be changed.
+    pub fn is_closed(self) -> bool {
+        matches!(self, Self::Cancelled | Self::Completed)
+    }
+}
+
+impl MovementType {
+    /// Effect of a movement on stock on hand. `in` and `out` quantities are
+    /// positive; an adjustment carries its own sign.
+    pub fn signed(self, quantity: Quantity) -> Quantity {
+        match self {
+            Self::In | Self::Adjustment => quantity,
+            Self::Out => -quantity,
+        }
+    }
+}
+
+impl LocationKind {
+    /// Whether a location of this kind may sit inside `parent`.
+    pub fn fits_in(self, parent: LocationKind) -> bool {
+        Self::ALL.iter().position(|k| *k == parent) < Self::ALL.iter().position(|k| *k == self)
+    }
+}
+
+impl PartnerType {
+    pub fn is_customer(self) -> bool {
+        matches!(self, Self::Customer | Self::Both)
+    }
+
+    pub fn is_vendor(self) -> bool {
+        matches!(self, Self::Vendor | Self::Both)
+    }
+}
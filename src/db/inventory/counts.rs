@@ -0,0 +1,435 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::config::NegativeStockPolicy;
+use crate::db::enums::{CountStatus, MovementType, UserRole};
+use crate::db::inventory::{LocationService, LotService, StockService};
+use crate::db::models::{
+    CreateStockCount, CreateStockMovement, ProductVariant, StockCount, StockCountLine,
+};
+use crate::db::money::{Money, Quantity};
+use crate::db::repositories::{Entity, Repository};
+use crate::error::{AppError, Result};
+
+/// `reference_type` of the adjustments posted for a count.
+pub const REFERENCE_TYPE: &str = "stock_count";
+
+/// Average cost of a ledger row `l` for variant `v`, falling back to the
+/// variant's cost price when nothing is on hand.
+const UNIT_COST: &str =
+    "COALESCE(CASE WHEN l.quantity > 0 THEN round(l.value / l.quantity, 4) END, v.cost_price)";
+
+/// Quantity of variant `v` in bin `loc` of warehouse `w`, or outside bins
+/// when `loc.id` is null.
+const EXPECTED: &str = "CASE WHEN loc.id IS NULL THEN COALESCE(l.quantity, 0) - COALESCE(( \
+         SELECT SUM(b.quantity) FROM stock_bin_balances b \
+         JOIN storage_locations s ON s.id = b.location_id \
+         WHERE b.variant_id = v.id AND s.warehouse_id = w.id), 0) \
+     ELSE COALESCE(( \
+         SELECT b.quantity FROM stock_bin_balances b \
+         WHERE b.location_id = loc.id AND b.variant_id = v.id), 0) END";
+
+/// A counted line that differs from what the ledger expected.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CountVariance {
+    pub line_id: Uuid,
+    pub variant_id: Uuid,
+    pub location_id: Option<Uuid>,
+    pub expected: Quantity,
+    pub counted: Quantity,
+    /// Counted minus expected; negative for shrinkage.
+    pub difference: Quantity,
+    pub unit_cost: Money,
+    pub value: Money,
+}
+
+/// Physical count sessions for one warehouse: open for counting, submitted
+/// for review, then approved and posted as adjustments. Postgres only; run
+/// inside a transaction.
+pub struct CountService<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> CountService<'c> {
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Opens a count and freezes the expected quantity and cost of every
+    /// ledger row in the warehouse, or only of `variants` for a cycle count.
+    /// Each bin holding a variant is counted on its own line, next to a
+    /// line for its stock outside bins.
+    pub async fn start(
+        &mut self,
+        input: CreateStockCount,
+        variants: Option<Vec<Uuid>>,
+    ) -> Result<StockCount> {
+        let warehouse_id = input.warehouse_id;
+        let count = Repository::<StockCount>::new(&mut *self.conn)
+            .create(input)
+            .await?;
+
+        sqlx::query(&format!(
+            "INSERT INTO stock_count_lines \
+             (count_id, variant_id, location_id, expected, unit_cost) \
+             SELECT $1, v.id, loc.id, {EXPECTED}, {UNIT_COST} \
+             FROM product_variants v \
+             JOIN warehouses w ON w.id = $2 \
+             LEFT JOIN stock_ledger l ON l.variant_id = v.id AND l.warehouse_id = w.id \
+             CROSS JOIN LATERAL ( \
+                 SELECT NULL::uuid AS id \
+                 UNION ALL \
+                 SELECT b.location_id FROM stock_bin_balances b \
+                 JOIN storage_locations s ON s.id = b.location_id \
+                 WHERE b.variant_id = v.id AND s.warehouse_id = w.id AND b.quantity > 0 \
+             ) loc \
+             WHERE CASE WHEN $3::uuid[] IS NULL THEN l.variant_id IS NOT NULL \
+             ELSE v.id = ANY($3) END"
+        ))
+        .bind(count.id)
+        .bind(warehouse_id)
+        .bind(variants)
+        .execute(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(count)
+    }
+
+    /// Sets the counted quantity of a variant in a bin, or outside bins
+    /// when `location_id` is `None`.
+    pub async fn record(
+        &mut self,
+        count_id: Uuid,
+        variant_id: Uuid,
+        location_id: Option<Uuid>,
+        counted: Quantity,
+    ) -> Result<StockCountLine> {
+        let count = self.lock(count_id, CountStatus::Open).await?;
+        self.upsert(&count, variant_id, location_id, counted, false)
+            .await
+    }
+
+    /// Adds `quantity` to the variant whose barcode or SKU was scanned, in
+    /// the bin being counted.
+    pub async fn scan(
+        &mut self,
+        count_id: Uuid,
+        code: &str,
+        location_id: Option<Uuid>,
+        quantity: Quantity,
+    ) -> Result<StockCountLine> {
+        let count = self.lock(count_id, CountStatus::Open).await?;
+        let variant: ProductVariant = Repository::<ProductVariant>::new(&mut *self.conn)
+            .find_by_code(count.company_id, code)
+            .await?
+            .ok_or_else(|| AppError::App(format!("no product with barcode or SKU {}", code)))?;
+        self.upsert(&count, variant.id, location_id, quantity, true)
+            .await
+    }
+
+    /// Counted lines that differ from the frozen quantity, largest value first.
+    pub async fn variances(&mut self, count_id: Uuid) -> Result<Vec<CountVariance>> {
+        sqlx::query_as(
+            "SELECT id AS line_id, variant_id, location_id, expected, counted, \
+             counted - expected AS difference, unit_cost, \
+             round((counted - expected) * unit_cost, 4) AS value \
+             FROM stock_count_lines \
+             WHERE count_id = $1 AND counted IS NOT NULL AND counted <> expected \
+             ORDER BY abs((counted - expected) * unit_cost) DESC, id",
+        )
+        .bind(count_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Hands a finished count over for approval.
+    pub async fn submit(&mut self, count_id: Uuid) -> Result<StockCount> {
+        self.lock(count_id, CountStatus::Open).await?;
+        self.set_status(count_id, CountStatus::Submitted, "submitted_at = now()")
+            .await
+    }
+
+    /// Sends a submitted count back for recounting.
+    pub async fn reopen(&mut self, count_id: Uuid) -> Result<StockCount> {
+        self.lock(count_id, CountStatus::Submitted).await?;
+        self.set_status(count_id, CountStatus::Open, "submitted_at = NULL")
+            .await
+    }
+
+    /// Posts every variance as an adjustment. Only admin and inventory
+    /// users may approve.
+    pub async fn approve(&mut self, count_id: Uuid, approver_id: Uuid) -> Result<StockCount> {
+        let count = self.lock(count_id, CountStatus::Submitted).await?;
+
+        let role: Option<UserRole> = sqlx::query_scalar("SELECT role FROM users WHERE id = $1")
+            .bind(approver_id)
+            .fetch_optional(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+        if !matches!(role, Some(UserRole::Admin | UserRole::Inventory)) {
+            return Err(AppError::App(
+                "only admin and inventory users can approve counts".into(),
+            ));
+        }
+
+        // The count is the truth now, so it may take stock below zero if
+        // the ledger moved since the count was frozen
+        let variances = self.variances(count_id).await?;
+        for variance in variances {
+            // Shrinkage comes out of the soonest expiring lots
+            let mut lot_service = LotService::new(&mut *self.conn);
+            let lots = if variance.difference.is_negative() {
+                lot_service
+                    .pick(
+                        variance.variant_id,
+                        count.warehouse_id,
+                        -variance.difference,
+                    )
+                    .await?
+            } else {
+                lot_service
+                    .found(variance.variant_id, count.warehouse_id, variance.difference)
+                    .await?
+            };
+            StockService::new(&mut *self.conn, NegativeStockPolicy::Allow)
+                .post(CreateStockMovement {
+                    company_id: count.company_id,
+                    variant_id: variance.variant_id,
+                    warehouse_id: count.warehouse_id,
+                    quantity: variance.difference,
+                    movement_type: MovementType::Adjustment,
+                    reference_type: Some(REFERENCE_TYPE.into()),
+                    reference_id: Some(count_id),
+                    unit_cost: Some(variance.unit_cost),
+                    location_id: variance.location_id,
+                    lots,
+                })
+                .await?;
+        }
+
+        sqlx::query_as(&format!(
+            "UPDATE stock_counts SET status = 'POSTED', approved_by = $2, posted_at = now() \
+             WHERE id = $1 RETURNING {}",
+            StockCount::COLUMNS
+        ))
+        .bind(count_id)
+        .bind(approver_id)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn lock(&mut self, count_id: Uuid, expected: CountStatus) -> Result<StockCount> {
+        let count: StockCount = sqlx::query_as(&format!(
+            "SELECT {} FROM stock_counts WHERE id = $1 FOR UPDATE",
+            StockCount::COLUMNS
+        ))
+        .bind(count_id)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::App(format!("stock count {} not found", count_id)))?;
+
+        if count.status != expected {
+            return Err(AppError::App(format!(
+                "stock count is {}, expected {}",
+                count.status, expected
+            )));
+        }
+        Ok(count)
+    }
+
+    /// Writes a counted quantity, or adds to it when `add` is set. A variant
+    /// or bin the count did not freeze is added with the ledger quantity as
+    /// of now.
+    async fn upsert(
+        &mut self,
+        count: &StockCount,
+        variant_id: Uuid,
+        location_id: Option<Uuid>,
+        quantity: Quantity,
+        add: bool,
+    ) -> Result<StockCountLine> {
+        if let Some(location_id) = location_id {
+            LocationService::new(&mut *self.conn)
+                .bin(location_id, count.warehouse_id)
+                .await?;
+        }
+        let counted = if add {
+            "COALESCE(stock_count_lines.counted, 0) + EXCLUDED.counted"
+        } else {
+            "EXCLUDED.counted"
+        };
+        sqlx::query_as(&format!(
+            "INSERT INTO stock_count_lines \
+             (count_id, variant_id, location_id, expected, counted, unit_cost) \
+             SELECT $1, v.id, loc.id, {EXPECTED}, $3, {UNIT_COST} \
+             FROM product_variants v \
+             JOIN warehouses w ON w.id = $4 \
+             LEFT JOIN stock_ledger l ON l.variant_id = v.id AND l.warehouse_id = w.id \
+             CROSS JOIN (SELECT $5::uuid AS id) loc \
+             WHERE v.id = $2 \
+             ON CONFLICT (count_id, variant_id, \
+                 COALESCE(location_id, '00000000-0000-0000-0000-000000000000'::uuid)) \
+             DO UPDATE SET counted = {counted} \
+             RETURNING {}",
+            StockCountLine::COLUMNS
+        ))
+        .bind(count.id)
+        .bind(variant_id)
+        .bind(quantity)
+        .bind(count.warehouse_id)
+        .bind(location_id)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::App(format!("product variant {} not found", variant_id)))
+    }
+
+    async fn set_status(
+        &mut self,
+        count_id: Uuid,
+        status: CountStatus,
+        set: &str,
+    ) -> Result<StockCount> {
+        sqlx::query_as(&format!(
+            "UPDATE stock_counts SET status = $2, {} WHERE id = $1 RETURNING {}",
+            set,
+            StockCount::COLUMNS
+        ))
+        .bind(count_id)
+        .bind(status)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{CountService, REFERENCE_TYPE};
+    use crate::config::NegativeStockPolicy;
+    use crate::db::enums::{CountStatus, MovementType, TrackingMode, UserRole};
+    use crate::db::inventory::StockService;
+    use crate::db::models::{CreateStockCount, CreateStockMovement};
+    use crate::db::money::{Money, Quantity};
+    use crate::db::testing;
+
+    fn receipt(
+        company_id: Uuid,
+        warehouse_id: Uuid,
+        variant_id: Uuid,
+        quantity: i64,
+    ) -> CreateStockMovement {
+        CreateStockMovement {
+            company_id,
+            variant_id,
+            warehouse_id,
+            quantity: Quantity::from(quantity),
+            movement_type: MovementType::In,
+            reference_type: None,
+            reference_id: None,
+            unit_cost: Some(Money::from(10)),
+            location_id: None,
+            lots: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn approved_counts_post_their_variances() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let warehouse = testing::warehouse(conn, company).await;
+        let short = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let sku = Uuid::new_v4().to_string();
+        let over = testing::variant(conn, company, TrackingMode::Untracked, Some(&sku), None).await;
+        StockService::new(conn, NegativeStockPolicy::Forbid)
+            .post_all([
+                receipt(company, warehouse, short, 10),
+                receipt(company, warehouse, over, 5),
+            ])
+            .await
+            .unwrap();
+        let counter = testing::user(conn, UserRole::Sales).await;
+        let approver = testing::user(conn, UserRole::Inventory).await;
+
+        let mut counts = CountService::new(conn);
+        let count = counts
+            .start(
+                CreateStockCount {
+                    company_id: company,
+                    warehouse_id: warehouse,
+                    notes: None,
+                    created_by: counter,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        counts
+            .record(count.id, short, None, Quantity::from(8))
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            counts
+                .scan(count.id, &sku, None, Quantity::from(3))
+                .await
+                .unwrap();
+        }
+
+        let variances = counts.variances(count.id).await.unwrap();
+        let found: Vec<_> = variances
+            .iter()
+            .map(|v| (v.variant_id, v.difference, v.value))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (short, Quantity::from(-2), Money::from(-20)),
+                (over, Quantity::from(1), Money::from(10)),
+            ]
+        );
+
+        counts.submit(count.id).await.unwrap();
+        let refused = counts.approve(count.id, counter).await;
+        assert!(
+            refused
+                .unwrap_err()
+                .to_string()
+                .contains("only admin and inventory")
+        );
+        let posted = counts.approve(count.id, approver).await.unwrap();
+        assert_eq!(posted.status, CountStatus::Posted);
+        let late = counts
+            .record(count.id, short, None, Quantity::from(1))
+            .await;
+        assert!(late.is_err());
+
+        let adjustments: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM stock_movements WHERE reference_type = $1 AND reference_id = $2",
+        )
+        .bind(REFERENCE_TYPE)
+        .bind(count.id)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+        assert_eq!(adjustments, 2);
+        let mut stock = StockService::new(conn, NegativeStockPolicy::Forbid);
+        assert_eq!(
+            stock.on_hand(short, warehouse).await.unwrap(),
+            Quantity::from(8)
+        );
+        assert_eq!(
+            stock.on_hand(over, warehouse).await.unwrap(),
+            Quantity::from(6)
+        );
+    }
+}
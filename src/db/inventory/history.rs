@@ -0,0 +1,379 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::db::enums::{MovementType, RoundingMode};
+use crate::db::models::StockLedger;
+use crate::db::money::{Money, Quantity};
+use crate::error::{AppError, Result};
+
+/// Quantity and book value of a variant in a warehouse at some moment.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct StockAsOf {
+    pub variant_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub quantity: Quantity,
+    pub value: Money,
+}
+
+/// A ledger row that did not match its movements, and what it was reset to.
+#[derive(Debug, Clone, Serialize)]
+pub struct LedgerDrift {
+    pub variant_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub ledger_quantity: Quantity,
+    pub ledger_value: Money,
+    pub quantity: Quantity,
+    pub value: Money,
+}
+
+#[derive(FromRow)]
+struct ReplayRow {
+    variant_id: Uuid,
+    warehouse_id: Uuid,
+    movement_type: MovementType,
+    quantity: Quantity,
+    unit_cost: Option<Money>,
+    cost_price: Money,
+    /// What a receipt added, from its cost layers.
+    received_cost: Option<Money>,
+    /// What an issue was charged, from its cost layer usages.
+    issued_cost: Option<Money>,
+}
+
+/// Stock and value as they were at a past moment, recomputed from
+/// `stock_movements` in posting order with the same arithmetic
+/// `ValuationService` applies when posting. Postgres only.
+pub struct HistoryService<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> HistoryService<'c> {
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Quantity and value per variant and warehouse after every movement
+    /// dated up to `at`, leaving out rows that were empty.
+    pub async fn as_of(
+        &mut self,
+        company_id: Uuid,
+        at: DateTime<Utc>,
+        warehouse_id: Option<Uuid>,
+    ) -> Result<Vec<StockAsOf>> {
+        let rows = self
+            .replay(company_id, Some(at), warehouse_id, None)
+            .await?;
+        Ok(rows
+            .into_values()
+            .filter(|row| !row.quantity.is_zero() || !row.value.is_zero())
+            .collect())
+    }
+
+    /// Recomputes the company's `stock_ledger` quantities and values from
+    /// its movements and returns the rows that differed.
+    ///
+    /// Only the ledger is rebuilt: reservations, cost layers and what
+    /// remains on them, lot balances and bin balances are taken as they
+    /// are. Holds back stock postings into the company's warehouses until
+    /// the caller's transaction ends; other companies carry on.
+    pub async fn rebuild_ledger(&mut self, company_id: Uuid) -> Result<Vec<LedgerDrift>> {
+        // Posting a movement references its warehouse, so locking the
+        // warehouses waits out postings in flight and keeps new ledger rows
+        // from appearing while the existing ones are compared
+        sqlx::query("SELECT id FROM warehouses WHERE company_id = $1 FOR UPDATE")
+            .bind(company_id)
+            .execute(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+        let ledger: Vec<StockLedger> = sqlx::query_as(
+            "SELECT l.variant_id, l.warehouse_id, l.quantity, l.value, l.reserved \
+             FROM stock_ledger l JOIN warehouses w ON w.id = l.warehouse_id \
+             WHERE w.company_id = $1 FOR UPDATE OF l",
+        )
+        .bind(company_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+        let mut rebuilt = self.replay(company_id, None, None, None).await?;
+
+        let mut drifts = Vec::new();
+        for row in &ledger {
+            let key = (row.variant_id, row.warehouse_id);
+            let expected = rebuilt.remove(&key).unwrap_or(StockAsOf {
+                variant_id: row.variant_id,
+                warehouse_id: row.warehouse_id,
+                ..StockAsOf::default()
+            });
+            if expected.quantity != row.quantity || expected.value != row.value {
+                drifts.push(drift(row.quantity, row.value, expected));
+            }
+        }
+        // Movements whose ledger row is missing altogether
+        drifts.extend(
+            rebuilt
+                .into_values()
+                .filter(|row| !row.quantity.is_zero() || !row.value.is_zero())
+                .map(|row| drift(Quantity::ZERO, Money::ZERO, row)),
+        );
+
+        for drift in &drifts {
+            sqlx::query(
+                "INSERT INTO stock_ledger (variant_id, warehouse_id, quantity, value) \
+                 VALUES ($1, $2, $3, $4) ON CONFLICT (variant_id, warehouse_id) \
+                 DO UPDATE SET quantity = EXCLUDED.quantity, value = EXCLUDED.value",
+            )
+            .bind(drift.variant_id)
+            .bind(drift.warehouse_id)
+            .bind(drift.quantity)
+            .bind(drift.value)
+            .execute(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+        }
+        drifts.sort_by_key(|drift| (drift.variant_id, drift.warehouse_id));
+        Ok(drifts)
+    }
+
+    /// Book value of one ledger row as its movements give it. Used after
+    /// movement costs are revised; the caller holds the ledger row lock.
+    pub(crate) async fn replayed_value(
+        &mut self,
+        company_id: Uuid,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+    ) -> Result<Money> {
+        let rows = self
+            .replay(company_id, None, Some(warehouse_id), Some(variant_id))
+            .await?;
+        Ok(rows
+            .get(&(variant_id, warehouse_id))
+            .map_or(Money::ZERO, |row| row.value))
+    }
+
+    /// Replays the full history in posting order, as the ledger was built.
+    /// A cut-off by date replays in date order instead, so no movement is
+    /// applied without those dated before it; movements of one transaction
+    /// share a date and keep their posting order.
+    async fn replay(
+        &mut self,
+        company_id: Uuid,
+        at: Option<DateTime<Utc>>,
+        warehouse_id: Option<Uuid>,
+        variant_id: Option<Uuid>,
+    ) -> Result<BTreeMap<(Uuid, Uuid), StockAsOf>> {
+        let movements: Vec<ReplayRow> = sqlx::query_as(
+            "SELECT m.variant_id, m.warehouse_id, m.movement_type, m.quantity, m.unit_cost, \
+             v.cost_price, \
+             (SELECT SUM(c.quantity * c.unit_cost) FROM cost_layers c \
+              WHERE c.movement_id = m.id) AS received_cost, \
+             (SELECT SUM(u.quantity * u.unit_cost) FROM cost_layer_usages u \
+              WHERE u.movement_id = m.id) AS issued_cost \
+             FROM stock_movements m JOIN product_variants v ON v.id = m.variant_id \
+             WHERE m.company_id = $1 \
+             AND ($2::timestamptz IS NULL OR m.movement_date <= $2) \
+             AND ($3::uuid IS NULL OR m.warehouse_id = $3) \
+             AND ($4::uuid IS NULL OR m.variant_id = $4) \
+             ORDER BY m.variant_id, m.warehouse_id, \
+             CASE WHEN $2::timestamptz IS NOT NULL THEN m.movement_date END, m.sequence",
+        )
+        .bind(company_id)
+        .bind(at)
+        .bind(warehouse_id)
+        .bind(variant_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        let mut rows = BTreeMap::new();
+        for movement in movements {
+            let row = rows
+                .entry((movement.variant_id, movement.warehouse_id))
+                .or_insert_with(|| StockAsOf {
+                    variant_id: movement.variant_id,
+                    warehouse_id: movement.warehouse_id,
+                    ..StockAsOf::default()
+                });
+            apply(row, &movement);
+        }
+        Ok(rows)
+    }
+}
+
+/// Mirrors `ValuationService::receive` and `issue`.
+fn apply(row: &mut StockAsOf, movement: &ReplayRow) {
+    let delta = movement.movement_type.signed(movement.quantity);
+    let after = row.quantity + delta;
+    let unit_cost = movement.unit_cost.unwrap_or_else(|| {
+        if row.quantity > Quantity::ZERO
+            && let Some(average) = row.value / row.quantity
+        {
+            round(average)
+        } else {
+            movement.cost_price
+        }
+    });
+
+    row.value = if !delta.is_negative() {
+        if row.quantity.is_negative() {
+            round(after * unit_cost)
+        } else {
+            // Landed costs may have split a receipt's layer to carry a
+            // rounding residue, so its layers hold its exact value
+            let cost = movement.received_cost.unwrap_or_else(|| delta * unit_cost);
+            row.value + round(cost)
+        }
+    } else if after.is_zero() {
+        Money::ZERO
+    } else {
+        // Movements from before valuation have no usages; charge them
+        // what they record
+        let cost = movement.issued_cost.unwrap_or_else(|| -delta * unit_cost);
+        row.value - round(cost)
+    };
+    row.quantity = after;
+}
+
+fn drift(ledger_quantity: Quantity, ledger_value: Money, expected: StockAsOf) -> LedgerDrift {
+    LedgerDrift {
+        variant_id: expected.variant_id,
+        warehouse_id: expected.warehouse_id,
+        ledger_quantity,
+        ledger_value,
+        quantity: expected.quantity,
+        value: expected.value,
+    }
+}
+
+fn round(amount: Money) -> Money {
+    amount.round(Money::SCALE, RoundingMode::HalfUp)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::HistoryService;
+    use crate::config::NegativeStockPolicy;
+    use crate::db::enums::{MovementType, TrackingMode};
+    use crate::db::inventory::StockService;
+    use crate::db::models::CreateStockMovement;
+    use crate::db::money::{Money, Quantity};
+    use crate::db::testing;
+
+    fn movement(
+        company_id: uuid::Uuid,
+        warehouse_id: uuid::Uuid,
+        variant_id: uuid::Uuid,
+        movement_type: MovementType,
+        quantity: i64,
+        unit_cost: Option<i64>,
+    ) -> CreateStockMovement {
+        CreateStockMovement {
+            company_id,
+            variant_id,
+            warehouse_id,
+            quantity: Quantity::from(quantity),
+            movement_type,
+            reference_type: None,
+            reference_id: None,
+            unit_cost: unit_cost.map(Money::from),
+            location_id: None,
+            lots: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn rebuilding_resets_only_rows_that_drifted() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let warehouse = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let mut stock = StockService::new(conn, NegativeStockPolicy::Forbid);
+        let receipt = movement(company, warehouse, variant, MovementType::In, 4, Some(10));
+        stock.post(receipt).await.unwrap();
+        let issue = movement(company, warehouse, variant, MovementType::Out, 1, None);
+        stock.post(issue).await.unwrap();
+
+        assert!(
+            HistoryService::new(conn)
+                .rebuild_ledger(company)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        sqlx::query("UPDATE stock_ledger SET quantity = 99 WHERE variant_id = $1")
+            .bind(variant)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        let drifts = HistoryService::new(conn)
+            .rebuild_ledger(company)
+            .await
+            .unwrap();
+        assert_eq!(drifts.len(), 1);
+        assert_eq!(drifts[0].ledger_quantity, Quantity::from(99));
+        assert_eq!(drifts[0].quantity, Quantity::from(3));
+        assert_eq!(drifts[0].value, Money::from(30));
+        let quantity: Quantity =
+            sqlx::query_scalar("SELECT quantity FROM stock_ledger WHERE variant_id = $1")
+                .bind(variant)
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+        assert_eq!(quantity, Quantity::from(3));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn stock_as_of_counts_movements_by_date() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let warehouse = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let mut stock = StockService::new(conn, NegativeStockPolicy::Forbid);
+        let first = movement(company, warehouse, variant, MovementType::In, 2, Some(10));
+        let first = stock.post(first).await.unwrap().movement.id;
+        let second = movement(company, warehouse, variant, MovementType::In, 3, Some(20));
+        let second = stock.post(second).await.unwrap().movement.id;
+
+        // The later posting is dated before the earlier one
+        let now = Utc::now();
+        for (id, date) in [
+            (first, now - Duration::hours(1)),
+            (second, now - Duration::hours(3)),
+        ] {
+            sqlx::query("UPDATE stock_movements SET movement_date = $2 WHERE id = $1")
+                .bind(id)
+                .bind(date)
+                .execute(&mut *conn)
+                .await
+                .unwrap();
+        }
+
+        let mut history = HistoryService::new(conn);
+        let before = history
+            .as_of(company, now - Duration::hours(4), None)
+            .await
+            .unwrap();
+        assert!(before.is_empty());
+        let between = history
+            .as_of(company, now - Duration::hours(2), None)
+            .await
+            .unwrap();
+        assert_eq!(between.len(), 1);
+        assert_eq!(between[0].quantity, Quantity::from(3));
+        assert_eq!(between[0].value, Money::from(60));
+        let after = history.as_of(company, now, Some(warehouse)).await.unwrap();
+        assert_eq!(after[0].quantity, Quantity::from(5));
+        assert_eq!(after[0].value, Money::from(80));
+    }
+}
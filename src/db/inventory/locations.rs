@@ -0,0 +1,641 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::config::NegativeStockPolicy;
+use crate::db::enums::LocationKind;
+use crate::db::inventory::StockService;
+use crate::db::models::{
+    CreateStockBinMove, CreateStorageLocation, StockBinMove, StockMovement, StorageLocation,
+};
+use crate::db::money::Quantity;
+use crate::db::repositories::Repository;
+use crate::error::{AppError, Result};
+
+/// Locations of the warehouse bound to $1 with their full path, e.g.
+/// `A/03/2/B`.
+const PATHS: &str = "WITH RECURSIVE paths AS ( \
+     SELECT id, code AS path, 0 AS depth FROM storage_locations \
+     WHERE warehouse_id = $1 AND parent_id IS NULL \
+     UNION ALL \
+     SELECT l.id, p.path || '/' || l.code, p.depth + 1 \
+     FROM storage_locations l JOIN paths p ON l.parent_id = p.id) ";
+
+const BIN_MOVE_COLUMNS: &str = "id, company_id, warehouse_id, variant_id, from_location_id, \
+     to_location_id, quantity, moved_by, moved_at";
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct LocationNode {
+    #[sqlx(flatten)]
+    pub location: StorageLocation,
+    pub path: String,
+    pub depth: i32,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct BinStock {
+    pub location_id: Uuid,
+    pub path: String,
+    pub variant_id: Uuid,
+    pub quantity: Quantity,
+}
+
+/// Part of a pick: a quantity from one bin, or from stock not in any bin.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Pick {
+    pub location_id: Option<Uuid>,
+    pub quantity: Quantity,
+}
+
+/// Zones, aisles, shelves and bins of warehouses, and the stock each bin
+/// holds. `StockService` keeps bin balances as movements name a bin; the
+/// part of the warehouse ledger no bin holds is unlocated stock, e.g.
+/// received before the warehouse had bins. Postgres only.
+pub struct LocationService<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> LocationService<'c> {
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Adds a location under `parent_id`, which must be an active location
+    /// of a higher level in the same warehouse.
+    pub async fn create(&mut self, mut input: CreateStorageLocation) -> Result<StorageLocation> {
+        input.code = input.code.trim().to_string();
+        if input.code.is_empty() || input.code.contains('/') {
+            return Err(AppError::App(
+                "a location code is required and cannot contain '/'".into(),
+            ));
+        }
+
+        if let Some(parent_id) = input.parent_id {
+            let parent = Repository::<StorageLocation>::new(&mut *self.conn)
+                .get(parent_id)
+                .await?
+                .ok_or_else(|| {
+                    AppError::App(format!("storage location {} not found", parent_id))
+                })?;
+            if parent.warehouse_id != input.warehouse_id || !parent.is_active {
+                return Err(AppError::App(format!(
+                    "location {} is not an active location of this warehouse",
+                    parent.code
+                )));
+            }
+            if !input.kind.fits_in(parent.kind) {
+                return Err(AppError::App(format!(
+                    "a {} cannot be placed in a {}",
+                    input.kind, parent.kind
+                )));
+            }
+        }
+
+        Repository::<StorageLocation>::new(&mut *self.conn)
+            .create(input)
+            .await
+    }
+
+    /// The warehouse's locations in tree order.
+    pub async fn tree(&mut self, warehouse_id: Uuid) -> Result<Vec<LocationNode>> {
+        sqlx::query_as(&format!(
+            "{}SELECT l.id, l.company_id, l.warehouse_id, l.parent_id, l.kind, l.code, l.name, \
+             l.is_active, l.created_at, p.path, p.depth \
+             FROM paths p JOIN storage_locations l ON l.id = p.id ORDER BY p.path",
+            PATHS
+        ))
+        .bind(warehouse_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Stock per bin of a warehouse, optionally for one variant.
+    pub async fn bin_stock(
+        &mut self,
+        warehouse_id: Uuid,
+        variant_id: Option<Uuid>,
+    ) -> Result<Vec<BinStock>> {
+        sqlx::query_as(&format!(
+            "{}SELECT b.location_id, p.path, b.variant_id, b.quantity \
+             FROM stock_bin_balances b JOIN paths p ON p.id = b.location_id \
+             WHERE b.quantity <> 0 AND ($2::uuid IS NULL OR b.variant_id = $2) \
+             ORDER BY p.path, b.variant_id",
+            PATHS
+        ))
+        .bind(warehouse_id)
+        .bind(variant_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Ledger stock of a variant in a warehouse that is in no bin.
+    pub async fn unlocated(&mut self, variant_id: Uuid, warehouse_id: Uuid) -> Result<Quantity> {
+        sqlx::query_scalar(
+            "SELECT COALESCE((SELECT quantity FROM stock_ledger \
+             WHERE variant_id = $1 AND warehouse_id = $2), 0) \
+             - COALESCE((SELECT SUM(b.quantity) FROM stock_bin_balances b \
+             JOIN storage_locations l ON l.id = b.location_id \
+             WHERE b.variant_id = $1 AND l.warehouse_id = $2), 0)",
+        )
+        .bind(variant_id)
+        .bind(warehouse_id)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Bin to put a receipt away to: the bin already holding most of the
+    /// variant, else the first empty active bin. `None` when the warehouse
+    /// has no free bin.
+    pub async fn putaway(&mut self, variant_id: Uuid, warehouse_id: Uuid) -> Result<Option<Uuid>> {
+        sqlx::query_scalar(
+            "SELECT l.id FROM storage_locations l \
+             LEFT JOIN stock_bin_balances b ON b.location_id = l.id AND b.variant_id = $1 \
+             WHERE l.warehouse_id = $2 AND l.kind = 'BIN' AND l.is_active \
+             AND (b.quantity > 0 OR NOT EXISTS ( \
+                 SELECT 1 FROM stock_bin_balances o WHERE o.location_id = l.id AND o.quantity > 0)) \
+             ORDER BY COALESCE(b.quantity, 0) DESC, l.code LIMIT 1",
+        )
+        .bind(variant_id)
+        .bind(warehouse_id)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Where to take `quantity` from: bins in code order, then unlocated
+    /// stock. Whatever neither covers is left on the unlocated part for the
+    /// negative stock policy to judge.
+    pub async fn pick(
+        &mut self,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+        quantity: Quantity,
+    ) -> Result<Vec<Pick>> {
+        let bins = self.bins(variant_id, warehouse_id).await?;
+        let mut picks = Vec::new();
+        let mut remaining = quantity;
+        for (location_id, held) in bins {
+            if remaining <= Quantity::ZERO {
+                break;
+            }
+            let take = held.min(remaining);
+            picks.push(Pick {
+                location_id: Some(location_id),
+                quantity: take,
+            });
+            remaining -= take;
+        }
+        if remaining > Quantity::ZERO {
+            picks.push(Pick {
+                location_id: None,
+                quantity: remaining,
+            });
+        }
+        Ok(picks)
+    }
+
+    /// Moves stock between two bins of a warehouse, or between a bin and
+    /// unlocated stock. The warehouse ledger and its value do not change.
+    pub async fn move_stock(&mut self, input: CreateStockBinMove) -> Result<StockBinMove> {
+        if input.quantity <= Quantity::ZERO {
+            return Err(AppError::App("a bin move needs a positive quantity".into()));
+        }
+        if input.from_location_id == input.to_location_id {
+            return Err(AppError::App(
+                "a bin move needs two different locations".into(),
+            ));
+        }
+
+        // Queue behind postings of the same variant and warehouse
+        StockService::new(&mut *self.conn, NegativeStockPolicy::Forbid)
+            .lock(input.variant_id, input.warehouse_id)
+            .await?;
+
+        match input.from_location_id {
+            Some(from) => {
+                self.bin(from, input.warehouse_id).await?;
+                self.adjust(from, input.variant_id, -input.quantity).await?;
+            }
+            None => {
+                let unlocated = self.unlocated(input.variant_id, input.warehouse_id).await?;
+                if unlocated < input.quantity {
+                    return Err(AppError::App(format!(
+                        "only {} of this variant is outside bins",
+                        unlocated.max(Quantity::ZERO)
+                    )));
+                }
+            }
+        }
+        if let Some(to) = input.to_location_id {
+            self.bin(to, input.warehouse_id).await?;
+            self.adjust(to, input.variant_id, input.quantity).await?;
+        }
+
+        sqlx::query_as(&format!(
+            "INSERT INTO stock_bin_moves (company_id, warehouse_id, variant_id, \
+             from_location_id, to_location_id, quantity, moved_by) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {}",
+            BIN_MOVE_COLUMNS
+        ))
+        .bind(input.company_id)
+        .bind(input.warehouse_id)
+        .bind(input.variant_id)
+        .bind(input.from_location_id)
+        .bind(input.to_location_id)
+        .bind(input.quantity)
+        .bind(input.moved_by)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Bin for a receipt or issue that did not name one, with the ledger row
+    /// already locked. Receipts go to the putaway bin. Issues stay unlocated
+    /// when that covers them or the variant is in no bin, else come from a
+    /// single bin holding enough; stock spread over bins must be picked
+    /// with one movement per bin (see `pick`).
+    pub(crate) async fn resolve(
+        &mut self,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+        delta: Quantity,
+    ) -> Result<Option<Uuid>> {
+        if !delta.is_negative() {
+            return self.putaway(variant_id, warehouse_id).await;
+        }
+
+        let needed = -delta;
+        let bins = self.bins(variant_id, warehouse_id).await?;
+        if bins.is_empty() || self.unlocated(variant_id, warehouse_id).await? >= needed {
+            return Ok(None);
+        }
+        if let Some((location_id, _)) = bins.iter().find(|(_, held)| *held >= needed) {
+            return Ok(Some(*location_id));
+        }
+        Err(AppError::App(
+            "stock of this variant is spread over several bins; pick from each bin with its own movement"
+                .into(),
+        ))
+    }
+
+    /// Moves the bin balance for a posted movement that names a bin.
+    pub(crate) async fn apply(&mut self, movement: &StockMovement) -> Result<()> {
+        let Some(location_id) = movement.location_id else {
+            return Ok(());
+        };
+        self.bin(location_id, movement.warehouse_id).await?;
+        self.adjust(
+            location_id,
+            movement.variant_id,
+            movement.movement_type.signed(movement.quantity),
+        )
+        .await
+    }
+
+    /// Active bins of the warehouse holding the variant, in code order.
+    async fn bins(
+        &mut self,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+    ) -> Result<Vec<(Uuid, Quantity)>> {
+        sqlx::query_as(
+            "SELECT b.location_id, b.quantity FROM stock_bin_balances b \
+             JOIN storage_locations l ON l.id = b.location_id \
+             WHERE b.variant_id = $1 AND l.warehouse_id = $2 AND l.is_active AND b.quantity > 0 \
+             ORDER BY l.code",
+        )
+        .bind(variant_id)
+        .bind(warehouse_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Checks that a location is an active bin of the warehouse.
+    pub(crate) async fn bin(
+        &mut self,
+        location_id: Uuid,
+        warehouse_id: Uuid,
+    ) -> Result<StorageLocation> {
+        let location = Repository::<StorageLocation>::new(&mut *self.conn)
+            .get(location_id)
+            .await?
+            .ok_or_else(|| AppError::App(format!("storage location {} not found", location_id)))?;
+        if location.warehouse_id != warehouse_id
+            || location.kind != LocationKind::Bin
+            || !location.is_active
+        {
+            return Err(AppError::App(format!(
+                "location {} is not an active bin of this warehouse",
+                location.code
+            )));
+        }
+        Ok(location)
+    }
+
+    async fn adjust(&mut self, location_id: Uuid, variant_id: Uuid, delta: Quantity) -> Result<()> {
+        let updated = if delta.is_negative() {
+            sqlx::query(
+                "UPDATE stock_bin_balances SET quantity = quantity + $3 \
+                 WHERE location_id = $1 AND variant_id = $2 AND quantity >= -$3",
+            )
+            .bind(location_id)
+            .bind(variant_id)
+            .bind(delta)
+            .execute(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?
+            .rows_affected()
+        } else {
+            sqlx::query(
+                "INSERT INTO stock_bin_balances (location_id, variant_id, quantity) \
+                 VALUES ($1, $2, $3) ON CONFLICT (location_id, variant_id) \
+                 DO UPDATE SET quantity = stock_bin_balances.quantity + EXCLUDED.quantity",
+            )
+            .bind(location_id)
+            .bind(variant_id)
+            .bind(delta)
+            .execute(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?
+            .rows_affected()
+        };
+
+        if updated == 0 {
+            return Err(AppError::App(format!(
+                "the bin does not hold {} of this variant",
+                -delta
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::postgres::PgConnection;
+    use uuid::Uuid;
+
+    use super::LocationService;
+    use crate::config::NegativeStockPolicy;
+    use crate::db::enums::{LocationKind, MovementType, TrackingMode, UserRole};
+    use crate::db::inventory::{CountService, StockService, TransferService};
+    use crate::db::models::{
+        CreateStockCount, CreateStockMovement, CreateStockTransfer, CreateStockTransferLine,
+        CreateStorageLocation, StockCount, StockTransfer, StockTransferLine, StorageLocation,
+    };
+    use crate::db::money::{Money, Quantity};
+    use crate::db::repositories::Repository;
+    use crate::db::testing;
+
+    async fn bin(
+        conn: &mut PgConnection,
+        company_id: Uuid,
+        warehouse_id: Uuid,
+        code: &str,
+    ) -> Uuid {
+        LocationService::new(conn)
+            .create(CreateStorageLocation {
+                company_id,
+                warehouse_id,
+                parent_id: None,
+                kind: LocationKind::Bin,
+                code: code.into(),
+                name: None,
+            })
+            .await
+            .unwrap()
+            .id
+    }
+
+    async fn post(
+        conn: &mut PgConnection,
+        company_id: Uuid,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+        movement_type: MovementType,
+        quantity: i64,
+        location_id: Option<Uuid>,
+    ) {
+        StockService::new(conn, NegativeStockPolicy::Forbid)
+            .post(CreateStockMovement {
+                company_id,
+                variant_id,
+                warehouse_id,
+                quantity: Quantity::from(quantity),
+                movement_type,
+                reference_type: None,
+                reference_id: None,
+                unit_cost: Some(Money::from(10)),
+                location_id,
+                lots: Vec::new(),
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn stock(
+        conn: &mut PgConnection,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+    ) -> (Vec<(Uuid, Quantity)>, Quantity) {
+        let mut locations = LocationService::new(conn);
+        let bins = locations
+            .bin_stock(warehouse_id, Some(variant_id))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|bin| (bin.location_id, bin.quantity))
+            .collect();
+        let unlocated = locations.unlocated(variant_id, warehouse_id).await.unwrap();
+        (bins, unlocated)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn stock_spread_over_bins_ships_and_counts_by_bin() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let source = testing::warehouse(conn, company).await;
+        let destination = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let admin = testing::user(conn, UserRole::Admin).await;
+        let first = bin(conn, company, source, "A1").await;
+        let second = bin(conn, company, source, "A2").await;
+
+        post(
+            conn,
+            company,
+            variant,
+            source,
+            MovementType::In,
+            3,
+            Some(first),
+        )
+        .await;
+        post(
+            conn,
+            company,
+            variant,
+            source,
+            MovementType::In,
+            3,
+            Some(second),
+        )
+        .await;
+        // Adjustments without a bin change stock outside bins
+        post(
+            conn,
+            company,
+            variant,
+            source,
+            MovementType::Adjustment,
+            2,
+            None,
+        )
+        .await;
+        assert_eq!(
+            stock(conn, variant, source).await,
+            (
+                vec![(first, Quantity::from(3)), (second, Quantity::from(3))],
+                Quantity::from(2)
+            )
+        );
+
+        // Shipping more than any one bin holds picks bins in code order
+        let transfer = Repository::<StockTransfer>::new(conn)
+            .create(CreateStockTransfer {
+                company_id: company,
+                from_warehouse_id: source,
+                to_warehouse_id: destination,
+                notes: None,
+                created_by: admin,
+            })
+            .await
+            .unwrap();
+        Repository::<StockTransferLine>::new(conn)
+            .create(CreateStockTransferLine {
+                transfer_id: transfer.id,
+                variant_id: variant,
+                quantity: Quantity::from(5),
+            })
+            .await
+            .unwrap();
+        TransferService::new(conn, NegativeStockPolicy::Forbid)
+            .ship(transfer.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            stock(conn, variant, source).await,
+            (vec![(second, Quantity::from(1))], Quantity::from(2))
+        );
+
+        // Each bin and the stock outside bins is counted on its own line
+        let count = CountService::new(conn)
+            .start(
+                CreateStockCount {
+                    company_id: company,
+                    warehouse_id: source,
+                    notes: None,
+                    created_by: admin,
+                },
+                Some(vec![variant]),
+            )
+            .await
+            .unwrap();
+        let frozen: Vec<_> = Repository::<StockCount>::new(conn)
+            .lines(count.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|line| (line.location_id, line.expected))
+            .collect();
+        assert_eq!(frozen.len(), 2);
+        assert!(frozen.contains(&(None, Quantity::from(2))));
+        assert!(frozen.contains(&(Some(second), Quantity::from(1))));
+
+        let mut counts = CountService::new(conn);
+        counts
+            .record(count.id, variant, Some(second), Quantity::ZERO)
+            .await
+            .unwrap();
+        counts
+            .record(count.id, variant, None, Quantity::from(3))
+            .await
+            .unwrap();
+        counts.submit(count.id).await.unwrap();
+        counts.approve(count.id, admin).await.unwrap();
+        assert_eq!(
+            stock(conn, variant, source).await,
+            (Vec::new(), Quantity::from(3))
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn locations_holding_stock_are_not_archived() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let warehouse = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let mut locations = LocationService::new(conn);
+        let zone = locations
+            .create(CreateStorageLocation {
+                company_id: company,
+                warehouse_id: warehouse,
+                parent_id: None,
+                kind: LocationKind::Zone,
+                code: "Z".into(),
+                name: None,
+            })
+            .await
+            .unwrap()
+            .id;
+        let zone_bin = locations
+            .create(CreateStorageLocation {
+                company_id: company,
+                warehouse_id: warehouse,
+                parent_id: Some(zone),
+                kind: LocationKind::Bin,
+                code: "Z-1".into(),
+                name: None,
+            })
+            .await
+            .unwrap()
+            .id;
+        post(
+            conn,
+            company,
+            variant,
+            warehouse,
+            MovementType::In,
+            2,
+            Some(zone_bin),
+        )
+        .await;
+
+        let mut locations = Repository::<StorageLocation>::new(&mut *conn);
+        assert!(!locations.archive(zone).await.unwrap());
+        assert!(!locations.archive(zone_bin).await.unwrap());
+
+        post(
+            conn,
+            company,
+            variant,
+            warehouse,
+            MovementType::Out,
+            2,
+            Some(zone_bin),
+        )
+        .await;
+        let mut locations = Repository::<StorageLocation>::new(&mut *conn);
+        assert!(locations.archive(zone_bin).await.unwrap());
+        assert!(locations.archive(zone).await.unwrap());
+    }
+}
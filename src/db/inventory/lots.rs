@@ -0,0 +1,562 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::db::enums::{MovementType, TrackingMode};
+use crate::db::models::{LotQuantity, StockLot, StockMovement};
+use crate::db::money::Quantity;
+use crate::error::{AppError, Result};
+
+const LOT_COLUMNS: &str = "id, company_id, variant_id, number, expires_on, created_at";
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct LotBalance {
+    pub lot_id: Uuid,
+    pub number: String,
+    pub expires_on: Option<NaiveDate>,
+    pub warehouse_id: Uuid,
+    pub quantity: Quantity,
+}
+
+/// One movement of a lot, with the document it belongs to.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct LotMovement {
+    pub movement_id: Uuid,
+    pub movement_type: MovementType,
+    pub warehouse_id: Uuid,
+    /// Quantity of this lot in the movement.
+    pub quantity: Quantity,
+    pub reference_type: Option<String>,
+    pub reference_id: Option<Uuid>,
+    pub movement_date: DateTime<Utc>,
+}
+
+/// Where a lot came from, where it went and where it is now.
+#[derive(Debug, Clone, Serialize)]
+pub struct LotTrace {
+    pub lot: StockLot,
+    pub movements: Vec<LotMovement>,
+    pub balances: Vec<LotBalance>,
+}
+
+/// Lot and serial records of tracked variants, kept by `StockService`
+/// as it posts movements. Postgres only.
+pub struct LotService<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> LotService<'c> {
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Checks that a movement names lots exactly when its variant is
+    /// tracked, and that they add up to the movement's quantity.
+    pub(crate) async fn check(
+        &mut self,
+        variant_id: Uuid,
+        quantity: Quantity,
+        lots: &[LotQuantity],
+    ) -> Result<TrackingMode> {
+        let tracking = self.tracking(variant_id).await?;
+        match tracking {
+            TrackingMode::Untracked if !lots.is_empty() => {
+                return Err(AppError::App(
+                    "lots given for a variant that is not lot or serial tracked".into(),
+                ));
+            }
+            TrackingMode::Untracked => return Ok(tracking),
+            TrackingMode::Lot | TrackingMode::Serial if lots.is_empty() => {
+                return Err(AppError::App(format!(
+                    "variant is tracked by {}, name the lots moved",
+                    tracking
+                )));
+            }
+            _ => {}
+        }
+
+        let mut numbers = HashSet::new();
+        for lot in lots {
+            if lot.number.trim().is_empty() || !numbers.insert(lot.number.trim()) {
+                return Err(AppError::App(format!(
+                    "invalid or repeated lot {:?}",
+                    lot.number
+                )));
+            }
+            if lot.quantity <= Quantity::ZERO {
+                return Err(AppError::App(format!(
+                    "lot {} needs a positive quantity",
+                    lot.number
+                )));
+            }
+            if tracking == TrackingMode::Serial && lot.quantity != Quantity::from(1) {
+                return Err(AppError::App(format!(
+                    "serial {} must move exactly one unit",
+                    lot.number
+                )));
+            }
+        }
+        let total: Quantity = lots.iter().map(|lot| lot.quantity).sum();
+        if total != quantity.abs() {
+            return Err(AppError::App(format!(
+                "lot quantities add up to {}, the movement moves {}",
+                total,
+                quantity.abs()
+            )));
+        }
+        Ok(tracking)
+    }
+
+    /// Records the lots of a posted movement and moves their balances.
+    /// Receipts create unknown lots; issues need the lot in stock in the
+    /// movement's warehouse, whatever the negative-stock policy.
+    pub(crate) async fn apply(
+        &mut self,
+        movement: &StockMovement,
+        tracking: TrackingMode,
+        lots: &[LotQuantity],
+    ) -> Result<()> {
+        let incoming = !movement
+            .movement_type
+            .signed(movement.quantity)
+            .is_negative();
+        for lot in lots {
+            let number = lot.number.trim();
+            let lot_id = if incoming {
+                self.receive(movement, tracking, number, lot).await?
+            } else {
+                self.issue(movement, number, lot.quantity).await?
+            };
+
+            sqlx::query(
+                "INSERT INTO stock_movement_lots (movement_id, lot_id, quantity) VALUES ($1, $2, $3)",
+            )
+            .bind(movement.id)
+            .bind(lot_id)
+            .bind(lot.quantity)
+            .execute(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+        }
+        Ok(())
+    }
+
+    async fn receive(
+        &mut self,
+        movement: &StockMovement,
+        tracking: TrackingMode,
+        number: &str,
+        lot: &LotQuantity,
+    ) -> Result<Uuid> {
+        let lot_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO stock_lots (company_id, variant_id, number, expires_on) \
+             VALUES ($1, $2, $3, $4) \
+             ON CONFLICT (variant_id, number) \
+             DO UPDATE SET expires_on = COALESCE(stock_lots.expires_on, EXCLUDED.expires_on) \
+             RETURNING id",
+        )
+        .bind(movement.company_id)
+        .bind(movement.variant_id)
+        .bind(number)
+        .bind(lot.expires_on)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        if tracking == TrackingMode::Serial {
+            let in_stock: Option<Quantity> = sqlx::query_scalar(
+                "SELECT SUM(quantity) FROM stock_lot_balances WHERE lot_id = $1",
+            )
+            .bind(lot_id)
+            .fetch_one(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+            if in_stock.is_some_and(|quantity| quantity > Quantity::ZERO) {
+                return Err(AppError::App(format!(
+                    "serial {} is already in stock",
+                    number
+                )));
+            }
+        }
+
+        sqlx::query(
+            "INSERT INTO stock_lot_balances (lot_id, warehouse_id, quantity) VALUES ($1, $2, $3) \
+             ON CONFLICT (lot_id, warehouse_id) \
+             DO UPDATE SET quantity = stock_lot_balances.quantity + EXCLUDED.quantity",
+        )
+        .bind(lot_id)
+        .bind(movement.warehouse_id)
+        .bind(lot.quantity)
+        .execute(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+        Ok(lot_id)
+    }
+
+    async fn issue(
+        &mut self,
+        movement: &StockMovement,
+        number: &str,
+        quantity: Quantity,
+    ) -> Result<Uuid> {
+        let lot_id: Option<Uuid> = sqlx::query_scalar(
+            "UPDATE stock_lot_balances b SET quantity = b.quantity - $4 \
+             FROM stock_lots l \
+             WHERE l.id = b.lot_id AND l.variant_id = $1 AND l.number = $2 \
+             AND b.warehouse_id = $3 AND b.quantity >= $4 \
+             RETURNING l.id",
+        )
+        .bind(movement.variant_id)
+        .bind(number)
+        .bind(movement.warehouse_id)
+        .bind(quantity)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        lot_id.ok_or_else(|| {
+            AppError::App(format!(
+                "lot {} does not have {} in stock in this warehouse",
+                number, quantity
+            ))
+        })
+    }
+
+    /// Lots of a variant with stock in a warehouse, soonest expiry first,
+    /// for pickers.
+    pub async fn available(
+        &mut self,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+    ) -> Result<Vec<LotBalance>> {
+        sqlx::query_as(
+            "SELECT l.id AS lot_id, l.number, l.expires_on, b.warehouse_id, b.quantity \
+             FROM stock_lots l JOIN stock_lot_balances b ON b.lot_id = l.id \
+             WHERE l.variant_id = $1 AND b.warehouse_id = $2 AND b.quantity > 0 \
+             ORDER BY l.expires_on NULLS LAST, l.created_at, l.number",
+        )
+        .bind(variant_id)
+        .bind(warehouse_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Lots to issue `quantity` of a variant from in a warehouse, soonest
+    /// expiry first. Empty for untracked variants; an error when the lots
+    /// there hold less.
+    pub async fn pick(
+        &mut self,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+        quantity: Quantity,
+    ) -> Result<Vec<LotQuantity>> {
+        if self.tracking(variant_id).await? == TrackingMode::Untracked {
+            return Ok(Vec::new());
+        }
+
+        let mut picked = Vec::new();
+        let mut remaining = quantity;
+        for lot in self.available(variant_id, warehouse_id).await? {
+            if remaining <= Quantity::ZERO {
+                break;
+            }
+            let take = lot.quantity.min(remaining);
+            picked.push(LotQuantity {
+                number: lot.number,
+                expires_on: lot.expires_on,
+                quantity: take,
+            });
+            remaining -= take;
+        }
+        if remaining > Quantity::ZERO {
+            return Err(AppError::App(format!(
+                "lots in this warehouse hold {} less than the {} needed",
+                remaining, quantity
+            )));
+        }
+        Ok(picked)
+    }
+
+    /// Lot for stock found without a document naming it, e.g. by a count:
+    /// the lot of the variant last received into the warehouse. Serials
+    /// cannot be guessed and must be received by number.
+    pub async fn found(
+        &mut self,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+        quantity: Quantity,
+    ) -> Result<Vec<LotQuantity>> {
+        match self.tracking(variant_id).await? {
+            TrackingMode::Untracked => Ok(Vec::new()),
+            TrackingMode::Serial => Err(AppError::App(
+                "found serial numbered stock must be received by serial number".into(),
+            )),
+            TrackingMode::Lot => {
+                let lot: Option<(String, Option<NaiveDate>)> = sqlx::query_as(
+                    "SELECT l.number, l.expires_on \
+                     FROM stock_lots l JOIN stock_lot_balances b ON b.lot_id = l.id \
+                     WHERE l.variant_id = $1 AND b.warehouse_id = $2 \
+                     ORDER BY l.created_at DESC, l.number DESC LIMIT 1",
+                )
+                .bind(variant_id)
+                .bind(warehouse_id)
+                .fetch_optional(&mut *self.conn)
+                .await
+                .map_err(AppError::Database)?;
+                let (number, expires_on) = lot.ok_or_else(|| {
+                    AppError::App(
+                        "no lot of this variant was ever in this warehouse; receive it by lot"
+                            .into(),
+                    )
+                })?;
+                Ok(vec![LotQuantity {
+                    number,
+                    expires_on,
+                    quantity,
+                }])
+            }
+        }
+    }
+
+    async fn tracking(&mut self, variant_id: Uuid) -> Result<TrackingMode> {
+        sqlx::query_scalar("SELECT tracking FROM product_variants WHERE id = $1")
+            .bind(variant_id)
+            .fetch_one(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)
+    }
+
+    /// Every movement of a lot or serial number in date order, and where it
+    /// is in stock now.
+    pub async fn trace(&mut self, variant_id: Uuid, number: &str) -> Result<Option<LotTrace>> {
+        let lot: Option<StockLot> = sqlx::query_as(&format!(
+            "SELECT {} FROM stock_lots WHERE variant_id = $1 AND number = $2",
+            LOT_COLUMNS
+        ))
+        .bind(variant_id)
+        .bind(number.trim())
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+        let Some(lot) = lot else {
+            return Ok(None);
+        };
+
+        let movements = sqlx::query_as(
+            "SELECT m.id AS movement_id, m.movement_type, m.warehouse_id, ml.quantity, \
+             m.reference_type, m.reference_id, m.movement_date \
+             FROM stock_movement_lots ml JOIN stock_movements m ON m.id = ml.movement_id \
+             WHERE ml.lot_id = $1 ORDER BY m.movement_date, m.id",
+        )
+        .bind(lot.id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        let balances = sqlx::query_as(
+            "SELECT l.id AS lot_id, l.number, l.expires_on, b.warehouse_id, b.quantity \
+             FROM stock_lots l JOIN stock_lot_balances b ON b.lot_id = l.id \
+             WHERE l.id = $1 AND b.quantity > 0 ORDER BY b.warehouse_id",
+        )
+        .bind(lot.id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(Some(LotTrace {
+            lot,
+            movements,
+            balances,
+        }))
+    }
+}
+
+/// Takes the first `quantity` off lots picked for several movements, e.g.
+/// one per bin, splitting a lot where the movements divide it.
+pub(crate) fn take(lots: &mut Vec<LotQuantity>, quantity: Quantity) -> Vec<LotQuantity> {
+    let mut taken = Vec::new();
+    let mut remaining = quantity;
+    while remaining > Quantity::ZERO && !lots.is_empty() {
+        if lots[0].quantity <= remaining {
+            let lot = lots.remove(0);
+            remaining -= lot.quantity;
+            taken.push(lot);
+        } else {
+            lots[0].quantity -= remaining;
+            taken.push(LotQuantity {
+                quantity: remaining,
+                ..lots[0].clone()
+            });
+            remaining = Quantity::ZERO;
+        }
+    }
+    taken
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use sqlx::postgres::PgConnection;
+    use uuid::Uuid;
+
+    use super::LotService;
+    use crate::config::NegativeStockPolicy;
+    use crate::db::enums::{MovementType, OrderStatus, TrackingMode, UserRole};
+    use crate::db::inventory::{CountService, ReservationService, StockService, TransferService};
+    use crate::db::models::{
+        CreateSalesOrder, CreateSalesOrderLine, CreateStockCount, CreateStockMovement,
+        CreateStockTransfer, CreateStockTransferLine, LotQuantity, SalesOrder, SalesOrderLine,
+        StockTransfer, StockTransferLine,
+    };
+    use crate::db::money::{Money, Quantity};
+    use crate::db::repositories::Repository;
+    use crate::db::testing;
+
+    async fn lots(
+        conn: &mut PgConnection,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+    ) -> Vec<(String, Quantity)> {
+        LotService::new(conn)
+            .available(variant_id, warehouse_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|lot| (lot.number, lot.quantity))
+            .collect()
+    }
+
+    fn lot(number: &str, quantity: i64) -> (String, Quantity) {
+        (number.to_string(), Quantity::from(quantity))
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn lot_tracked_stock_ships_receives_counts_and_delivers() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let source = testing::warehouse(conn, company).await;
+        let destination = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Lot, None, None).await;
+        let admin = testing::user(conn, UserRole::Admin).await;
+
+        StockService::new(conn, NegativeStockPolicy::Forbid)
+            .post(CreateStockMovement {
+                company_id: company,
+                variant_id: variant,
+                warehouse_id: source,
+                quantity: Quantity::from(10),
+                movement_type: MovementType::In,
+                reference_type: None,
+                reference_id: None,
+                unit_cost: Some(Money::from(10)),
+                location_id: None,
+                lots: vec![
+                    LotQuantity {
+                        number: "LATE".into(),
+                        expires_on: NaiveDate::from_ymd_opt(2031, 1, 1),
+                        quantity: Quantity::from(5),
+                    },
+                    LotQuantity {
+                        number: "EARLY".into(),
+                        expires_on: NaiveDate::from_ymd_opt(2030, 1, 1),
+                        quantity: Quantity::from(5),
+                    },
+                ],
+            })
+            .await
+            .unwrap();
+
+        // Transfers ship the soonest expiring lots and receive the same ones
+        let transfer = Repository::<StockTransfer>::new(conn)
+            .create(CreateStockTransfer {
+                company_id: company,
+                from_warehouse_id: source,
+                to_warehouse_id: destination,
+                notes: None,
+                created_by: admin,
+            })
+            .await
+            .unwrap();
+        Repository::<StockTransferLine>::new(conn)
+            .create(CreateStockTransferLine {
+                transfer_id: transfer.id,
+                variant_id: variant,
+                quantity: Quantity::from(6),
+            })
+            .await
+            .unwrap();
+        let mut transfers = TransferService::new(conn, NegativeStockPolicy::Forbid);
+        transfers.ship(transfer.id).await.unwrap();
+        transfers.receive(transfer.id).await.unwrap();
+        assert_eq!(lots(conn, variant, source).await, vec![lot("LATE", 4)]);
+        assert_eq!(
+            lots(conn, variant, destination).await,
+            vec![lot("EARLY", 5), lot("LATE", 1)]
+        );
+
+        // Shrinkage found by a count comes out of the remaining lot
+        let mut counts = CountService::new(conn);
+        let count = counts
+            .start(
+                CreateStockCount {
+                    company_id: company,
+                    warehouse_id: source,
+                    notes: None,
+                    created_by: admin,
+                },
+                Some(vec![variant]),
+            )
+            .await
+            .unwrap();
+        counts
+            .record(count.id, variant, None, Quantity::from(3))
+            .await
+            .unwrap();
+        counts.submit(count.id).await.unwrap();
+        counts.approve(count.id, admin).await.unwrap();
+        assert_eq!(lots(conn, variant, source).await, vec![lot("LATE", 3)]);
+
+        // Deliveries pick the soonest expiring lots
+        let currency = sqlx::query_scalar("SELECT base_currency_id FROM companies WHERE id = $1")
+            .bind(company)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        let customer = testing::partner(conn, company).await;
+        let order = Repository::<SalesOrder>::new(conn)
+            .create(CreateSalesOrder {
+                company_id: company,
+                customer_id: customer,
+                order_date: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap(),
+                currency_id: currency,
+                status: OrderStatus::Draft,
+                created_by: admin,
+            })
+            .await
+            .unwrap();
+        Repository::<SalesOrderLine>::new(conn)
+            .create(CreateSalesOrderLine {
+                sales_order_id: order.id,
+                variant_id: variant,
+                quantity: Quantity::from(5),
+                unit_id: None,
+                unit_price: Money::from(20),
+            })
+            .await
+            .unwrap();
+        let mut reservations = ReservationService::new(conn, NegativeStockPolicy::Forbid);
+        reservations
+            .confirm_order(order.id, destination)
+            .await
+            .unwrap();
+        reservations.deliver_order(order.id).await.unwrap();
+        assert_eq!(lots(conn, variant, destination).await, vec![lot("LATE", 1)]);
+    }
+}
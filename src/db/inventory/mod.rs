@@ -0,0 +1,27 @@
+pub mod boms;
+pub mod counts;
+pub mod history;
+pub mod landed_costs;
+pub mod locations;
+pub mod lots;
+pub mod receipts;
+pub mod replenishment;
+pub mod reservations;
+pub mod stock;
+pub mod transfers;
+pub mod units;
+pub mod valuation;
+
+pub use boms::BomService;
+pub use counts::CountService;
+pub use history::HistoryService;
+pub use landed_costs::LandedCostService;
+pub use locations::LocationService;
+pub use lots::LotService;
+pub use receipts::{PurchaseReceipt, ReceiptService};
+pub use replenishment::ReplenishmentService;
+pub use reservations::ReservationService;
+pub use stock::{StockPosting, StockService};
+pub use transfers::TransferService;
+pub use units::UnitService;
+pub use valuation::ValuationService;
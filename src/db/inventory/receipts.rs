@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::config::NegativeStockPolicy;
+use crate::db::enums::{MovementType, OrderStatus, RoundingMode};
+use crate::db::inventory::units::UnitUse;
+use crate::db::inventory::{StockPosting, StockService, UnitService, lots, valuation};
+use crate::db::models::{CreateStockMovement, LotQuantity, PurchaseOrder};
+use crate::db::money::{Money, Quantity};
+use crate::db::repositories::{Entity, Repository};
+use crate::error::{AppError, Result};
+
+/// `reference_type` of the movements that receive a purchase order; their
+/// `reference_id` is the order's id. Replenishment counts them against what
+/// is on order and landed costs are allocated over them.
+pub const REFERENCE_TYPE: &str = "purchase_order";
+
+/// Goods arriving on one purchase order line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurchaseReceipt {
+    pub line_id: Uuid,
+    /// In the line's unit.
+    pub quantity: Quantity,
+    /// Bin to put the goods away to; `None` uses the putaway rules.
+    #[serde(default)]
+    pub location_id: Option<Uuid>,
+    /// In the variant's stock unit.
+    #[serde(default)]
+    pub lots: Vec<LotQuantity>,
+}
+
+/// Books goods received on purchase orders into the order's warehouse at
+/// the line's cost. Postgres only; run inside a transaction.
+pub struct ReceiptService<'c> {
+    conn: &'c mut PgConnection,
+    policy: NegativeStockPolicy,
+}
+
+impl<'c> ReceiptService<'c> {
+    pub fn new(conn: &'c mut PgConnection, policy: NegativeStockPolicy) -> Self {
+        Self { conn, policy }
+    }
+
+    /// Posts a receipt for each line of a confirmed order, in the variant's
+    /// stock unit and at exactly what the received quantity costs on the
+    /// line. The order completes once everything on it has arrived.
+    pub async fn receive(
+        &mut self,
+        order_id: Uuid,
+        receipts: Vec<PurchaseReceipt>,
+    ) -> Result<Vec<StockPosting>> {
+        let order = self.lock_order(order_id).await?;
+        if order.status != OrderStatus::Confirmed {
+            return Err(AppError::App(format!(
+                "purchase order is {}, only confirmed orders can be received",
+                order.status
+            )));
+        }
+        let Some(warehouse_id) = order.warehouse_id else {
+            return Err(AppError::App(
+                "the purchase order has no warehouse to receive into".into(),
+            ));
+        };
+        let base_currency: bool = sqlx::query_scalar(
+            "SELECT base_currency_id IS NOT DISTINCT FROM $2 FROM companies WHERE id = $1",
+        )
+        .bind(order.company_id)
+        .bind(order.currency_id)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+        if !base_currency {
+            return Err(AppError::App(
+                "only purchase orders in the company's base currency can be received at cost"
+                    .into(),
+            ));
+        }
+
+        let lines = Repository::<PurchaseOrder>::new(&mut *self.conn)
+            .lines(order_id)
+            .await?;
+        let mut postings = Vec::new();
+        for receipt in receipts {
+            let Some(line) = lines.iter().find(|line| line.id == receipt.line_id) else {
+                return Err(AppError::App(format!(
+                    "line {} is not on this purchase order",
+                    receipt.line_id
+                )));
+            };
+            let quantity = UnitService::new(&mut *self.conn)
+                .stock_quantity(
+                    line.variant_id,
+                    line.unit_id,
+                    receipt.quantity,
+                    UnitUse::Purchase,
+                )
+                .await?;
+            let cost =
+                (receipt.quantity * line.unit_cost).round(Money::SCALE, RoundingMode::HalfUp);
+
+            let mut received_lots = receipt.lots;
+            let mut stock = StockService::new(&mut *self.conn, self.policy);
+            for (quantity, unit_cost) in valuation::cost_parts(quantity, cost) {
+                postings.push(
+                    stock
+                        .post(CreateStockMovement {
+                            company_id: order.company_id,
+                            variant_id: line.variant_id,
+                            warehouse_id,
+                            quantity,
+                            movement_type: MovementType::In,
+                            reference_type: Some(REFERENCE_TYPE.into()),
+                            reference_id: Some(order_id),
+                            unit_cost: Some(unit_cost),
+                            location_id: receipt.location_id,
+                            lots: lots::take(&mut received_lots, quantity),
+                        })
+                        .await?,
+                );
+            }
+        }
+
+        if self.outstanding(order_id).await? {
+            return Ok(postings);
+        }
+        sqlx::query("UPDATE purchase_orders SET status = $2 WHERE id = $1")
+            .bind(order_id)
+            .bind(OrderStatus::Completed)
+            .execute(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+        Ok(postings)
+    }
+
+    /// Whether any variant on the order has arrived short of what was
+    /// ordered, counted in stock units over the whole order.
+    async fn outstanding(&mut self, order_id: Uuid) -> Result<bool> {
+        sqlx::query_scalar(
+            "SELECT EXISTS ( \
+                 SELECT 1 FROM purchase_order_lines pl \
+                 WHERE pl.purchase_order_id = $1 \
+                 GROUP BY pl.variant_id \
+                 HAVING SUM(pl.quantity * uom_factor(pl.variant_id, pl.unit_id)) > COALESCE(( \
+                     SELECT SUM(m.quantity) FROM stock_movements m \
+                     WHERE m.reference_type = $2 AND m.reference_id = $1 \
+                     AND m.variant_id = pl.variant_id AND m.movement_type = 'in' \
+                 ), 0))",
+        )
+        .bind(order_id)
+        .bind(REFERENCE_TYPE)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn lock_order(&mut self, order_id: Uuid) -> Result<PurchaseOrder> {
+        sqlx::query_as(&format!(
+            "SELECT {} FROM purchase_orders WHERE id = $1 FOR UPDATE",
+            PurchaseOrder::COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::App(format!("purchase order {} not found", order_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{PurchaseReceipt, REFERENCE_TYPE, ReceiptService};
+    use crate::config::NegativeStockPolicy;
+    use crate::db::enums::{OrderStatus, TrackingMode};
+    use crate::db::models::PurchaseOrder;
+    use crate::db::money::{Money, Quantity};
+    use crate::db::repositories::Repository;
+    use crate::db::testing;
+
+    fn receipt(line_id: Uuid, quantity: i64) -> PurchaseReceipt {
+        PurchaseReceipt {
+            line_id,
+            quantity: Quantity::from(quantity),
+            location_id: None,
+            lots: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn orders_complete_once_everything_has_arrived() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let warehouse = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let (order, line) = testing::purchase_order(conn, company, warehouse, variant, 3, 10).await;
+
+        let mut receipts = ReceiptService::new(conn, NegativeStockPolicy::Forbid);
+        let first = receipts
+            .receive(order, vec![receipt(line, 1)])
+            .await
+            .unwrap();
+        assert_eq!(first.len(), 1);
+        let movement = &first[0].movement;
+        assert_eq!(movement.reference_type.as_deref(), Some(REFERENCE_TYPE));
+        assert_eq!(movement.reference_id, Some(order));
+        assert_eq!(first[0].cost, Money::from(10));
+
+        receipts
+            .receive(order, vec![receipt(line, 2)])
+            .await
+            .unwrap();
+        let status = Repository::<PurchaseOrder>::new(conn)
+            .get(order)
+            .await
+            .unwrap()
+            .unwrap()
+            .status;
+        assert_eq!(status, OrderStatus::Completed);
+
+        let mut receipts = ReceiptService::new(conn, NegativeStockPolicy::Forbid);
+        let late = receipts.receive(order, vec![receipt(line, 1)]).await;
+        assert!(late.unwrap_err().to_string().contains("only confirmed"));
+    }
+}
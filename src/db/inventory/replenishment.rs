@@ -0,0 +1,302 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::db::enums::OrderStatus;
+use crate::db::inventory::receipts;
+use crate::db::models::{
+    CreatePurchaseOrder, CreatePurchaseOrderLine, PurchaseOrder, PurchaseOrderLine,
+};
+use crate::db::money::{Money, Quantity};
+use crate::db::repositories::Repository;
+use crate::error::{AppError, Result};
+
+/// A rule whose projected stock has reached its reorder point.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Proposal {
+    pub rule_id: Uuid,
+    pub variant_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub on_hand: Quantity,
+    pub reserved: Quantity,
+    /// Still to arrive on open purchase orders for the warehouse.
+    pub incoming: Quantity,
+    #[sqlx(skip)]
+    pub projected: Quantity,
+    #[sqlx(skip)]
+    pub quantity: Quantity,
+    /// Last price paid, preferably to this vendor, else the variant's cost price.
+    pub unit_cost: Money,
+    #[serde(skip)]
+    min_quantity: Quantity,
+    #[serde(skip)]
+    max_quantity: Option<Quantity>,
+    #[serde(skip)]
+    reorder_quantity: Option<Quantity>,
+    #[serde(skip)]
+    order_multiple: Quantity,
+    #[serde(skip)]
+    vendor_id: Option<Uuid>,
+}
+
+/// Proposals for one vendor; `vendor_id` is `None` for variants with no
+/// preferred vendor and no purchase history, which cannot be ordered.
+#[derive(Debug, Clone, Serialize)]
+pub struct VendorProposal {
+    pub vendor_id: Option<Uuid>,
+    pub lines: Vec<Proposal>,
+}
+
+/// Replenishment runs over the reorder rules of a company. Postgres only.
+pub struct ReplenishmentService<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> ReplenishmentService<'c> {
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Proposes what to order for every active rule whose projected stock
+    /// (on hand - reserved + incoming) is at or below its reorder point.
+    pub async fn run(&mut self, company_id: Uuid) -> Result<Vec<VendorProposal>> {
+        let rules: Vec<Proposal> = sqlx::query_as(
+            "SELECT r.id AS rule_id, r.variant_id, r.warehouse_id, r.min_quantity, \
+             r.max_quantity, r.reorder_quantity, r.order_multiple, \
+             COALESCE(r.vendor_id, last.vendor_id) AS vendor_id, \
+             COALESCE(l.quantity, 0) AS on_hand, COALESCE(l.reserved, 0) AS reserved, \
+             COALESCE(incoming.quantity, 0) AS incoming, \
+             COALESCE(last.unit_cost, v.cost_price) AS unit_cost \
+             FROM reorder_rules r \
+             JOIN product_variants v ON v.id = r.variant_id \
+             LEFT JOIN stock_ledger l \
+             ON l.variant_id = r.variant_id AND l.warehouse_id = r.warehouse_id \
+             LEFT JOIN LATERAL ( \
+                 SELECT SUM(GREATEST(ordered.quantity - COALESCE(( \
+                     SELECT SUM(m.quantity) FROM stock_movements m \
+                     WHERE m.reference_type = $2 AND m.reference_id = ordered.purchase_order_id \
+                     AND m.variant_id = r.variant_id AND m.movement_type = 'in' \
+                 ), 0), 0)) AS quantity \
+                 FROM ( \
+                     SELECT po.id AS purchase_order_id, \
+                     SUM(pl.quantity * uom_factor(pl.variant_id, pl.unit_id)) AS quantity \
+                     FROM purchase_orders po \
+                     JOIN purchase_order_lines pl ON pl.purchase_order_id = po.id \
+                     WHERE po.warehouse_id = r.warehouse_id AND pl.variant_id = r.variant_id \
+                     AND po.status IN ('DRAFT', 'PENDING', 'CONFIRMED') \
+                     GROUP BY po.id \
+                 ) ordered \
+             ) incoming ON true \
+             LEFT JOIN LATERAL ( \
+                 SELECT po.vendor_id, round(pl.unit_cost / uom_factor(pl.variant_id, pl.unit_id), 4) AS unit_cost \
+                 FROM purchase_order_lines pl \
+                 JOIN purchase_orders po ON po.id = pl.purchase_order_id \
+                 WHERE pl.variant_id = r.variant_id AND po.company_id = r.company_id \
+                 AND po.status <> 'CANCELLED' \
+                 ORDER BY po.vendor_id = r.vendor_id DESC NULLS LAST, \
+                 po.order_date DESC, po.created_at DESC \
+                 LIMIT 1 \
+             ) last ON true \
+             WHERE r.company_id = $1 AND r.is_active \
+             ORDER BY r.warehouse_id, r.variant_id",
+        )
+        .bind(company_id)
+        .bind(receipts::REFERENCE_TYPE)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        let mut by_vendor: BTreeMap<Option<Uuid>, Vec<Proposal>> = BTreeMap::new();
+        for mut proposal in rules {
+            proposal.projected = proposal.on_hand - proposal.reserved + proposal.incoming;
+            if proposal.projected > proposal.min_quantity {
+                continue;
+            }
+            proposal.quantity = order_quantity(&proposal);
+            by_vendor
+                .entry(proposal.vendor_id)
+                .or_default()
+                .push(proposal);
+        }
+
+        // Orderable vendors first, the ones without a vendor last
+        let mut proposals: Vec<VendorProposal> = by_vendor
+            .into_iter()
+            .map(|(vendor_id, lines)| VendorProposal { vendor_id, lines })
+            .collect();
+        proposals.sort_by_key(|p| p.vendor_id.is_none());
+        Ok(proposals)
+    }
+
+    /// Turns proposals into draft purchase orders in the company's base
+    /// currency, one per vendor and warehouse. Proposals without a vendor
+    /// are skipped.
+    pub async fn draft_orders(
+        &mut self,
+        company_id: Uuid,
+        proposals: &[VendorProposal],
+        order_date: NaiveDate,
+        created_by: Uuid,
+    ) -> Result<Vec<PurchaseOrder>> {
+        let currency_id: Option<Uuid> =
+            sqlx::query_scalar("SELECT base_currency_id FROM companies WHERE id = $1")
+                .bind(company_id)
+                .fetch_one(&mut *self.conn)
+                .await
+                .map_err(AppError::Database)?;
+        let currency_id = currency_id.ok_or_else(|| {
+            AppError::App("set the company's base currency before drafting purchase orders".into())
+        })?;
+
+        let mut orders = Vec::new();
+        for proposal in proposals {
+            let Some(vendor_id) = proposal.vendor_id else {
+                continue;
+            };
+            let mut by_warehouse: BTreeMap<Uuid, Vec<&Proposal>> = BTreeMap::new();
+            for line in &proposal.lines {
+                by_warehouse
+                    .entry(line.warehouse_id)
+                    .or_default()
+                    .push(line);
+            }
+
+            for (warehouse_id, lines) in by_warehouse {
+                let order = Repository::<PurchaseOrder>::new(&mut *self.conn)
+                    .create(CreatePurchaseOrder {
+                        company_id,
+                        vendor_id,
+                        order_date,
+                        currency_id,
+                        warehouse_id: Some(warehouse_id),
+                        status: OrderStatus::Draft,
+                        created_by,
+                    })
+                    .await?;
+
+                for line in lines {
+                    Repository::<PurchaseOrderLine>::new(&mut *self.conn)
+                        .create(CreatePurchaseOrderLine {
+                            purchase_order_id: order.id,
+                            variant_id: line.variant_id,
+                            quantity: line.quantity,
+                            unit_id: None,
+                            unit_cost: line.unit_cost,
+                        })
+                        .await?;
+                }
+
+                // Re-read for the total the line triggers maintain
+                let order = Repository::<PurchaseOrder>::new(&mut *self.conn)
+                    .get(order.id)
+                    .await?
+                    .unwrap_or(order);
+                orders.push(order);
+            }
+        }
+        Ok(orders)
+    }
+}
+
+/// Quantity to order: up to `max_quantity`, or enough multiples of
+/// `reorder_quantity` to get back above the reorder point, rounded up to
+/// the order multiple.
+fn order_quantity(proposal: &Proposal) -> Quantity {
+    let shortfall = proposal.min_quantity - proposal.projected;
+    let quantity = match (proposal.max_quantity, proposal.reorder_quantity) {
+        (Some(max), _) => max - proposal.projected,
+        (None, Some(step)) => {
+            let steps = (shortfall.value() / step.value()).floor() + Decimal::ONE;
+            Quantity::new(steps * step.value())
+        }
+        // The table requires one of the two
+        (None, None) => shortfall,
+    };
+
+    let multiple = proposal.order_multiple.value();
+    Quantity::new((quantity.value() / multiple).ceil() * multiple)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReplenishmentService;
+    use crate::config::NegativeStockPolicy;
+    use crate::db::enums::{MovementType, TrackingMode, UserRole};
+    use crate::db::inventory::{StockService, receipts};
+    use crate::db::models::CreateStockMovement;
+    use crate::db::money::{Money, Quantity};
+    use crate::db::testing;
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn receipts_count_against_the_whole_order() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let warehouse = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let vendor = testing::partner(conn, company).await;
+        let admin = testing::user(conn, UserRole::Admin).await;
+
+        let order: uuid::Uuid = sqlx::query_scalar(
+            "INSERT INTO purchase_orders \
+             (company_id, vendor_id, order_date, currency_id, warehouse_id, status, created_by) \
+             SELECT id, $2, CURRENT_DATE, base_currency_id, $3, 'CONFIRMED', $4 \
+             FROM companies WHERE id = $1 RETURNING id",
+        )
+        .bind(company)
+        .bind(vendor)
+        .bind(warehouse)
+        .bind(admin)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+        // The variant is ordered on two lines of 5
+        sqlx::query(
+            "INSERT INTO purchase_order_lines \
+             (purchase_order_id, variant_id, quantity, unit_cost, subtotal) \
+             VALUES ($1, $2, 5, 10, 0), ($1, $2, 5, 10, 0)",
+        )
+        .bind(order)
+        .bind(variant)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO reorder_rules (company_id, variant_id, warehouse_id, min_quantity, \
+             max_quantity) VALUES ($1, $2, $3, 10, 20)",
+        )
+        .bind(company)
+        .bind(variant)
+        .bind(warehouse)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        StockService::new(conn, NegativeStockPolicy::Forbid)
+            .post(CreateStockMovement {
+                company_id: company,
+                variant_id: variant,
+                warehouse_id: warehouse,
+                quantity: Quantity::from(4),
+                movement_type: MovementType::In,
+                reference_type: Some(receipts::REFERENCE_TYPE.to_string()),
+                reference_id: Some(order),
+                unit_cost: Some(Money::from(10)),
+                location_id: None,
+                lots: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        let proposals = ReplenishmentService::new(conn).run(company).await.unwrap();
+        let line = &proposals[0].lines[0];
+        assert_eq!(line.on_hand, Quantity::from(4));
+        assert_eq!(line.incoming, Quantity::from(6));
+        assert_eq!(line.quantity, Quantity::from(10));
+    }
+}
@@ -0,0 +1,386 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::config::NegativeStockPolicy;
+use crate::db::enums::{MovementType, OrderStatus};
+use crate::db::inventory::units::UnitUse;
+use crate::db::inventory::{
+    BomService, LocationService, LotService, StockPosting, StockService, UnitService, lots,
+};
+use crate::db::models::{CreateStockMovement, SalesOrder, StockReservation};
+use crate::db::money::Quantity;
+use crate::db::repositories::{Entity, Repository};
+use crate::error::{AppError, Result};
+
+/// `reference_type` of the movements posted when an order is delivered.
+pub const REFERENCE_TYPE: &str = "sales_order";
+
+const RESERVATION_COLUMNS: &str = "r.id, r.company_id, r.sales_order_line_id, r.variant_id, \
+    r.warehouse_id, r.quantity, r.created_at, r.released_at";
+
+/// Stock of one variant in one warehouse.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Availability {
+    pub variant_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub on_hand: Quantity,
+    pub reserved: Quantity,
+    /// On hand minus reserved: what can still be promised.
+    pub available: Quantity,
+}
+
+/// Holds stock for confirmed sales orders so it cannot be promised twice,
+/// and releases it when the order is cancelled or delivered. Postgres only;
+/// run inside a transaction.
+pub struct ReservationService<'c> {
+    conn: &'c mut PgConnection,
+    policy: NegativeStockPolicy,
+}
+
+impl<'c> ReservationService<'c> {
+    /// `policy` decides whether an order may reserve more than is available.
+    pub fn new(conn: &'c mut PgConnection, policy: NegativeStockPolicy) -> Self {
+        Self { conn, policy }
+    }
+
+    /// Confirms a draft or pending order and reserves its lines in
+    /// `warehouse_id`.
+    pub async fn confirm_order(
+        &mut self,
+        order_id: Uuid,
+        warehouse_id: Uuid,
+    ) -> Result<Vec<StockReservation>> {
+        let order = self.lock_order(order_id).await?;
+        if !matches!(order.status, OrderStatus::Draft | OrderStatus::Pending) {
+            return Err(AppError::App(format!(
+                "sales order is {}, only draft or pending orders can be confirmed",
+                order.status
+            )));
+        }
+        self.set_status(order_id, OrderStatus::Confirmed).await?;
+
+        let lines = Repository::<SalesOrder>::new(&mut *self.conn)
+            .lines(order_id)
+            .await?;
+        let mut parts = Vec::new();
+        for line in lines {
+            // Reservations are in the stock unit, whatever the line was entered in
+            let quantity = UnitService::new(&mut *self.conn)
+                .stock_quantity(line.variant_id, line.unit_id, line.quantity, UnitUse::Sales)
+                .await?;
+            // A kit reserves its components, so delivery ships those
+            for (variant_id, quantity) in BomService::new(&mut *self.conn)
+                .explode(line.variant_id, quantity)
+                .await?
+            {
+                parts.push((variant_id, line.id, quantity));
+            }
+        }
+        // A fixed lock order keeps two confirmations from deadlocking
+        parts.sort_by_key(|(variant_id, ..)| *variant_id);
+
+        let mut reservations = Vec::new();
+        for (variant_id, line_id, quantity) in parts {
+            let ledger = StockService::new(&mut *self.conn, self.policy)
+                .lock(variant_id, warehouse_id)
+                .await?;
+            if quantity > ledger.available() && self.policy == NegativeStockPolicy::Forbid {
+                return Err(AppError::InsufficientStock {
+                    variant_id,
+                    warehouse_id,
+                    on_hand: ledger.available(),
+                    requested: quantity,
+                });
+            }
+
+            let reservation: StockReservation = sqlx::query_as(&format!(
+                "INSERT INTO stock_reservations AS r \
+                 (company_id, sales_order_line_id, variant_id, warehouse_id, quantity) \
+                 VALUES ($1, $2, $3, $4, $5) RETURNING {}",
+                RESERVATION_COLUMNS
+            ))
+            .bind(order.company_id)
+            .bind(line_id)
+            .bind(variant_id)
+            .bind(warehouse_id)
+            .bind(quantity)
+            .fetch_one(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+
+            self.add_reserved(variant_id, warehouse_id, quantity)
+                .await?;
+            reservations.push(reservation);
+        }
+        Ok(reservations)
+    }
+
+    /// Cancels an open order and releases what it had reserved.
+    pub async fn cancel_order(&mut self, order_id: Uuid) -> Result<()> {
+        let order = self.lock_order(order_id).await?;
+        if order.status.is_closed() {
+            return Err(AppError::App(format!(
+                "sales order is already {}",
+                order.status
+            )));
+        }
+        self.release(order_id).await?;
+        self.set_status(order_id, OrderStatus::Cancelled).await
+    }
+
+    /// Ships a confirmed order from the warehouses its lines were reserved
+    /// in, releasing the reservations, and completes it.
+    pub async fn deliver_order(&mut self, order_id: Uuid) -> Result<Vec<StockPosting>> {
+        let order = self.lock_order(order_id).await?;
+        if order.status != OrderStatus::Confirmed {
+            return Err(AppError::App(format!(
+                "sales order is {}, only confirmed orders can be delivered",
+                order.status
+            )));
+        }
+
+        let released = self.release(order_id).await?;
+        let mut postings = Vec::new();
+        for reservation in released {
+            let mut lots = LotService::new(&mut *self.conn)
+                .pick(
+                    reservation.variant_id,
+                    reservation.warehouse_id,
+                    reservation.quantity,
+                )
+                .await?;
+            // One movement per bin the quantity is picked from
+            let picks = LocationService::new(&mut *self.conn)
+                .pick(
+                    reservation.variant_id,
+                    reservation.warehouse_id,
+                    reservation.quantity,
+                )
+                .await?;
+            let mut stock = StockService::new(&mut *self.conn, self.policy);
+            for pick in picks {
+                postings.push(
+                    stock
+                        .post(CreateStockMovement {
+                            company_id: order.company_id,
+                            variant_id: reservation.variant_id,
+                            warehouse_id: reservation.warehouse_id,
+                            quantity: pick.quantity,
+                            movement_type: MovementType::Out,
+                            reference_type: Some(REFERENCE_TYPE.into()),
+                            reference_id: Some(order_id),
+                            unit_cost: None,
+                            location_id: pick.location_id,
+                            lots: lots::take(&mut lots, pick.quantity),
+                        })
+                        .await?,
+                );
+            }
+        }
+
+        self.set_status(order_id, OrderStatus::Completed).await?;
+        Ok(postings)
+    }
+
+    /// Active reservations of an order.
+    pub async fn order_reservations(&mut self, order_id: Uuid) -> Result<Vec<StockReservation>> {
+        sqlx::query_as(&format!(
+            "SELECT {} FROM stock_reservations r \
+             JOIN sales_order_lines l ON l.id = r.sales_order_line_id \
+             WHERE l.sales_order_id = $1 AND r.released_at IS NULL ORDER BY l.id",
+            RESERVATION_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// On hand, reserved and available per warehouse for a company, or for
+    /// one of its variants.
+    pub async fn availability(
+        &mut self,
+        company_id: Uuid,
+        variant_id: Option<Uuid>,
+    ) -> Result<Vec<Availability>> {
+        sqlx::query_as(
+            "SELECT l.variant_id, l.warehouse_id, l.quantity AS on_hand, l.reserved, \
+             l.quantity - l.reserved AS available \
+             FROM stock_ledger l JOIN warehouses w ON w.id = l.warehouse_id \
+             WHERE w.company_id = $1 AND ($2::uuid IS NULL OR l.variant_id = $2) \
+             ORDER BY l.variant_id, w.name",
+        )
+        .bind(company_id)
+        .bind(variant_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Releases every active reservation of an order and returns them.
+    async fn release(&mut self, order_id: Uuid) -> Result<Vec<StockReservation>> {
+        let released: Vec<StockReservation> = sqlx::query_as(&format!(
+            "UPDATE stock_reservations r SET released_at = now() \
+             FROM sales_order_lines l \
+             WHERE l.id = r.sales_order_line_id AND l.sales_order_id = $1 \
+             AND r.released_at IS NULL RETURNING {}",
+            RESERVATION_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        for reservation in &released {
+            self.add_reserved(
+                reservation.variant_id,
+                reservation.warehouse_id,
+                -reservation.quantity,
+            )
+            .await?;
+        }
+        Ok(released)
+    }
+
+    async fn add_reserved(
+        &mut self,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+        quantity: Quantity,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE stock_ledger SET reserved = reserved + $3 \
+             WHERE variant_id = $1 AND warehouse_id = $2",
+        )
+        .bind(variant_id)
+        .bind(warehouse_id)
+        .bind(quantity)
+        .execute(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    async fn lock_order(&mut self, order_id: Uuid) -> Result<SalesOrder> {
+        sqlx::query_as(&format!(
+            "SELECT {} FROM sales_orders WHERE id = $1 FOR UPDATE",
+            SalesOrder::COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::App(format!("sales order {} not found", order_id)))
+    }
+
+    async fn set_status(&mut self, order_id: Uuid, status: OrderStatus) -> Result<()> {
+        sqlx::query("UPDATE sales_orders SET status = $2 WHERE id = $1")
+            .bind(order_id)
+            .bind(status)
+            .execute(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::ReservationService;
+    use crate::config::NegativeStockPolicy;
+    use crate::db::enums::{MovementType, OrderStatus, TrackingMode, UserRole};
+    use crate::db::inventory::StockService;
+    use crate::db::models::{
+        CreateSalesOrder, CreateSalesOrderLine, CreateStockMovement, SalesOrder, SalesOrderLine,
+        UpdateSalesOrder,
+    };
+    use crate::db::money::{Money, Quantity};
+    use crate::db::repositories::Repository;
+    use crate::db::testing;
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn confirmed_orders_are_only_cancelled_with_their_reservations() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let warehouse = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let customer = testing::partner(conn, company).await;
+        let admin = testing::user(conn, UserRole::Admin).await;
+        let currency = sqlx::query_scalar("SELECT base_currency_id FROM companies WHERE id = $1")
+            .bind(company)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+
+        StockService::new(conn, NegativeStockPolicy::Forbid)
+            .post(CreateStockMovement {
+                company_id: company,
+                variant_id: variant,
+                warehouse_id: warehouse,
+                quantity: Quantity::from(5),
+                movement_type: MovementType::In,
+                reference_type: None,
+                reference_id: None,
+                unit_cost: Some(Money::from(10)),
+                location_id: None,
+                lots: Vec::new(),
+            })
+            .await
+            .unwrap();
+        let order = Repository::<SalesOrder>::new(conn)
+            .create(CreateSalesOrder {
+                company_id: company,
+                customer_id: customer,
+                order_date: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap(),
+                currency_id: currency,
+                status: OrderStatus::Draft,
+                created_by: admin,
+            })
+            .await
+            .unwrap();
+        Repository::<SalesOrderLine>::new(conn)
+            .create(CreateSalesOrderLine {
+                sales_order_id: order.id,
+                variant_id: variant,
+                quantity: Quantity::from(2),
+                unit_id: None,
+                unit_price: Money::from(20),
+            })
+            .await
+            .unwrap();
+        ReservationService::new(conn, NegativeStockPolicy::Forbid)
+            .confirm_order(order.id, warehouse)
+            .await
+            .unwrap();
+
+        // The repository leaves a confirmed order alone
+        let mut orders = Repository::<SalesOrder>::new(conn);
+        assert!(!orders.archive(order.id).await.unwrap());
+        let update = UpdateSalesOrder {
+            order_date: NaiveDate::from_ymd_opt(2026, 1, 2),
+            ..Default::default()
+        };
+        assert!(orders.update(order.id, update).await.unwrap().is_none());
+
+        let mut reservations = ReservationService::new(conn, NegativeStockPolicy::Forbid);
+        reservations.cancel_order(order.id).await.unwrap();
+        let availability = reservations
+            .availability(company, Some(variant))
+            .await
+            .unwrap();
+        assert_eq!(availability[0].reserved, Quantity::ZERO);
+        assert_eq!(availability[0].available, Quantity::from(5));
+        let order = Repository::<SalesOrder>::new(conn)
+            .get(order.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(order.status, OrderStatus::Cancelled);
+    }
+}
@@ -0,0 +1,306 @@
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::config::NegativeStockPolicy;
+use crate::db::enums::{MovementType, TrackingMode};
+use crate::db::inventory::{LocationService, LotService, ValuationService};
+use crate::db::models::{CreateStockMovement, StockLedger, StockMovement};
+use crate::db::money::{Money, Quantity};
+use crate::db::repositories::Entity;
+use crate::error::{AppError, Result};
+
+/// A recorded movement and the stock it left behind.
+#[derive(Debug, Clone)]
+pub struct StockPosting {
+    pub movement: StockMovement,
+    /// Ledger quantity for the variant and warehouse after the movement.
+    pub on_hand: Quantity,
+    /// Value received, or cost of goods sold for stock going out.
+    pub cost: Money,
+    /// The movement took stock below zero under `NegativeStockPolicy::Warn`.
+    pub negative: bool,
+}
+
+/// Records stock movements and keeps `stock_ledger` in step with them.
+/// Run it inside a transaction (`UnitOfWork::stock`) so the movement and the
+/// ledger change commit together. Postgres only.
+pub struct StockService<'c> {
+    conn: &'c mut PgConnection,
+    policy: NegativeStockPolicy,
+}
+
+impl<'c> StockService<'c> {
+    pub fn new(conn: &'c mut PgConnection, policy: NegativeStockPolicy) -> Self {
+        Self { conn, policy }
+    }
+
+    pub async fn on_hand(&mut self, variant_id: Uuid, warehouse_id: Uuid) -> Result<Quantity> {
+        let quantity = sqlx::query_scalar(
+            "SELECT quantity FROM stock_ledger WHERE variant_id = $1 AND warehouse_id = $2",
+        )
+        .bind(variant_id)
+        .bind(warehouse_id)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(quantity.unwrap_or_default())
+    }
+
+    pub async fn post(&mut self, mut input: CreateStockMovement) -> Result<StockPosting> {
+        validate(&input)?;
+        let delta = input.movement_type.signed(input.quantity);
+        let lots = std::mem::take(&mut input.lots);
+        let tracking = LotService::new(&mut *self.conn)
+            .check(input.variant_id, delta, &lots)
+            .await?;
+
+        let before = self.lock(input.variant_id, input.warehouse_id).await?;
+        let on_hand = before.quantity;
+        let after = on_hand + delta;
+        let negative = delta.is_negative() && after.is_negative();
+        if negative && self.policy == NegativeStockPolicy::Forbid {
+            return Err(AppError::InsufficientStock {
+                variant_id: input.variant_id,
+                warehouse_id: input.warehouse_id,
+                on_hand,
+                requested: -delta,
+            });
+        }
+
+        // Adjustments change the bin they name, else stock outside bins
+        if input.location_id.is_none() && input.movement_type != MovementType::Adjustment {
+            input.location_id = LocationService::new(&mut *self.conn)
+                .resolve(input.variant_id, input.warehouse_id, delta)
+                .await?;
+        }
+
+        let mut movement: StockMovement = sqlx::query_as(&format!(
+            "INSERT INTO stock_movements (company_id, variant_id, warehouse_id, quantity, \
+             movement_type, reference_type, reference_id, unit_cost, location_id) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING {}",
+            StockMovement::COLUMNS
+        ))
+        .bind(input.company_id)
+        .bind(input.variant_id)
+        .bind(input.warehouse_id)
+        .bind(input.quantity)
+        .bind(input.movement_type)
+        .bind(input.reference_type)
+        .bind(input.reference_id)
+        .bind(input.unit_cost)
+        .bind(input.location_id)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+        LocationService::new(&mut *self.conn)
+            .apply(&movement)
+            .await?;
+        if tracking != TrackingMode::Untracked {
+            LotService::new(&mut *self.conn)
+                .apply(&movement, tracking, &lots)
+                .await?;
+        }
+        let valuation = ValuationService::new(&mut *self.conn)
+            .apply(&movement, &before)
+            .await?;
+
+        if movement.unit_cost != Some(valuation.unit_cost) {
+            sqlx::query("UPDATE stock_movements SET unit_cost = $2 WHERE id = $1")
+                .bind(movement.id)
+                .bind(valuation.unit_cost)
+                .execute(&mut *self.conn)
+                .await
+                .map_err(AppError::Database)?;
+            movement.unit_cost = Some(valuation.unit_cost);
+        }
+
+        sqlx::query(
+            "UPDATE stock_ledger SET quantity = $3, value = $4 \
+             WHERE variant_id = $1 AND warehouse_id = $2",
+        )
+        .bind(movement.variant_id)
+        .bind(movement.warehouse_id)
+        .bind(after)
+        .bind(valuation.value)
+        .execute(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(StockPosting {
+            movement,
+            on_hand: after,
+            cost: valuation.cost,
+            negative: negative && self.policy == NegativeStockPolicy::Warn,
+        })
+    }
+
+    /// Posts the movements of one document in order; the first failure
+    /// stops the rest, and the caller's transaction undoes the ones before.
+    pub async fn post_all(
+        &mut self,
+        inputs: impl IntoIterator<Item = CreateStockMovement>,
+    ) -> Result<Vec<StockPosting>> {
+        let mut postings = Vec::new();
+        for input in inputs {
+            postings.push(self.post(input).await?);
+        }
+        Ok(postings)
+    }
+
+    /// Locks the ledger row, creating it at zero on first use. Concurrent
+    /// postings and reservations for the same row queue up here.
+    pub(crate) async fn lock(
+        &mut self,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+    ) -> Result<StockLedger> {
+        sqlx::query(
+            "INSERT INTO stock_ledger (variant_id, warehouse_id, quantity) VALUES ($1, $2, 0) \
+             ON CONFLICT (variant_id, warehouse_id) DO NOTHING",
+        )
+        .bind(variant_id)
+        .bind(warehouse_id)
+        .execute(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        sqlx::query_as(
+            "SELECT variant_id, warehouse_id, quantity, value, reserved FROM stock_ledger \
+             WHERE variant_id = $1 AND warehouse_id = $2 FOR UPDATE",
+        )
+        .bind(variant_id)
+        .bind(warehouse_id)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+}
+
+fn validate(input: &CreateStockMovement) -> Result<()> {
+    match input.movement_type {
+        MovementType::Adjustment if input.quantity.is_zero() => Err(AppError::App(
+            "an adjustment must change the quantity".into(),
+        )),
+        MovementType::In | MovementType::Out if input.quantity <= Quantity::ZERO => {
+            Err(AppError::App(format!(
+                "{} movements need a positive quantity, got {}",
+                input.movement_type, input.quantity
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{StockService, validate};
+    use crate::config::NegativeStockPolicy;
+    use crate::db::enums::{MovementType, TrackingMode};
+    use crate::db::models::CreateStockMovement;
+    use crate::db::money::{Money, Quantity};
+    use crate::db::testing;
+    use crate::error::AppError;
+
+    fn movement(
+        company_id: Uuid,
+        warehouse_id: Uuid,
+        variant_id: Uuid,
+        movement_type: MovementType,
+        quantity: i64,
+    ) -> CreateStockMovement {
+        CreateStockMovement {
+            company_id,
+            variant_id,
+            warehouse_id,
+            quantity: Quantity::from(quantity),
+            movement_type,
+            reference_type: None,
+            reference_id: None,
+            unit_cost: Some(Money::from(10)),
+            location_id: None,
+            lots: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn quantities_must_move_stock() {
+        let id = Uuid::new_v4();
+        for (movement_type, quantity) in [
+            (MovementType::In, 0),
+            (MovementType::Out, -1),
+            (MovementType::Adjustment, 0),
+        ] {
+            assert!(validate(&movement(id, id, id, movement_type, quantity)).is_err());
+        }
+        assert!(validate(&movement(id, id, id, MovementType::Adjustment, -1)).is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn postings_keep_the_ledger_in_step() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let warehouse = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let movement = |movement_type, quantity| {
+            movement(company, warehouse, variant, movement_type, quantity)
+        };
+
+        let mut stock = StockService::new(conn, NegativeStockPolicy::Forbid);
+        let postings = stock
+            .post_all([
+                movement(MovementType::In, 5),
+                movement(MovementType::Out, 2),
+                movement(MovementType::Adjustment, -1),
+            ])
+            .await
+            .unwrap();
+        let on_hand: Vec<_> = postings.iter().map(|posting| posting.on_hand).collect();
+        assert_eq!(on_hand, [5, 3, 2].map(Quantity::from));
+        assert_eq!(
+            stock.on_hand(variant, warehouse).await.unwrap(),
+            Quantity::from(2)
+        );
+        let value: Money =
+            sqlx::query_scalar("SELECT value FROM stock_ledger WHERE variant_id = $1")
+                .bind(variant)
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+        assert_eq!(value, Money::from(20));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn negative_stock_follows_the_policy() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let warehouse = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let issue = movement(company, warehouse, variant, MovementType::Out, 1);
+
+        let forbidden = StockService::new(conn, NegativeStockPolicy::Forbid)
+            .post(issue.clone())
+            .await;
+        assert!(matches!(forbidden, Err(AppError::InsufficientStock { .. })));
+        let posted: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM stock_movements WHERE variant_id = $1")
+                .bind(variant)
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+        assert_eq!(posted, 0);
+
+        let warned = StockService::new(conn, NegativeStockPolicy::Warn)
+            .post(issue)
+            .await
+            .unwrap();
+        assert!(warned.negative);
+        assert_eq!(warned.on_hand, Quantity::from(-1));
+    }
+}
@@ -0,0 +1,486 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::config::NegativeStockPolicy;
+use crate::db::enums::{MovementType, TransferStatus};
+use crate::db::inventory::{LocationService, LotService, StockService, lots, valuation};
+use crate::db::models::{CreateStockMovement, LotQuantity, StockTransfer, StockTransferLine};
+use crate::db::money::{Money, Quantity};
+use crate::db::repositories::{Entity, Repository};
+use crate::error::{AppError, Result};
+
+/// `reference_type` of the movements posted for a transfer; their
+/// `reference_id` is the transfer's id.
+pub const REFERENCE_TYPE: &str = "stock_transfer";
+
+/// A shipped line that has not arrived yet.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct InTransit {
+    pub transfer_id: Uuid,
+    pub variant_id: Uuid,
+    pub from_warehouse_id: Uuid,
+    pub to_warehouse_id: Uuid,
+    pub quantity: Quantity,
+    pub cost: Money,
+    pub shipped_at: DateTime<Utc>,
+}
+
+/// Moves transfers from draft to shipped to received, or cancels them,
+/// posting their stock movements. Postgres only; run inside a transaction.
+pub struct TransferService<'c> {
+    conn: &'c mut PgConnection,
+    policy: NegativeStockPolicy,
+}
+
+impl<'c> TransferService<'c> {
+    pub fn new(conn: &'c mut PgConnection, policy: NegativeStockPolicy) -> Self {
+        Self { conn, policy }
+    }
+
+    /// Takes the goods out of the source warehouse. Each line records the
+    /// cost it was issued at, which the destination receives them at.
+    pub async fn ship(&mut self, transfer_id: Uuid) -> Result<StockTransfer> {
+        let transfer = self.lock(transfer_id, &[TransferStatus::Draft]).await?;
+        let lines = self.lines(transfer_id).await?;
+        if lines.is_empty() {
+            return Err(AppError::App("cannot ship a transfer without lines".into()));
+        }
+
+        for line in lines {
+            let mut lots = LotService::new(&mut *self.conn)
+                .pick(line.variant_id, transfer.from_warehouse_id, line.quantity)
+                .await?;
+            // One movement per bin the quantity is picked from
+            let picks = LocationService::new(&mut *self.conn)
+                .pick(line.variant_id, transfer.from_warehouse_id, line.quantity)
+                .await?;
+            let mut cost = Money::ZERO;
+            for pick in picks {
+                let posting = StockService::new(&mut *self.conn, self.policy)
+                    .post(CreateStockMovement {
+                        company_id: transfer.company_id,
+                        variant_id: line.variant_id,
+                        warehouse_id: transfer.from_warehouse_id,
+                        quantity: pick.quantity,
+                        movement_type: MovementType::Out,
+                        reference_type: Some(REFERENCE_TYPE.into()),
+                        reference_id: Some(transfer_id),
+                        unit_cost: None,
+                        location_id: pick.location_id,
+                        lots: lots::take(&mut lots, pick.quantity),
+                    })
+                    .await?;
+                cost += posting.cost;
+            }
+
+            sqlx::query("UPDATE stock_transfer_lines SET cost = $2 WHERE id = $1")
+                .bind(line.id)
+                .bind(cost)
+                .execute(&mut *self.conn)
+                .await
+                .map_err(AppError::Database)?;
+        }
+
+        self.set_status(transfer_id, TransferStatus::Shipped, Some("shipped_at"))
+            .await
+    }
+
+    /// Books the goods into the destination warehouse at their shipped cost,
+    /// in the lots they were shipped in.
+    pub async fn receive(&mut self, transfer_id: Uuid) -> Result<StockTransfer> {
+        let transfer = self.lock(transfer_id, &[TransferStatus::Shipped]).await?;
+        self.book_in(&transfer, transfer.to_warehouse_id).await?;
+        self.set_status(transfer_id, TransferStatus::Received, Some("received_at"))
+            .await
+    }
+
+    /// Calls off a draft or shipped transfer. Goods already shipped go back
+    /// into the source warehouse the way `receive` would have booked them
+    /// into the destination.
+    pub async fn cancel(&mut self, transfer_id: Uuid) -> Result<StockTransfer> {
+        let transfer = self
+            .lock(
+                transfer_id,
+                &[TransferStatus::Draft, TransferStatus::Shipped],
+            )
+            .await?;
+        if transfer.status == TransferStatus::Shipped {
+            self.book_in(&transfer, transfer.from_warehouse_id).await?;
+        }
+        self.set_status(transfer_id, TransferStatus::Cancelled, None)
+            .await
+    }
+
+    /// Posts the shipped lines into `warehouse_id` at exactly their shipped
+    /// cost.
+    async fn book_in(&mut self, transfer: &StockTransfer, warehouse_id: Uuid) -> Result<()> {
+        let mut shipped_lots: HashMap<Uuid, Vec<LotQuantity>> = HashMap::new();
+        for line in self.lines(transfer.id).await? {
+            let Some(cost) = line.cost else {
+                return Err(AppError::App(format!(
+                    "transfer line {} was never shipped",
+                    line.id
+                )));
+            };
+            if let Entry::Vacant(entry) = shipped_lots.entry(line.variant_id) {
+                entry.insert(self.shipped_lots(transfer, line.variant_id).await?);
+            }
+            let lots = shipped_lots.entry(line.variant_id).or_default();
+
+            for (quantity, unit_cost) in valuation::cost_parts(line.quantity, cost) {
+                StockService::new(&mut *self.conn, self.policy)
+                    .post(CreateStockMovement {
+                        company_id: transfer.company_id,
+                        variant_id: line.variant_id,
+                        warehouse_id,
+                        quantity,
+                        movement_type: MovementType::In,
+                        reference_type: Some(REFERENCE_TYPE.into()),
+                        reference_id: Some(transfer.id),
+                        unit_cost: Some(unit_cost),
+                        location_id: None,
+                        lots: lots::take(lots, quantity),
+                    })
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Goods shipped but not yet received, oldest first.
+    pub async fn in_transit(&mut self, company_id: Uuid) -> Result<Vec<InTransit>> {
+        sqlx::query_as(
+            "SELECT t.id AS transfer_id, l.variant_id, t.from_warehouse_id, t.to_warehouse_id, \
+             l.quantity, COALESCE(l.cost, 0) AS cost, t.shipped_at \
+             FROM stock_transfers t JOIN stock_transfer_lines l ON l.transfer_id = t.id \
+             WHERE t.company_id = $1 AND t.status = 'SHIPPED' \
+             ORDER BY t.shipped_at, t.id, l.id",
+        )
+        .bind(company_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Lots a variant left the source warehouse in for this transfer,
+    /// soonest expiry first.
+    async fn shipped_lots(
+        &mut self,
+        transfer: &StockTransfer,
+        variant_id: Uuid,
+    ) -> Result<Vec<LotQuantity>> {
+        let lots: Vec<(String, Option<NaiveDate>, Quantity)> = sqlx::query_as(
+            "SELECT l.number, l.expires_on, SUM(ml.quantity) \
+             FROM stock_movements m \
+             JOIN stock_movement_lots ml ON ml.movement_id = m.id \
+             JOIN stock_lots l ON l.id = ml.lot_id \
+             WHERE m.reference_type = $1 AND m.reference_id = $2 AND m.variant_id = $3 \
+             AND m.warehouse_id = $4 AND m.movement_type = 'out' \
+             GROUP BY l.id ORDER BY l.expires_on NULLS LAST, l.created_at, l.number",
+        )
+        .bind(REFERENCE_TYPE)
+        .bind(transfer.id)
+        .bind(variant_id)
+        .bind(transfer.from_warehouse_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(lots
+            .into_iter()
+            .map(|(number, expires_on, quantity)| LotQuantity {
+                number,
+                expires_on,
+                quantity,
+            })
+            .collect())
+    }
+
+    async fn lock(
+        &mut self,
+        transfer_id: Uuid,
+        expected: &[TransferStatus],
+    ) -> Result<StockTransfer> {
+        let transfer: StockTransfer = sqlx::query_as(&format!(
+            "SELECT {} FROM stock_transfers WHERE id = $1 FOR UPDATE",
+            StockTransfer::COLUMNS
+        ))
+        .bind(transfer_id)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::App(format!("transfer {} not found", transfer_id)))?;
+
+        if !expected.contains(&transfer.status) {
+            let expected: Vec<_> = expected.iter().map(ToString::to_string).collect();
+            return Err(AppError::App(format!(
+                "transfer is {}, expected {}",
+                transfer.status,
+                expected.join(" or ")
+            )));
+        }
+        Ok(transfer)
+    }
+
+    async fn lines(&mut self, transfer_id: Uuid) -> Result<Vec<StockTransferLine>> {
+        Repository::<StockTransfer>::new(&mut *self.conn)
+            .lines(transfer_id)
+            .await
+    }
+
+    async fn set_status(
+        &mut self,
+        transfer_id: Uuid,
+        status: TransferStatus,
+        timestamp: Option<&str>,
+    ) -> Result<StockTransfer> {
+        let stamp = timestamp.map_or_else(String::new, |column| format!(", {} = now()", column));
+        sqlx::query_as(&format!(
+            "UPDATE stock_transfers SET status = $2{} WHERE id = $1 RETURNING {}",
+            stamp,
+            StockTransfer::COLUMNS
+        ))
+        .bind(transfer_id)
+        .bind(status)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Connection;
+    use uuid::Uuid;
+
+    use super::TransferService;
+    use crate::config::NegativeStockPolicy;
+    use crate::db::enums::{MovementType, TrackingMode, TransferStatus, UserRole};
+    use crate::db::inventory::StockService;
+    use crate::db::models::{
+        CreateStockMovement, CreateStockTransfer, CreateStockTransferLine, StockTransfer,
+        StockTransferLine,
+    };
+    use crate::db::money::{Money, Quantity};
+    use crate::db::repositories::Repository;
+    use crate::db::testing;
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn lines_cannot_be_added_once_shipped() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let source = testing::warehouse(conn, company).await;
+        let destination = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let admin = testing::user(conn, UserRole::Admin).await;
+        StockService::new(conn, NegativeStockPolicy::Forbid)
+            .post(CreateStockMovement {
+                company_id: company,
+                variant_id: variant,
+                warehouse_id: source,
+                quantity: Quantity::from(4),
+                movement_type: MovementType::In,
+                reference_type: None,
+                reference_id: None,
+                unit_cost: Some(Money::from(10)),
+                location_id: None,
+                lots: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        let transfer = Repository::<StockTransfer>::new(conn)
+            .create(CreateStockTransfer {
+                company_id: company,
+                from_warehouse_id: source,
+                to_warehouse_id: destination,
+                notes: None,
+                created_by: admin,
+            })
+            .await
+            .unwrap();
+        let line = CreateStockTransferLine {
+            transfer_id: transfer.id,
+            variant_id: variant,
+            quantity: Quantity::from(2),
+        };
+        Repository::<StockTransferLine>::new(conn)
+            .create(line.clone())
+            .await
+            .unwrap();
+        TransferService::new(conn, NegativeStockPolicy::Forbid)
+            .ship(transfer.id)
+            .await
+            .unwrap();
+
+        let mut savepoint = conn.begin().await.unwrap();
+        let late = Repository::<StockTransferLine>::new(&mut savepoint)
+            .create(line)
+            .await;
+        assert!(late.unwrap_err().to_string().contains("draft"));
+        savepoint.rollback().await.unwrap();
+
+        let received = TransferService::new(conn, NegativeStockPolicy::Forbid)
+            .receive(transfer.id)
+            .await
+            .unwrap();
+        assert_eq!(received.status, TransferStatus::Received);
+        let on_hand = StockService::new(conn, NegativeStockPolicy::Forbid)
+            .on_hand(variant, destination)
+            .await
+            .unwrap();
+        assert_eq!(on_hand, Quantity::from(2));
+    }
+
+    async fn receipt(
+        conn: &mut sqlx::PgConnection,
+        company: Uuid,
+        variant: Uuid,
+        warehouse: Uuid,
+        quantity: i64,
+        unit_cost: i64,
+    ) {
+        StockService::new(conn, NegativeStockPolicy::Forbid)
+            .post(CreateStockMovement {
+                company_id: company,
+                variant_id: variant,
+                warehouse_id: warehouse,
+                quantity: Quantity::from(quantity),
+                movement_type: MovementType::In,
+                reference_type: None,
+                reference_id: None,
+                unit_cost: Some(Money::from(unit_cost)),
+                location_id: None,
+                lots: Vec::new(),
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn shipped(
+        conn: &mut sqlx::PgConnection,
+        company: Uuid,
+        source: Uuid,
+        destination: Uuid,
+        variant: Uuid,
+        quantity: i64,
+    ) -> Uuid {
+        let admin = testing::user(conn, UserRole::Admin).await;
+        let transfer = Repository::<StockTransfer>::new(&mut *conn)
+            .create(CreateStockTransfer {
+                company_id: company,
+                from_warehouse_id: source,
+                to_warehouse_id: destination,
+                notes: None,
+                created_by: admin,
+            })
+            .await
+            .unwrap();
+        Repository::<StockTransferLine>::new(&mut *conn)
+            .create(CreateStockTransferLine {
+                transfer_id: transfer.id,
+                variant_id: variant,
+                quantity: Quantity::from(quantity),
+            })
+            .await
+            .unwrap();
+        TransferService::new(conn, NegativeStockPolicy::Forbid)
+            .ship(transfer.id)
+            .await
+            .unwrap();
+        transfer.id
+    }
+
+    async fn value(conn: &mut sqlx::PgConnection, variant: Uuid, warehouse: Uuid) -> Money {
+        sqlx::query_scalar(
+            "SELECT value FROM stock_ledger WHERE variant_id = $1 AND warehouse_id = $2",
+        )
+        .bind(variant)
+        .bind(warehouse)
+        .fetch_one(conn)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn the_destination_takes_in_the_exact_shipped_cost() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let source = testing::warehouse(conn, company).await;
+        let destination = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        receipt(conn, company, variant, source, 2, 3).await;
+        receipt(conn, company, variant, source, 1, 4).await;
+
+        // 10 over three units leaves a residue on one of them
+        let transfer = shipped(conn, company, source, destination, variant, 3).await;
+        TransferService::new(conn, NegativeStockPolicy::Forbid)
+            .receive(transfer)
+            .await
+            .unwrap();
+        assert_eq!(value(conn, variant, destination).await, Money::from(10));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn cancelling_a_shipped_transfer_returns_the_goods() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let source = testing::warehouse(conn, company).await;
+        let destination = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        receipt(conn, company, variant, source, 4, 10).await;
+
+        let transfer = shipped(conn, company, source, destination, variant, 3).await;
+        let mut transfers = TransferService::new(conn, NegativeStockPolicy::Forbid);
+        let cancelled = transfers.cancel(transfer).await.unwrap();
+        assert_eq!(cancelled.status, TransferStatus::Cancelled);
+        assert!(transfers.cancel(transfer).await.is_err());
+        assert!(transfers.in_transit(company).await.unwrap().is_empty());
+
+        let mut stock = StockService::new(conn, NegativeStockPolicy::Forbid);
+        assert_eq!(
+            stock.on_hand(variant, source).await.unwrap(),
+            Quantity::from(4)
+        );
+        assert_eq!(
+            stock.on_hand(variant, destination).await.unwrap(),
+            Quantity::ZERO
+        );
+        assert_eq!(value(conn, variant, source).await, Money::from(40));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn warehouses_must_belong_to_the_company() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let other = testing::company(conn).await;
+        let source = testing::warehouse(conn, company).await;
+        let foreign = testing::warehouse(conn, other).await;
+        let admin = testing::user(conn, UserRole::Admin).await;
+
+        let created = Repository::<StockTransfer>::new(conn)
+            .create(CreateStockTransfer {
+                company_id: company,
+                from_warehouse_id: source,
+                to_warehouse_id: foreign,
+                notes: None,
+                created_by: admin,
+            })
+            .await;
+        assert!(created.is_err());
+    }
+}
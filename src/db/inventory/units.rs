@@ -0,0 +1,243 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::db::enums::RoundingMode;
+use crate::db::money::{Factor, Quantity};
+use crate::error::{AppError, Result};
+
+/// Which side of the business a quantity was entered on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitUse {
+    Purchase,
+    Sales,
+}
+
+/// A unit a variant can be entered in, with what one of it is in the
+/// variant's stock unit.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AllowedUnit {
+    pub unit_id: Uuid,
+    pub symbol: String,
+    pub name: String,
+    pub factor: Factor,
+}
+
+/// Converts quantities entered in a purchase or sales unit to the variant's
+/// stock unit, the only unit stock movements are kept in. The conversion
+/// itself is the `uom_factor` SQL function, which reports and queries share.
+/// Postgres only.
+pub struct UnitService<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> UnitService<'c> {
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Units an order line for the variant may use, the stock unit first.
+    pub async fn allowed_units(
+        &mut self,
+        variant_id: Uuid,
+        usage: UnitUse,
+    ) -> Result<Vec<AllowedUnit>> {
+        sqlx::query_as(
+            "SELECT u.id AS unit_id, u.symbol, u.name, uom_factor(v.id, u.id) AS factor \
+             FROM product_variants v JOIN units_of_measure u ON u.id = v.stock_unit_id \
+             WHERE v.id = $1 \
+             UNION ALL \
+             SELECT * FROM ( \
+                 SELECT u.id, u.symbol, u.name, uom_factor(vu.variant_id, u.id) \
+                 FROM variant_units vu JOIN units_of_measure u ON u.id = vu.unit_id \
+                 WHERE vu.variant_id = $1 AND u.is_active \
+                 AND CASE WHEN $2 THEN vu.for_purchase ELSE vu.for_sales END \
+                 AND uom_factor(vu.variant_id, u.id) IS NOT NULL \
+                 ORDER BY u.symbol \
+             ) alternates",
+        )
+        .bind(variant_id)
+        .bind(usage == UnitUse::Purchase)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Stock units per one `unit_id`; `None` is the stock unit itself.
+    pub async fn factor(
+        &mut self,
+        variant_id: Uuid,
+        unit_id: Option<Uuid>,
+        usage: UnitUse,
+    ) -> Result<Factor> {
+        let factor: Option<Option<Factor>> = sqlx::query_scalar(
+            "SELECT uom_factor(v.id, $2) FROM product_variants v \
+             WHERE v.id = $1 AND ($2::uuid IS NULL OR $2 = v.stock_unit_id OR EXISTS ( \
+                 SELECT 1 FROM variant_units vu \
+                 WHERE vu.variant_id = v.id AND vu.unit_id = $2 \
+                 AND CASE WHEN $3 THEN vu.for_purchase ELSE vu.for_sales END))",
+        )
+        .bind(variant_id)
+        .bind(unit_id)
+        .bind(usage == UnitUse::Purchase)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        match factor {
+            Some(Some(factor)) => Ok(factor),
+            Some(None) => Err(AppError::App(
+                "the unit does not convert to the variant's stock unit; give the variant a factor for it"
+                    .into(),
+            )),
+            None => Err(AppError::App(format!(
+                "the unit is not allowed for {} of this variant",
+                match usage {
+                    UnitUse::Purchase => "purchases",
+                    UnitUse::Sales => "sales",
+                }
+            ))),
+        }
+    }
+
+    /// `quantity` of `unit_id` in the variant's stock unit.
+    pub async fn stock_quantity(
+        &mut self,
+        variant_id: Uuid,
+        unit_id: Option<Uuid>,
+        quantity: Quantity,
+        usage: UnitUse,
+    ) -> Result<Quantity> {
+        if unit_id.is_none() {
+            return Ok(quantity);
+        }
+        let factor = self.factor(variant_id, unit_id, usage).await?;
+        Ok((quantity * factor).round(Quantity::SCALE, RoundingMode::HalfUp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Connection;
+    use uuid::Uuid;
+
+    use crate::db::enums::{TrackingMode, UserRole};
+    use crate::db::models::{
+        CreatePurchaseOrderLine, CreateSalesOrderLine, PurchaseOrderLine, SalesOrderLine,
+        UpdateSalesOrderLine,
+    };
+    use crate::db::money::{Money, Quantity};
+    use crate::db::repositories::Repository;
+    use crate::db::testing;
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn order_lines_only_take_allowed_units() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let partner = testing::partner(conn, company).await;
+        let admin = testing::user(conn, UserRole::Admin).await;
+
+        // Stocked in pieces, bought but never sold in cartons of 12
+        let (piece, carton): (Uuid, Uuid) = sqlx::query_as(
+            "WITH category AS ( \
+                 INSERT INTO uom_categories (company_id, name) VALUES ($1, 'Count') RETURNING id \
+             ), units AS ( \
+                 INSERT INTO units_of_measure (company_id, category_id, name, symbol, factor) \
+                 SELECT $1, id, name, symbol, 1 FROM category, \
+                 (VALUES ('Piece', 'pc'), ('Carton', 'ctn')) u (name, symbol) \
+                 RETURNING id, symbol \
+             ) SELECT (SELECT id FROM units WHERE symbol = 'pc'), \
+             (SELECT id FROM units WHERE symbol = 'ctn')",
+        )
+        .bind(company)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+        sqlx::query(
+            "WITH stock AS (UPDATE product_variants SET stock_unit_id = $2 WHERE id = $1) \
+             INSERT INTO variant_units (variant_id, unit_id, factor, for_sales) \
+             VALUES ($1, $3, 12, false)",
+        )
+        .bind(variant)
+        .bind(piece)
+        .bind(carton)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+
+        let order = |table: &str| {
+            format!(
+                "INSERT INTO {} (company_id, {}, order_date, currency_id, created_by) \
+                 SELECT id, $2, CURRENT_DATE, base_currency_id, $3 FROM companies \
+                 WHERE id = $1 RETURNING id",
+                table,
+                if table == "sales_orders" {
+                    "customer_id"
+                } else {
+                    "vendor_id"
+                },
+            )
+        };
+        let mut ids = Vec::new();
+        for table in ["purchase_orders", "sales_orders"] {
+            let id: Uuid = sqlx::query_scalar(&order(table))
+                .bind(company)
+                .bind(partner)
+                .bind(admin)
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        let (purchase_order, sales_order) = (ids[0], ids[1]);
+
+        Repository::<PurchaseOrderLine>::new(conn)
+            .create(CreatePurchaseOrderLine {
+                purchase_order_id: purchase_order,
+                variant_id: variant,
+                quantity: Quantity::from(2),
+                unit_id: Some(carton),
+                unit_cost: Money::from(120),
+            })
+            .await
+            .unwrap();
+
+        let line = CreateSalesOrderLine {
+            sales_order_id: sales_order,
+            variant_id: variant,
+            quantity: Quantity::from(2),
+            unit_id: Some(carton),
+            unit_price: Money::from(150),
+        };
+        let mut savepoint = conn.begin().await.unwrap();
+        let refused = Repository::<SalesOrderLine>::new(&mut savepoint)
+            .create(line.clone())
+            .await;
+        assert!(refused.unwrap_err().to_string().contains("not allowed"));
+        savepoint.rollback().await.unwrap();
+
+        let line = Repository::<SalesOrderLine>::new(conn)
+            .create(CreateSalesOrderLine {
+                unit_id: Some(piece),
+                ..line
+            })
+            .await
+            .unwrap();
+        let mut savepoint = conn.begin().await.unwrap();
+        let refused = Repository::<SalesOrderLine>::new(&mut savepoint)
+            .update(
+                line.id,
+                UpdateSalesOrderLine {
+                    unit_id: Some(Some(carton)),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(refused.unwrap_err().to_string().contains("not allowed"));
+        savepoint.rollback().await.unwrap();
+    }
+}
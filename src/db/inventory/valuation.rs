@@ -0,0 +1,337 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::db::enums::{CostingMethod, RoundingMode};
+use crate::db::models::{CostLayer, StockLedger, StockMovement};
+use crate::db::money::{Money, Quantity};
+use crate::error::{AppError, Result};
+
+/// Value effect of one posted movement.
+#[derive(Debug, Clone, Copy)]
+pub struct Valuation {
+    /// Value added by a receipt, or cost of goods sold by an issue.
+    pub cost: Money,
+    pub unit_cost: Money,
+    /// Ledger value after the movement.
+    pub value: Money,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct VariantValue {
+    pub variant_id: Uuid,
+    pub quantity: Quantity,
+    pub value: Money,
+}
+
+impl VariantValue {
+    /// Average cost of the stock on hand, `None` when there is none.
+    pub fn unit_cost(&self) -> Option<Money> {
+        if self.quantity > Quantity::ZERO {
+            (self.value / self.quantity).map(round)
+        } else {
+            None
+        }
+    }
+}
+
+/// Cost layers and inventory value, by FIFO or moving weighted average as
+/// set on the company. Receipts always open a layer and issues always
+/// consume layers oldest first; the method only decides what an issue is
+/// charged. Postgres only.
+pub struct ValuationService<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> ValuationService<'c> {
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+
+    pub async fn costing_method(&mut self, company_id: Uuid) -> Result<CostingMethod> {
+        sqlx::query_scalar("SELECT costing_method FROM companies WHERE id = $1")
+            .bind(company_id)
+            .fetch_one(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)
+    }
+
+    /// Values a movement that has just been inserted, given the ledger row
+    /// as it was before it. Called by `StockService::post`, which writes the
+    /// returned value back to the ledger.
+    pub(crate) async fn apply(
+        &mut self,
+        movement: &StockMovement,
+        before: &StockLedger,
+    ) -> Result<Valuation> {
+        let delta = movement.movement_type.signed(movement.quantity);
+        if delta.is_negative() {
+            self.issue(movement, before, -delta).await
+        } else {
+            self.receive(movement, before, delta).await
+        }
+    }
+
+    async fn receive(
+        &mut self,
+        movement: &StockMovement,
+        before: &StockLedger,
+        quantity: Quantity,
+    ) -> Result<Valuation> {
+        let unit_cost = match movement.unit_cost {
+            Some(cost) => cost,
+            None => self.fallback_cost(before).await?,
+        };
+        let cost = round(quantity * unit_cost);
+        let after = before.quantity + quantity;
+
+        // Part of a receipt into negative stock settles what was issued
+        // without cover; only the rest stays on a layer
+        let remaining = if before.quantity.is_negative() {
+            after.max(Quantity::ZERO)
+        } else {
+            quantity
+        };
+        sqlx::query(
+            "INSERT INTO cost_layers (company_id, variant_id, warehouse_id, movement_id, \
+             received_at, quantity, remaining, unit_cost) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(movement.company_id)
+        .bind(movement.variant_id)
+        .bind(movement.warehouse_id)
+        .bind(movement.id)
+        .bind(movement.movement_date)
+        .bind(quantity)
+        .bind(remaining)
+        .bind(unit_cost)
+        .execute(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        let value = if before.quantity.is_negative() {
+            round(after * unit_cost)
+        } else {
+            before.value + cost
+        };
+        Ok(Valuation {
+            cost,
+            unit_cost,
+            value,
+        })
+    }
+
+    async fn issue(
+        &mut self,
+        movement: &StockMovement,
+        before: &StockLedger,
+        quantity: Quantity,
+    ) -> Result<Valuation> {
+        let method = self.costing_method(movement.company_id).await?;
+        let average = self.fallback_cost(before).await?;
+
+        let layers: Vec<CostLayer> = sqlx::query_as(
+            "SELECT l.* FROM cost_layers l \
+             LEFT JOIN stock_movements m ON m.id = l.movement_id \
+             WHERE l.variant_id = $1 AND l.warehouse_id = $2 AND l.remaining > 0 \
+             ORDER BY l.received_at, m.sequence NULLS FIRST, l.id FOR UPDATE OF l",
+        )
+        .bind(movement.variant_id)
+        .bind(movement.warehouse_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        let mut left = quantity;
+        let mut usages = Vec::new();
+        for layer in layers {
+            if left.is_zero() {
+                break;
+            }
+            let take = left.min(layer.remaining);
+            sqlx::query("UPDATE cost_layers SET remaining = remaining - $2 WHERE id = $1")
+                .bind(layer.id)
+                .bind(take)
+                .execute(&mut *self.conn)
+                .await
+                .map_err(AppError::Database)?;
+
+            let unit_cost = match method {
+                CostingMethod::Fifo => layer.unit_cost,
+                CostingMethod::WeightedAverage => average,
+            };
+            usages.push((Some(layer.id), take, unit_cost));
+            left -= take;
+        }
+        // Issued below zero: charge the last known cost until a receipt settles it
+        if !left.is_zero() {
+            let unit_cost = usages.last().map_or(average, |&(_, _, cost)| cost);
+            usages.push((None, left, unit_cost));
+        }
+
+        let mut cost = Money::ZERO;
+        for (layer_id, take, unit_cost) in usages {
+            sqlx::query(
+                "INSERT INTO cost_layer_usages (movement_id, layer_id, quantity, unit_cost) \
+                 VALUES ($1, $2, $3, $4)",
+            )
+            .bind(movement.id)
+            .bind(layer_id)
+            .bind(take)
+            .bind(unit_cost)
+            .execute(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+            cost += take * unit_cost;
+        }
+
+        // Emptying the warehouse takes its whole book value, so rounding
+        // never leaves a residue behind
+        let cost = if (before.quantity - quantity).is_zero() {
+            before.value
+        } else {
+            round(cost)
+        };
+        Ok(Valuation {
+            cost,
+            unit_cost: (cost / quantity).map(round).unwrap_or_default(),
+            value: before.value - cost,
+        })
+    }
+
+    /// Cost for stock with no layers to go by: the current average, else the
+    /// variant's standard cost price.
+    async fn fallback_cost(&mut self, ledger: &StockLedger) -> Result<Money> {
+        if ledger.quantity > Quantity::ZERO
+            && let Some(average) = ledger.value / ledger.quantity
+        {
+            return Ok(round(average));
+        }
+        sqlx::query_scalar("SELECT cost_price FROM product_variants WHERE id = $1")
+            .bind(ledger.variant_id)
+            .fetch_one(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)
+    }
+
+    /// Cost of goods sold charged to an outgoing movement.
+    pub async fn cogs(&mut self, movement_id: Uuid) -> Result<Money> {
+        let cost: Option<Money> = sqlx::query_scalar(
+            "SELECT SUM(quantity * unit_cost) FROM cost_layer_usages WHERE movement_id = $1",
+        )
+        .bind(movement_id)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+        Ok(cost.map(round).unwrap_or_default())
+    }
+
+    pub async fn open_layers(
+        &mut self,
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+    ) -> Result<Vec<CostLayer>> {
+        sqlx::query_as(
+            "SELECT l.* FROM cost_layers l \
+             LEFT JOIN stock_movements m ON m.id = l.movement_id \
+             WHERE l.variant_id = $1 AND l.warehouse_id = $2 AND l.remaining > 0 \
+             ORDER BY l.received_at, m.sequence NULLS FIRST, l.id",
+        )
+        .bind(variant_id)
+        .bind(warehouse_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Quantity and book value of one variant across all warehouses.
+    pub async fn variant_value(&mut self, variant_id: Uuid) -> Result<VariantValue> {
+        sqlx::query_as(
+            "SELECT $1 AS variant_id, COALESCE(SUM(quantity), 0) AS quantity, \
+             COALESCE(SUM(value), 0) AS value FROM stock_ledger WHERE variant_id = $1",
+        )
+        .bind(variant_id)
+        .fetch_one(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    /// Inventory value per variant for a company, largest first.
+    pub async fn inventory_value(&mut self, company_id: Uuid) -> Result<Vec<VariantValue>> {
+        sqlx::query_as(
+            "SELECT l.variant_id, SUM(l.quantity) AS quantity, SUM(l.value) AS value \
+             FROM stock_ledger l JOIN warehouses w ON w.id = l.warehouse_id \
+             WHERE w.company_id = $1 \
+             GROUP BY l.variant_id HAVING SUM(l.quantity) <> 0 OR SUM(l.value) <> 0 \
+             ORDER BY value DESC",
+        )
+        .bind(company_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+}
+
+fn round(amount: Money) -> Money {
+    amount.round(Money::SCALE, RoundingMode::HalfUp)
+}
+
+/// Splits `cost` over `quantity` into receipts at a rounded unit cost that
+/// add up to it exactly: what rounding leaves over goes on one unit of its
+/// own. Quantities of one unit or less take the rounded cost.
+pub(crate) fn cost_parts(quantity: Quantity, cost: Money) -> Vec<(Quantity, Money)> {
+    let unit_cost = (cost / quantity).map(round).unwrap_or_default();
+    let residue = cost - round(quantity * unit_cost);
+    let one = Quantity::from(1);
+    if residue.is_zero() || quantity <= one {
+        vec![(quantity, unit_cost)]
+    } else {
+        vec![(quantity - one, unit_cost), (one, unit_cost + residue)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::NegativeStockPolicy;
+    use crate::db::enums::{MovementType, TrackingMode};
+    use crate::db::inventory::StockService;
+    use crate::db::models::CreateStockMovement;
+    use crate::db::money::{Money, Quantity};
+    use crate::db::testing;
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn fifo_issues_receipts_of_one_transaction_in_posting_order() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let warehouse = testing::warehouse(conn, company).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        let movement = |movement_type, unit_cost: Option<i64>| CreateStockMovement {
+            company_id: company,
+            variant_id: variant,
+            warehouse_id: warehouse,
+            quantity: Quantity::from(1),
+            movement_type,
+            reference_type: None,
+            reference_id: None,
+            unit_cost: unit_cost.map(Money::from),
+            location_id: None,
+            lots: Vec::new(),
+        };
+
+        // Everything here shares the transaction's movement_date
+        let mut stock = StockService::new(conn, NegativeStockPolicy::Forbid);
+        for cost in [10, 20, 30, 40] {
+            stock
+                .post(movement(MovementType::In, Some(cost)))
+                .await
+                .unwrap();
+        }
+        for cost in [10, 20, 30, 40] {
+            let posting = stock.post(movement(MovementType::Out, None)).await.unwrap();
+            assert_eq!(posting.cost, Money::from(cost));
+        }
+    }
+}
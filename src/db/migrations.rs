@@ -0,0 +1,542 @@
+use std::fmt;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, Executor, FromRow, IntoArguments, Pool, QueryBuilder};
+
+use crate::db::backend::Backend;
+use crate::db::repositories::SqlValue;
+use crate::error::{AppError, Result};
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+impl Migration {
+    pub fn checksum(&self) -> Vec<u8> {
+        Sha256::digest(self.up.as_bytes()).to_vec()
+    }
+}
+
+pub static POSTGRES_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        up: include_str!("migrations/postgres/0001_initial_schema.up.sql"),
+        down: include_str!("migrations/postgres/0001_initial_schema.down.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "users_not_null",
+        up: include_str!("migrations/postgres/0002_users_not_null.up.sql"),
+        down: include_str!("migrations/postgres/0002_users_not_null.down.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "entity_constraints",
+        up: include_str!("migrations/postgres/0003_entity_constraints.up.sql"),
+        down: include_str!("migrations/postgres/0003_entity_constraints.down.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "enum_columns",
+        up: include_str!("migrations/postgres/0004_enum_columns.up.sql"),
+        down: include_str!("migrations/postgres/0004_enum_columns.down.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "currency_rounding",
+        up: include_str!("migrations/postgres/0005_currency_rounding.up.sql"),
+        down: include_str!("migrations/postgres/0005_currency_rounding.down.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "list_indexes",
+        up: include_str!("migrations/postgres/0006_list_indexes.up.sql"),
+        down: include_str!("migrations/postgres/0006_list_indexes.down.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "search_indexes",
+        up: include_str!("migrations/postgres/0007_search_indexes.up.sql"),
+        down: include_str!("migrations/postgres/0007_search_indexes.down.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "stock_posting",
+        up: include_str!("migrations/postgres/0008_stock_posting.up.sql"),
+        down: include_str!("migrations/postgres/0008_stock_posting.down.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "inventory_valuation",
+        up: include_str!("migrations/postgres/0009_inventory_valuation.up.sql"),
+        down: include_str!("migrations/postgres/0009_inventory_valuation.down.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "stock_transfers",
+        up: include_str!("migrations/postgres/0010_stock_transfers.up.sql"),
+        down: include_str!("migrations/postgres/0010_stock_transfers.down.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "stock_counts",
+        up: include_str!("migrations/postgres/0011_stock_counts.up.sql"),
+        down: include_str!("migrations/postgres/0011_stock_counts.down.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "stock_reservations",
+        up: include_str!("migrations/postgres/0012_stock_reservations.up.sql"),
+        down: include_str!("migrations/postgres/0012_stock_reservations.down.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "replenishment",
+        up: include_str!("migrations/postgres/0013_replenishment.up.sql"),
+        down: include_str!("migrations/postgres/0013_replenishment.down.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "lot_tracking",
+        up: include_str!("migrations/postgres/0014_lot_tracking.up.sql"),
+        down: include_str!("migrations/postgres/0014_lot_tracking.down.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "storage_locations",
+        up: include_str!("migrations/postgres/0015_storage_locations.up.sql"),
+        down: include_str!("migrations/postgres/0015_storage_locations.down.sql"),
+    },
+    Migration {
+        version: 16,
+        name: "movement_sequence",
+        up: include_str!("migrations/postgres/0016_movement_sequence.up.sql"),
+        down: include_str!("migrations/postgres/0016_movement_sequence.down.sql"),
+    },
+    Migration {
+        version: 17,
+        name: "product_attributes",
+        up: include_str!("migrations/postgres/0017_product_attributes.up.sql"),
+        down: include_str!("migrations/postgres/0017_product_attributes.down.sql"),
+    },
+    Migration {
+        version: 18,
+        name: "units_of_measure",
+        up: include_str!("migrations/postgres/0018_units_of_measure.up.sql"),
+        down: include_str!("migrations/postgres/0018_units_of_measure.down.sql"),
+    },
+    Migration {
+        version: 19,
+        name: "price_lists",
+        up: include_str!("migrations/postgres/0019_price_lists.up.sql"),
+        down: include_str!("migrations/postgres/0019_price_lists.down.sql"),
+    },
+    Migration {
+        version: 20,
+        name: "bills_of_materials",
+        up: include_str!("migrations/postgres/0020_bills_of_materials.up.sql"),
+        down: include_str!("migrations/postgres/0020_bills_of_materials.down.sql"),
+    },
+    Migration {
+        version: 21,
+        name: "landed_costs",
+        up: include_str!("migrations/postgres/0021_landed_costs.up.sql"),
+        down: include_str!("migrations/postgres/0021_landed_costs.down.sql"),
+    },
+    Migration {
+        version: 22,
+        name: "count_locations",
+        up: include_str!("migrations/postgres/0022_count_locations.up.sql"),
+        down: include_str!("migrations/postgres/0022_count_locations.down.sql"),
+    },
+    Migration {
+        version: 23,
+        name: "transfer_line_guard",
+        up: include_str!("migrations/postgres/0023_transfer_line_guard.up.sql"),
+        down: include_str!("migrations/postgres/0023_transfer_line_guard.down.sql"),
+    },
+    Migration {
+        version: 24,
+        name: "landed_cost_charge_guard",
+        up: include_str!("migrations/postgres/0024_landed_cost_charge_guard.up.sql"),
+        down: include_str!("migrations/postgres/0024_landed_cost_charge_guard.down.sql"),
+    },
+    Migration {
+        version: 25,
+        name: "order_line_units",
+        up: include_str!("migrations/postgres/0025_order_line_units.up.sql"),
+        down: include_str!("migrations/postgres/0025_order_line_units.down.sql"),
+    },
+    Migration {
+        version: 26,
+        name: "transfer_warehouse_company",
+        up: include_str!("migrations/postgres/0026_transfer_warehouse_company.up.sql"),
+        down: include_str!("migrations/postgres/0026_transfer_warehouse_company.down.sql"),
+    },
+    Migration {
+        version: 27,
+        name: "variant_attribute_guard",
+        up: include_str!("migrations/postgres/0027_variant_attribute_guard.up.sql"),
+        down: include_str!("migrations/postgres/0027_variant_attribute_guard.down.sql"),
+    },
+    Migration {
+        version: 28,
+        name: "journal_enums",
+        up: include_str!("migrations/postgres/0028_journal_enums.up.sql"),
+        down: include_str!("migrations/postgres/0028_journal_enums.down.sql"),
+    },
+];
+
+// The embedded database starts from the Postgres schema as of version 3,
+// so its history is numbered independently. It only follows the tables
+// behind `Storage`; see there for what stays Postgres only.
+pub static SQLITE_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        up: include_str!("migrations/sqlite/0001_initial_schema.up.sql"),
+        down: include_str!("migrations/sqlite/0001_initial_schema.down.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "currency_rounding",
+        up: include_str!("migrations/sqlite/0002_currency_rounding.up.sql"),
+        down: include_str!("migrations/sqlite/0002_currency_rounding.down.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "list_indexes",
+        up: include_str!("migrations/sqlite/0003_list_indexes.up.sql"),
+        down: include_str!("migrations/sqlite/0003_list_indexes.down.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "costing_method",
+        up: include_str!("migrations/sqlite/0004_costing_method.up.sql"),
+        down: include_str!("migrations/sqlite/0004_costing_method.down.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "purchase_order_warehouse",
+        up: include_str!("migrations/sqlite/0005_purchase_order_warehouse.up.sql"),
+        down: include_str!("migrations/sqlite/0005_purchase_order_warehouse.down.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "variant_tracking",
+        up: include_str!("migrations/sqlite/0006_variant_tracking.up.sql"),
+        down: include_str!("migrations/sqlite/0006_variant_tracking.down.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "movement_location",
+        up: include_str!("migrations/sqlite/0007_movement_location.up.sql"),
+        down: include_str!("migrations/sqlite/0007_movement_location.down.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "units_of_measure",
+        up: include_str!("migrations/sqlite/0008_units_of_measure.up.sql"),
+        down: include_str!("migrations/sqlite/0008_units_of_measure.down.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "variant_dimensions",
+        up: include_str!("migrations/sqlite/0009_variant_dimensions.up.sql"),
+        down: include_str!("migrations/sqlite/0009_variant_dimensions.down.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "journal_enums",
+        up: include_str!("migrations/sqlite/0010_journal_enums.up.sql"),
+        down: include_str!("migrations/sqlite/0010_journal_enums.down.sql"),
+    },
+];
+
+#[derive(Debug, Clone, FromRow)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub checksum: Vec<u8>,
+    pub applied_at: DateTime<Utc>,
+    pub execution_ms: i64,
+}
+
+pub struct MigrationReport {
+    pub applied: Vec<AppliedMigration>,
+    pub pending: Vec<&'static Migration>,
+}
+
+impl MigrationReport {
+    pub fn current_version(&self) -> Option<i64> {
+        self.applied.iter().map(|m| m.version).max()
+    }
+
+    pub fn is_up_to_date(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.current_version() {
+            Some(version) => write!(f, "schema at version {}", version)?,
+            None => write!(f, "empty schema")?,
+        }
+
+        if self.pending.is_empty() {
+            return write!(f, ", up to date");
+        }
+
+        write!(f, ", {} pending:", self.pending.len())?;
+        for migration in &self.pending {
+            write!(f, " {:04}_{}", migration.version, migration.name)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Migrator<'a, DB: Backend> {
+    pool: &'a Pool<DB>,
+    migrations: &'static [Migration],
+}
+
+impl<'a, DB> Migrator<'a, DB>
+where
+    DB: Backend,
+    for<'e> &'e mut DB::Connection: Executor<'e, Database = DB>,
+    for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+    AppliedMigration: for<'r> FromRow<'r, DB::Row>,
+{
+    pub fn new(pool: &'a Pool<DB>) -> Self {
+        Self {
+            pool,
+            migrations: DB::MIGRATIONS,
+        }
+    }
+
+    pub fn latest_version(&self) -> i64 {
+        self.migrations.iter().map(|m| m.version).max().unwrap_or(0)
+    }
+
+    /// Compares the applied migrations with the ones compiled into this
+    /// binary without changing anything.
+    pub async fn status(&self) -> Result<MigrationReport> {
+        let mut conn = self.pool.acquire().await.map_err(AppError::Database)?;
+        Self::ensure_migrations_table(&mut conn).await?;
+
+        let applied = Self::applied_migrations(&mut conn).await?;
+        self.verify(&applied)?;
+
+        Ok(self.report(applied))
+    }
+
+    /// Applies every pending migration, each one in its own transaction.
+    pub async fn run(&self) -> Result<MigrationReport> {
+        let mut conn = self.pool.acquire().await.map_err(AppError::Database)?;
+        Self::lock(&mut conn).await?;
+
+        let result = self.run_locked(&mut conn).await;
+
+        Self::unlock(&mut conn).await?;
+        result
+    }
+
+    /// Reverts applied migrations newer than `target`, newest first.
+    pub async fn rollback_to(&self, target: i64) -> Result<Vec<i64>> {
+        let mut conn = self.pool.acquire().await.map_err(AppError::Database)?;
+        Self::lock(&mut conn).await?;
+
+        let result = self.rollback_locked(&mut conn, target).await;
+
+        Self::unlock(&mut conn).await?;
+        result
+    }
+
+    async fn run_locked(&self, conn: &mut DB::Connection) -> Result<MigrationReport> {
+        Self::ensure_migrations_table(conn).await?;
+
+        let applied = Self::applied_migrations(conn).await?;
+        self.verify(&applied)?;
+
+        for migration in self.report(applied).pending {
+            self.apply(conn, migration).await?;
+        }
+
+        let applied = Self::applied_migrations(conn).await?;
+        Ok(self.report(applied))
+    }
+
+    async fn rollback_locked(&self, conn: &mut DB::Connection, target: i64) -> Result<Vec<i64>> {
+        Self::ensure_migrations_table(conn).await?;
+
+        let applied = Self::applied_migrations(conn).await?;
+        self.verify(&applied)?;
+
+        let mut reverted = Vec::new();
+        for applied in applied.iter().rev().filter(|m| m.version > target) {
+            let migration = self.find(applied.version).ok_or_else(|| {
+                AppError::Migration(format!(
+                    "no down migration available for version {}",
+                    applied.version
+                ))
+            })?;
+            self.revert(conn, migration).await?;
+            reverted.push(migration.version);
+        }
+
+        Ok(reverted)
+    }
+
+    async fn apply(&self, conn: &mut DB::Connection, migration: &Migration) -> Result<()> {
+        let started = Instant::now();
+        let mut tx = conn.begin().await.map_err(AppError::Database)?;
+
+        Executor::execute(&mut *tx, migration.up)
+            .await
+            .map_err(|e| {
+                AppError::Migration(format!(
+                    "{:04}_{} failed: {}",
+                    migration.version, migration.name, e
+                ))
+            })?;
+
+        let values: [SqlValue; 4] = [
+            migration.version.into(),
+            migration.name.into(),
+            migration.checksum().into(),
+            (started.elapsed().as_millis() as i64).into(),
+        ];
+        let mut insert = QueryBuilder::<DB>::new(
+            "INSERT INTO schema_migrations (version, name, checksum, execution_ms) VALUES (",
+        );
+        for (i, value) in values.into_iter().enumerate() {
+            if i > 0 {
+                insert.push(", ");
+            }
+            value.push_bind(&mut insert);
+        }
+        insert.push(")");
+
+        DB::build(&mut insert)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+
+        tx.commit().await.map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    async fn revert(&self, conn: &mut DB::Connection, migration: &Migration) -> Result<()> {
+        let mut tx = conn.begin().await.map_err(AppError::Database)?;
+
+        Executor::execute(&mut *tx, migration.down)
+            .await
+            .map_err(|e| {
+                AppError::Migration(format!(
+                    "reverting {:04}_{} failed: {}",
+                    migration.version, migration.name, e
+                ))
+            })?;
+
+        let mut delete = QueryBuilder::<DB>::new("DELETE FROM schema_migrations WHERE version = ");
+        SqlValue::from(migration.version).push_bind(&mut delete);
+
+        DB::build(&mut delete)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+
+        tx.commit().await.map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    fn find(&self, version: i64) -> Option<&'static Migration> {
+        self.migrations.iter().find(|m| m.version == version)
+    }
+
+    fn verify(&self, applied: &[AppliedMigration]) -> Result<()> {
+        let latest = self.latest_version();
+        if let Some(newest) = applied.iter().map(|m| m.version).max()
+            && newest > latest
+        {
+            return Err(AppError::SchemaAhead {
+                database: newest,
+                supported: latest,
+            });
+        }
+
+        for applied in applied {
+            let migration = self.find(applied.version).ok_or_else(|| {
+                AppError::Migration(format!(
+                    "applied migration {} is unknown to this build",
+                    applied.version
+                ))
+            })?;
+
+            if migration.checksum() != applied.checksum {
+                return Err(AppError::Migration(format!(
+                    "{:04}_{} was modified after it was applied",
+                    migration.version, migration.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn report(&self, applied: Vec<AppliedMigration>) -> MigrationReport {
+        let pending = self
+            .migrations
+            .iter()
+            .filter(|m| !applied.iter().any(|a| a.version == m.version))
+            .collect();
+
+        MigrationReport { applied, pending }
+    }
+
+    async fn ensure_migrations_table(conn: &mut DB::Connection) -> Result<()> {
+        Executor::execute(&mut *conn, DB::MIGRATIONS_TABLE)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    async fn applied_migrations(conn: &mut DB::Connection) -> Result<Vec<AppliedMigration>> {
+        sqlx::query_as::<DB, AppliedMigration>(
+            r#"
+            SELECT version, name, checksum, applied_at, execution_ms
+            FROM schema_migrations
+            ORDER BY version
+            "#,
+        )
+        .fetch_all(conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn lock(conn: &mut DB::Connection) -> Result<()> {
+        if let Some((lock, _)) = DB::MIGRATION_LOCK {
+            Executor::execute(&mut *conn, lock)
+                .await
+                .map_err(AppError::Database)?;
+        }
+        Ok(())
+    }
+
+    async fn unlock(conn: &mut DB::Connection) -> Result<()> {
+        if let Some((_, unlock)) = DB::MIGRATION_LOCK {
+            Executor::execute(&mut *conn, unlock)
+                .await
+                .map_err(AppError::Database)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+-- =====================================================
+-- Reverts 0001_initial_schema
+-- =====================================================
+DROP TABLE IF EXISTS revaluation_entries;
+DROP TABLE IF EXISTS document_postings;
+DROP TABLE IF EXISTS journal_lines;
+DROP TABLE IF EXISTS journal_entries;
+DROP TABLE IF EXISTS chart_of_accounts;
+DROP TABLE IF EXISTS purchase_order_lines;
+DROP TABLE IF EXISTS purchase_orders;
+DROP TABLE IF EXISTS sales_order_lines;
+DROP TABLE IF EXISTS sales_orders;
+DROP TABLE IF EXISTS stock_ledger;
+DROP TABLE IF EXISTS stock_movements;
+DROP TABLE IF EXISTS warehouses;
+DROP TABLE IF EXISTS product_variants;
+DROP TABLE IF EXISTS products;
+DROP TABLE IF EXISTS partners;
+DROP TABLE IF EXISTS users;
+DROP TABLE IF EXISTS currency_rates;
+DROP TABLE IF EXISTS companies;
+DROP TABLE IF EXISTS currencies;
+
+DROP TYPE IF EXISTS user_role;
+DROP TYPE IF EXISTS payment_method;
+DROP TYPE IF EXISTS transaction_type;
+DROP TYPE IF EXISTS order_status;
+DROP TYPE IF EXISTS payment_status;
+DROP TYPE IF EXISTS journal_status;
+DROP TYPE IF EXISTS account_type;
+DROP TYPE IF EXISTS movement_type_enum;
+DROP TYPE IF EXISTS partner_type_enum;
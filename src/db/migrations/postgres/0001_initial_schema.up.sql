@@ -41,11 +41,22 @@ CREATE TYPE user_role AS ENUM (
     'purchasing'
 );
 
+-- =====================================================
+-- CURRENCIES
+-- =====================================================
+CREATE TABLE currencies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    code VARCHAR(10) UNIQUE NOT NULL,
+    name TEXT NOT NULL,
+    symbol TEXT,
+    is_active BOOLEAN DEFAULT true
+);
+
 -- =====================================================
 -- COMPANIES
 -- =====================================================
 CREATE TABLE companies (
-    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     name VARCHAR(200) NOT NULL,
     address TEXT,
     -- fiscal_year_start DATE NOT NULL DEFAULT CURRENT_DATE,
@@ -55,6 +66,15 @@ CREATE TABLE companies (
     updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
 );
 
+CREATE TABLE currency_rates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID REFERENCES companies(id) ON DELETE CASCADE,
+    currency_id UUID REFERENCES currencies(id) ON DELETE CASCADE,
+    rate NUMERIC(18,8) NOT NULL,
+    rate_date DATE NOT NULL,
+    UNIQUE(company_id, currency_id, rate_date)
+);
+
 -- ================================
 -- USERS
 -- ================================
@@ -70,26 +90,6 @@ CREATE TABLE users (
     updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
 );
 
--- =====================================================
--- CURRENCIES
--- =====================================================
-CREATE TABLE currencies (
-    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-    code VARCHAR(10) UNIQUE NOT NULL,
-    name TEXT NOT NULL,
-    symbol TEXT,
-    is_active BOOLEAN DEFAULT true
-);
-
-CREATE TABLE currency_rates (
-    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-    company_id UUID REFERENCES companies(id) ON DELETE CASCADE,
-    currency_id UUID REFERENCES currencies(id) ON DELETE CASCADE,
-    rate NUMERIC(18,8) NOT NULL,
-    rate_date DATE NOT NULL,
-    UNIQUE(company_id, currency_id, rate_date)
-);
-
 -- =====================================================
 -- PARTNERS (Customers / Vendors)
 -- =====================================================
@@ -0,0 +1,4 @@
+ALTER TABLE users
+    ALTER COLUMN is_active DROP NOT NULL,
+    ALTER COLUMN created_at DROP NOT NULL,
+    ALTER COLUMN updated_at DROP NOT NULL;
@@ -0,0 +1,11 @@
+-- =====================================================
+-- USERS: columns the application never leaves empty
+-- =====================================================
+UPDATE users SET is_active = true WHERE is_active IS NULL;
+UPDATE users SET created_at = CURRENT_TIMESTAMP WHERE created_at IS NULL;
+UPDATE users SET updated_at = created_at WHERE updated_at IS NULL;
+
+ALTER TABLE users
+    ALTER COLUMN is_active SET NOT NULL,
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN updated_at SET NOT NULL;
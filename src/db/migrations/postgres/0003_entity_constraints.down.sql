@@ -0,0 +1,98 @@
+DROP TRIGGER IF EXISTS purchase_order_lines_total ON purchase_order_lines;
+DROP TRIGGER IF EXISTS sales_order_lines_total ON sales_order_lines;
+DROP TRIGGER IF EXISTS purchase_order_lines_subtotal ON purchase_order_lines;
+DROP TRIGGER IF EXISTS sales_order_lines_subtotal ON sales_order_lines;
+DROP FUNCTION IF EXISTS refresh_purchase_order_total();
+DROP FUNCTION IF EXISTS refresh_sales_order_total();
+DROP FUNCTION IF EXISTS set_line_subtotal();
+
+DROP INDEX IF EXISTS idx_journal_lines_entry;
+DROP INDEX IF EXISTS idx_po_lines_order;
+DROP INDEX IF EXISTS idx_so_lines_order;
+
+ALTER TABLE journal_lines
+    ALTER COLUMN journal_entry_id DROP NOT NULL,
+    ALTER COLUMN account_id DROP NOT NULL,
+    ALTER COLUMN debit DROP NOT NULL,
+    ALTER COLUMN credit DROP NOT NULL;
+
+ALTER TABLE journal_entries
+    ALTER COLUMN company_id DROP NOT NULL,
+    ALTER COLUMN posted DROP NOT NULL,
+    ALTER COLUMN created_at DROP NOT NULL,
+    ALTER COLUMN created_at TYPE TIMESTAMP USING created_at AT TIME ZONE 'UTC',
+    ALTER COLUMN created_by DROP NOT NULL;
+
+ALTER TABLE chart_of_accounts
+    ALTER COLUMN company_id DROP NOT NULL,
+    ALTER COLUMN account_type DROP NOT NULL,
+    ALTER COLUMN is_active DROP NOT NULL;
+
+ALTER TABLE purchase_order_lines
+    ALTER COLUMN purchase_order_id DROP NOT NULL,
+    ALTER COLUMN variant_id DROP NOT NULL;
+
+ALTER TABLE purchase_orders
+    ALTER COLUMN company_id DROP NOT NULL,
+    ALTER COLUMN vendor_id DROP NOT NULL,
+    ALTER COLUMN currency_id DROP NOT NULL,
+    ALTER COLUMN status DROP DEFAULT,
+    ALTER COLUMN status DROP NOT NULL,
+    ALTER COLUMN total_amount DROP NOT NULL,
+    ALTER COLUMN created_at DROP NOT NULL,
+    ALTER COLUMN created_at TYPE TIMESTAMP USING created_at AT TIME ZONE 'UTC',
+    ALTER COLUMN created_by DROP NOT NULL;
+
+ALTER TABLE sales_order_lines
+    ALTER COLUMN sales_order_id DROP NOT NULL,
+    ALTER COLUMN variant_id DROP NOT NULL;
+
+ALTER TABLE sales_orders
+    ALTER COLUMN company_id DROP NOT NULL,
+    ALTER COLUMN customer_id DROP NOT NULL,
+    ALTER COLUMN currency_id DROP NOT NULL,
+    ALTER COLUMN status DROP DEFAULT,
+    ALTER COLUMN status DROP NOT NULL,
+    ALTER COLUMN total_amount DROP NOT NULL,
+    ALTER COLUMN created_at DROP NOT NULL,
+    ALTER COLUMN created_at TYPE TIMESTAMP USING created_at AT TIME ZONE 'UTC',
+    ALTER COLUMN created_by DROP NOT NULL;
+
+ALTER TABLE stock_movements
+    ALTER COLUMN company_id DROP NOT NULL,
+    ALTER COLUMN variant_id DROP NOT NULL,
+    ALTER COLUMN warehouse_id DROP NOT NULL,
+    ALTER COLUMN movement_type DROP NOT NULL,
+    ALTER COLUMN movement_date DROP NOT NULL,
+    ALTER COLUMN movement_date TYPE TIMESTAMP USING movement_date AT TIME ZONE 'UTC';
+
+ALTER TABLE warehouses
+    ALTER COLUMN company_id DROP NOT NULL,
+    DROP COLUMN is_active;
+
+ALTER TABLE product_variants
+    ALTER COLUMN product_id DROP NOT NULL,
+    ALTER COLUMN cost_price DROP NOT NULL,
+    ALTER COLUMN selling_price DROP NOT NULL,
+    ALTER COLUMN is_active DROP NOT NULL;
+
+ALTER TABLE products
+    ALTER COLUMN company_id DROP NOT NULL,
+    ALTER COLUMN is_active DROP NOT NULL,
+    ALTER COLUMN created_at DROP NOT NULL,
+    ALTER COLUMN created_at TYPE TIMESTAMP USING created_at AT TIME ZONE 'UTC';
+
+ALTER TABLE partners
+    ALTER COLUMN company_id DROP NOT NULL,
+    ALTER COLUMN type DROP NOT NULL,
+    ALTER COLUMN created_at DROP NOT NULL,
+    ALTER COLUMN created_at TYPE TIMESTAMP USING created_at AT TIME ZONE 'UTC',
+    DROP COLUMN is_active;
+
+ALTER TABLE currencies
+    ALTER COLUMN is_active DROP NOT NULL;
+
+ALTER TABLE companies
+    ALTER COLUMN is_active DROP NOT NULL,
+    ALTER COLUMN created_at DROP NOT NULL,
+    ALTER COLUMN updated_at DROP NOT NULL;
@@ -0,0 +1,166 @@
+-- =====================================================
+-- Align the tables with db::models: required columns become
+-- NOT NULL, timestamps carry a time zone and every entity
+-- that can be archived gets an is_active flag.
+-- =====================================================
+
+ALTER TABLE companies
+    ALTER COLUMN is_active SET NOT NULL,
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN updated_at SET NOT NULL;
+
+ALTER TABLE currencies
+    ALTER COLUMN is_active SET NOT NULL;
+
+ALTER TABLE partners
+    ALTER COLUMN company_id SET NOT NULL,
+    ALTER COLUMN type SET NOT NULL,
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at AT TIME ZONE 'UTC',
+    ALTER COLUMN created_at SET NOT NULL,
+    ADD COLUMN is_active BOOLEAN NOT NULL DEFAULT true;
+
+ALTER TABLE products
+    ALTER COLUMN company_id SET NOT NULL,
+    ALTER COLUMN is_active SET NOT NULL,
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at AT TIME ZONE 'UTC',
+    ALTER COLUMN created_at SET NOT NULL;
+
+ALTER TABLE product_variants
+    ALTER COLUMN product_id SET NOT NULL,
+    ALTER COLUMN cost_price SET NOT NULL,
+    ALTER COLUMN selling_price SET NOT NULL,
+    ALTER COLUMN is_active SET NOT NULL;
+
+ALTER TABLE warehouses
+    ALTER COLUMN company_id SET NOT NULL,
+    ADD COLUMN is_active BOOLEAN NOT NULL DEFAULT true;
+
+ALTER TABLE stock_movements
+    ALTER COLUMN company_id SET NOT NULL,
+    ALTER COLUMN variant_id SET NOT NULL,
+    ALTER COLUMN warehouse_id SET NOT NULL,
+    ALTER COLUMN movement_type SET NOT NULL,
+    ALTER COLUMN movement_date TYPE TIMESTAMPTZ USING movement_date AT TIME ZONE 'UTC',
+    ALTER COLUMN movement_date SET NOT NULL;
+
+ALTER TABLE sales_orders
+    ALTER COLUMN company_id SET NOT NULL,
+    ALTER COLUMN customer_id SET NOT NULL,
+    ALTER COLUMN currency_id SET NOT NULL,
+    ALTER COLUMN status SET DEFAULT 'DRAFT',
+    ALTER COLUMN status SET NOT NULL,
+    ALTER COLUMN total_amount SET NOT NULL,
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at AT TIME ZONE 'UTC',
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN created_by SET NOT NULL;
+
+ALTER TABLE sales_order_lines
+    ALTER COLUMN sales_order_id SET NOT NULL,
+    ALTER COLUMN variant_id SET NOT NULL;
+
+ALTER TABLE purchase_orders
+    ALTER COLUMN company_id SET NOT NULL,
+    ALTER COLUMN vendor_id SET NOT NULL,
+    ALTER COLUMN currency_id SET NOT NULL,
+    ALTER COLUMN status SET DEFAULT 'DRAFT',
+    ALTER COLUMN status SET NOT NULL,
+    ALTER COLUMN total_amount SET NOT NULL,
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at AT TIME ZONE 'UTC',
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN created_by SET NOT NULL;
+
+ALTER TABLE purchase_order_lines
+    ALTER COLUMN purchase_order_id SET NOT NULL,
+    ALTER COLUMN variant_id SET NOT NULL;
+
+ALTER TABLE chart_of_accounts
+    ALTER COLUMN company_id SET NOT NULL,
+    ALTER COLUMN account_type SET NOT NULL,
+    ALTER COLUMN is_active SET NOT NULL;
+
+ALTER TABLE journal_entries
+    ALTER COLUMN company_id SET NOT NULL,
+    ALTER COLUMN posted SET NOT NULL,
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at AT TIME ZONE 'UTC',
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN created_by SET NOT NULL;
+
+ALTER TABLE journal_lines
+    ALTER COLUMN journal_entry_id SET NOT NULL,
+    ALTER COLUMN account_id SET NOT NULL,
+    ALTER COLUMN debit SET NOT NULL,
+    ALTER COLUMN credit SET NOT NULL;
+
+CREATE INDEX idx_so_lines_order ON sales_order_lines(sales_order_id);
+CREATE INDEX idx_po_lines_order ON purchase_order_lines(purchase_order_id);
+CREATE INDEX idx_journal_lines_entry ON journal_lines(journal_entry_id);
+
+-- =====================================================
+-- ORDER LINE TOTALS
+-- Line subtotals and order totals are derived, so keep
+-- them in the database instead of trusting every caller.
+-- =====================================================
+CREATE OR REPLACE FUNCTION set_line_subtotal() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_TABLE_NAME = 'sales_order_lines' THEN
+        NEW.subtotal := NEW.quantity * NEW.unit_price;
+    ELSE
+        NEW.subtotal := NEW.quantity * NEW.unit_cost;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER sales_order_lines_subtotal
+    BEFORE INSERT OR UPDATE ON sales_order_lines
+    FOR EACH ROW EXECUTE FUNCTION set_line_subtotal();
+
+CREATE TRIGGER purchase_order_lines_subtotal
+    BEFORE INSERT OR UPDATE ON purchase_order_lines
+    FOR EACH ROW EXECUTE FUNCTION set_line_subtotal();
+
+CREATE OR REPLACE FUNCTION refresh_sales_order_total() RETURNS TRIGGER AS $$
+DECLARE
+    order_id UUID;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        order_id := OLD.sales_order_id;
+    ELSE
+        order_id := NEW.sales_order_id;
+    END IF;
+
+    UPDATE sales_orders
+    SET total_amount = (
+        SELECT COALESCE(SUM(subtotal), 0) FROM sales_order_lines WHERE sales_order_id = order_id
+    )
+    WHERE id = order_id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER sales_order_lines_total
+    AFTER INSERT OR UPDATE OR DELETE ON sales_order_lines
+    FOR EACH ROW EXECUTE FUNCTION refresh_sales_order_total();
+
+CREATE OR REPLACE FUNCTION refresh_purchase_order_total() RETURNS TRIGGER AS $$
+DECLARE
+    order_id UUID;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        order_id := OLD.purchase_order_id;
+    ELSE
+        order_id := NEW.purchase_order_id;
+    END IF;
+
+    UPDATE purchase_orders
+    SET total_amount = (
+        SELECT COALESCE(SUM(subtotal), 0) FROM purchase_order_lines WHERE purchase_order_id = order_id
+    )
+    WHERE id = order_id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER purchase_order_lines_total
+    AFTER INSERT OR UPDATE OR DELETE ON purchase_order_lines
+    FOR EACH ROW EXECUTE FUNCTION refresh_purchase_order_total();
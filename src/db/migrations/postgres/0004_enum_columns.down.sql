@@ -0,0 +1,17 @@
+ALTER TABLE purchase_orders ALTER COLUMN status DROP DEFAULT;
+ALTER TABLE purchase_orders ALTER COLUMN status TYPE VARCHAR(20) USING status::text;
+ALTER TABLE purchase_orders ALTER COLUMN status SET DEFAULT 'DRAFT';
+
+ALTER TABLE sales_orders ALTER COLUMN status DROP DEFAULT;
+ALTER TABLE sales_orders ALTER COLUMN status TYPE VARCHAR(20) USING status::text;
+ALTER TABLE sales_orders ALTER COLUMN status SET DEFAULT 'DRAFT';
+
+ALTER TABLE stock_movements
+    ALTER COLUMN movement_type TYPE VARCHAR(20) USING movement_type::text;
+ALTER TABLE stock_movements ADD CONSTRAINT stock_movements_movement_type_check
+    CHECK (movement_type IN ('in','out','adjustment'));
+
+ALTER TABLE partners
+    ALTER COLUMN type TYPE VARCHAR(20) USING type::text;
+ALTER TABLE partners ADD CONSTRAINT partners_type_check
+    CHECK (type IN ('customer','vendor','both'));
@@ -0,0 +1,22 @@
+-- =====================================================
+-- Use the enum types declared in 0001 for the columns
+-- that were still free text with CHECK constraints.
+-- =====================================================
+
+ALTER TABLE partners DROP CONSTRAINT partners_type_check;
+ALTER TABLE partners
+    ALTER COLUMN type TYPE partner_type_enum USING type::partner_type_enum;
+
+ALTER TABLE stock_movements DROP CONSTRAINT stock_movements_movement_type_check;
+ALTER TABLE stock_movements
+    ALTER COLUMN movement_type TYPE movement_type_enum USING movement_type::movement_type_enum;
+
+ALTER TABLE sales_orders ALTER COLUMN status DROP DEFAULT;
+ALTER TABLE sales_orders
+    ALTER COLUMN status TYPE order_status USING upper(status)::order_status;
+ALTER TABLE sales_orders ALTER COLUMN status SET DEFAULT 'DRAFT';
+
+ALTER TABLE purchase_orders ALTER COLUMN status DROP DEFAULT;
+ALTER TABLE purchase_orders
+    ALTER COLUMN status TYPE order_status USING upper(status)::order_status;
+ALTER TABLE purchase_orders ALTER COLUMN status SET DEFAULT 'DRAFT';
@@ -0,0 +1,5 @@
+ALTER TABLE currencies
+    DROP COLUMN rounding_mode,
+    DROP COLUMN decimal_places;
+
+DROP TYPE rounding_mode;
@@ -0,0 +1,14 @@
+-- =====================================================
+-- Amounts are exact decimals in the application; each
+-- currency says how they round to its minor unit.
+-- =====================================================
+
+CREATE TYPE rounding_mode AS ENUM ('HALF_UP', 'HALF_EVEN', 'DOWN', 'UP');
+
+ALTER TABLE currencies
+    ADD COLUMN decimal_places SMALLINT NOT NULL DEFAULT 2
+        CHECK (decimal_places BETWEEN 0 AND 4),
+    ADD COLUMN rounding_mode rounding_mode NOT NULL DEFAULT 'HALF_UP';
+
+UPDATE currencies SET decimal_places = 0 WHERE code IN ('JPY', 'KRW', 'VND', 'CLP', 'ISK', 'UZS');
+UPDATE currencies SET decimal_places = 3 WHERE code IN ('BHD', 'KWD', 'OMR', 'JOD', 'TND');
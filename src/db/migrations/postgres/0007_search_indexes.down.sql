@@ -0,0 +1,11 @@
+DROP INDEX idx_variants_barcode_trgm;
+DROP INDEX idx_variants_sku_trgm;
+DROP INDEX idx_products_name_trgm;
+DROP INDEX idx_products_search;
+DROP INDEX idx_partners_phone_trgm;
+DROP INDEX idx_partners_email_trgm;
+DROP INDEX idx_partners_name_trgm;
+DROP INDEX idx_partners_search;
+
+ALTER TABLE products DROP COLUMN search_vector;
+ALTER TABLE partners DROP COLUMN search_vector;
@@ -0,0 +1,28 @@
+-- =====================================================
+-- Fuzzy search (db::search): trigram indexes for names
+-- and codes, full-text vectors for longer text.
+-- =====================================================
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+ALTER TABLE partners
+    ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+        to_tsvector('simple', name || ' ' || COALESCE(email, ''))
+    ) STORED;
+
+ALTER TABLE products
+    ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+        to_tsvector('simple',
+            name || ' ' || COALESCE(category, '') || ' ' || COALESCE(description, ''))
+    ) STORED;
+
+CREATE INDEX idx_partners_search ON partners USING GIN (search_vector);
+CREATE INDEX idx_partners_name_trgm ON partners USING GIN (name gin_trgm_ops);
+CREATE INDEX idx_partners_email_trgm ON partners USING GIN (email gin_trgm_ops);
+CREATE INDEX idx_partners_phone_trgm ON partners USING GIN (phone gin_trgm_ops);
+
+CREATE INDEX idx_products_search ON products USING GIN (search_vector);
+CREATE INDEX idx_products_name_trgm ON products USING GIN (name gin_trgm_ops);
+
+CREATE INDEX idx_variants_sku_trgm ON product_variants USING GIN (sku gin_trgm_ops);
+CREATE INDEX idx_variants_barcode_trgm ON product_variants USING GIN (barcode gin_trgm_ops);
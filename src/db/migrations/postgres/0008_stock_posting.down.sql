@@ -0,0 +1,2 @@
+-- The rebuilt ledger is kept; it is still correct for the old schema.
+ALTER TABLE stock_movements DROP CONSTRAINT stock_movements_quantity_sign;
@@ -0,0 +1,15 @@
+-- in/out quantities are positive and the movement type gives the direction;
+-- adjustments carry their own sign. NOT VALID keeps older rows loadable.
+ALTER TABLE stock_movements
+    ADD CONSTRAINT stock_movements_quantity_sign
+    CHECK (movement_type = 'adjustment' OR quantity > 0) NOT VALID;
+
+-- Nothing maintained the ledger until now, so rebuild it from the movements
+DELETE FROM stock_ledger;
+
+INSERT INTO stock_ledger (variant_id, warehouse_id, quantity)
+SELECT variant_id,
+       warehouse_id,
+       SUM(CASE movement_type WHEN 'out' THEN -quantity ELSE quantity END)
+FROM stock_movements
+GROUP BY variant_id, warehouse_id;
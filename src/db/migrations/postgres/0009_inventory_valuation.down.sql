@@ -0,0 +1,5 @@
+DROP TABLE cost_layer_usages;
+DROP TABLE cost_layers;
+ALTER TABLE stock_ledger DROP COLUMN value;
+ALTER TABLE companies DROP COLUMN costing_method;
+DROP TYPE costing_method;
@@ -0,0 +1,62 @@
+-- =====================================================
+-- Inventory valuation: each company costs outgoing stock
+-- by FIFO or moving weighted average. Receipts open cost
+-- layers; issues consume them oldest first and record
+-- what they were charged in cost_layer_usages.
+-- =====================================================
+
+CREATE TYPE costing_method AS ENUM ('FIFO', 'WEIGHTED_AVERAGE');
+
+ALTER TABLE companies
+    ADD COLUMN costing_method costing_method NOT NULL DEFAULT 'FIFO';
+
+-- Book value of the quantity on hand
+ALTER TABLE stock_ledger
+    ADD COLUMN value NUMERIC(18,4) NOT NULL DEFAULT 0;
+
+-- Stock from before costing is valued at standard cost
+UPDATE stock_ledger l
+SET value = round(l.quantity * v.cost_price, 4)
+FROM product_variants v
+WHERE v.id = l.variant_id;
+
+CREATE TABLE cost_layers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    variant_id UUID NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+    warehouse_id UUID NOT NULL REFERENCES warehouses(id) ON DELETE CASCADE,
+    -- NULL for the opening layer of stock from before costing
+    movement_id UUID REFERENCES stock_movements(id) ON DELETE CASCADE,
+    received_at TIMESTAMPTZ NOT NULL,
+    quantity NUMERIC(18,4) NOT NULL CHECK (quantity > 0),
+    remaining NUMERIC(18,4) NOT NULL CHECK (remaining >= 0 AND remaining <= quantity),
+    unit_cost NUMERIC(18,4) NOT NULL CHECK (unit_cost >= 0)
+);
+
+-- Stock from before costing opens a layer at standard cost, so FIFO
+-- issues it before anything received later
+INSERT INTO cost_layers (company_id, variant_id, warehouse_id, received_at,
+                         quantity, remaining, unit_cost)
+SELECT w.company_id, l.variant_id, l.warehouse_id, now(), l.quantity, l.quantity,
+       v.cost_price
+FROM stock_ledger l
+JOIN warehouses w ON w.id = l.warehouse_id
+JOIN product_variants v ON v.id = l.variant_id
+WHERE l.quantity > 0;
+
+CREATE INDEX idx_cost_layers_open
+    ON cost_layers(variant_id, warehouse_id, received_at, id)
+    WHERE remaining > 0;
+CREATE INDEX idx_cost_layers_movement ON cost_layers(movement_id);
+
+-- layer_id is NULL for quantity issued below zero, which no layer covers
+CREATE TABLE cost_layer_usages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    movement_id UUID NOT NULL REFERENCES stock_movements(id) ON DELETE CASCADE,
+    layer_id UUID REFERENCES cost_layers(id) ON DELETE CASCADE,
+    quantity NUMERIC(18,4) NOT NULL CHECK (quantity > 0),
+    unit_cost NUMERIC(18,4) NOT NULL
+);
+
+CREATE INDEX idx_cost_layer_usages_movement ON cost_layer_usages(movement_id);
+CREATE INDEX idx_cost_layer_usages_layer ON cost_layer_usages(layer_id);
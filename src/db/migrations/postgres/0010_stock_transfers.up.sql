@@ -0,0 +1,36 @@
+-- =====================================================
+-- Transfers between two warehouses of a company. Shipping
+-- posts the outgoing movements and records their cost;
+-- receiving posts the incoming ones at that cost. Until
+-- then the goods are in transit.
+-- =====================================================
+
+CREATE TYPE transfer_status AS ENUM ('DRAFT', 'SHIPPED', 'RECEIVED', 'CANCELLED');
+
+CREATE TABLE stock_transfers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    from_warehouse_id UUID NOT NULL REFERENCES warehouses(id),
+    to_warehouse_id UUID NOT NULL REFERENCES warehouses(id),
+    status transfer_status NOT NULL DEFAULT 'DRAFT',
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_by UUID NOT NULL REFERENCES users(id),
+    shipped_at TIMESTAMPTZ,
+    received_at TIMESTAMPTZ,
+    CHECK (from_warehouse_id <> to_warehouse_id)
+);
+
+CREATE INDEX idx_stock_transfers_company ON stock_transfers(company_id, created_at DESC, id DESC);
+CREATE INDEX idx_stock_transfers_in_transit ON stock_transfers(company_id) WHERE status = 'SHIPPED';
+
+CREATE TABLE stock_transfer_lines (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transfer_id UUID NOT NULL REFERENCES stock_transfers(id) ON DELETE CASCADE,
+    variant_id UUID NOT NULL REFERENCES product_variants(id),
+    quantity NUMERIC(18,4) NOT NULL CHECK (quantity > 0),
+    -- Cost taken out of the source warehouse, set when shipped
+    cost NUMERIC(18,4)
+);
+
+CREATE INDEX idx_stock_transfer_lines_transfer ON stock_transfer_lines(transfer_id);
@@ -0,0 +1,3 @@
+DROP TABLE stock_count_lines;
+DROP TABLE stock_counts;
+DROP TYPE count_status;
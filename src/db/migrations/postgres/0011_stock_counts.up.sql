@@ -0,0 +1,33 @@
+-- =====================================================
+-- Physical counts. Starting a count freezes the expected
+-- quantity and unit cost of every ledger row it covers;
+-- approving it posts the differences as adjustments.
+-- =====================================================
+
+CREATE TYPE count_status AS ENUM ('OPEN', 'SUBMITTED', 'POSTED', 'CANCELLED');
+
+CREATE TABLE stock_counts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    warehouse_id UUID NOT NULL REFERENCES warehouses(id),
+    status count_status NOT NULL DEFAULT 'OPEN',
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_by UUID NOT NULL REFERENCES users(id),
+    submitted_at TIMESTAMPTZ,
+    approved_by UUID REFERENCES users(id),
+    posted_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_stock_counts_company ON stock_counts(company_id, created_at DESC, id DESC);
+
+CREATE TABLE stock_count_lines (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    count_id UUID NOT NULL REFERENCES stock_counts(id) ON DELETE CASCADE,
+    variant_id UUID NOT NULL REFERENCES product_variants(id),
+    expected NUMERIC(18,4) NOT NULL,
+    -- NULL until counted; uncounted lines are not adjusted
+    counted NUMERIC(18,4) CHECK (counted >= 0),
+    unit_cost NUMERIC(18,4) NOT NULL DEFAULT 0,
+    UNIQUE (count_id, variant_id)
+);
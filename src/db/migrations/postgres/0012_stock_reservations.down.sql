@@ -0,0 +1,2 @@
+DROP TABLE stock_reservations;
+ALTER TABLE stock_ledger DROP COLUMN reserved;
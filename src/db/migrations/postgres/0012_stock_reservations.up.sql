@@ -0,0 +1,26 @@
+-- =====================================================
+-- Reservations hold stock for confirmed sales order lines
+-- until they are delivered or cancelled. The ledger keeps
+-- the reserved total next to the quantity on hand so both
+-- change under the same row lock.
+-- =====================================================
+
+ALTER TABLE stock_ledger
+    ADD COLUMN reserved NUMERIC(18,4) NOT NULL DEFAULT 0 CHECK (reserved >= 0);
+
+CREATE TABLE stock_reservations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    sales_order_line_id UUID NOT NULL REFERENCES sales_order_lines(id) ON DELETE CASCADE,
+    variant_id UUID NOT NULL REFERENCES product_variants(id),
+    warehouse_id UUID NOT NULL REFERENCES warehouses(id),
+    quantity NUMERIC(18,4) NOT NULL CHECK (quantity > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    released_at TIMESTAMPTZ
+);
+
+-- At most one active reservation per order line
+CREATE UNIQUE INDEX idx_stock_reservations_line
+    ON stock_reservations(sales_order_line_id) WHERE released_at IS NULL;
+CREATE INDEX idx_stock_reservations_stock
+    ON stock_reservations(variant_id, warehouse_id) WHERE released_at IS NULL;
@@ -0,0 +1,2 @@
+DROP TABLE reorder_rules;
+ALTER TABLE purchase_orders DROP COLUMN warehouse_id;
@@ -0,0 +1,29 @@
+-- =====================================================
+-- Reorder rules per variant and warehouse. When projected
+-- stock (on hand - reserved + on order) falls to the
+-- reorder point, replenishment proposes either filling up
+-- to max_quantity or ordering a fixed reorder_quantity.
+-- =====================================================
+
+-- Where a purchase order will be received; replenishment counts open
+-- orders as incoming stock for this warehouse
+ALTER TABLE purchase_orders ADD COLUMN warehouse_id UUID REFERENCES warehouses(id);
+
+CREATE TABLE reorder_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    variant_id UUID NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+    warehouse_id UUID NOT NULL REFERENCES warehouses(id) ON DELETE CASCADE,
+    min_quantity NUMERIC(18,4) NOT NULL DEFAULT 0 CHECK (min_quantity >= 0),
+    max_quantity NUMERIC(18,4),
+    reorder_quantity NUMERIC(18,4) CHECK (reorder_quantity > 0),
+    -- Proposals are rounded up to a multiple of this, e.g. a carton
+    order_multiple NUMERIC(18,4) NOT NULL DEFAULT 1 CHECK (order_multiple > 0),
+    vendor_id UUID REFERENCES partners(id),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    UNIQUE (variant_id, warehouse_id),
+    CHECK (max_quantity IS NOT NULL OR reorder_quantity IS NOT NULL),
+    CHECK (max_quantity IS NULL OR max_quantity > min_quantity)
+);
+
+CREATE INDEX idx_reorder_rules_company ON reorder_rules(company_id) WHERE is_active;
@@ -0,0 +1,5 @@
+DROP TABLE stock_movement_lots;
+DROP TABLE stock_lot_balances;
+DROP TABLE stock_lots;
+ALTER TABLE product_variants DROP COLUMN tracking;
+DROP TYPE tracking_mode;
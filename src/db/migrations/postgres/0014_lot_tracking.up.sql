@@ -0,0 +1,37 @@
+-- =====================================================
+-- Lot and serial tracking. A tracked variant's movements
+-- say which lots (or serial numbers, one unit each) they
+-- move, and each lot keeps its own stock per warehouse.
+-- =====================================================
+
+CREATE TYPE tracking_mode AS ENUM ('NONE', 'LOT', 'SERIAL');
+
+ALTER TABLE product_variants
+    ADD COLUMN tracking tracking_mode NOT NULL DEFAULT 'NONE';
+
+-- Serial numbers are lots of one
+CREATE TABLE stock_lots (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    variant_id UUID NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+    number TEXT NOT NULL,
+    expires_on DATE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (variant_id, number)
+);
+
+CREATE TABLE stock_lot_balances (
+    lot_id UUID NOT NULL REFERENCES stock_lots(id) ON DELETE CASCADE,
+    warehouse_id UUID NOT NULL REFERENCES warehouses(id) ON DELETE CASCADE,
+    quantity NUMERIC(18,4) NOT NULL DEFAULT 0 CHECK (quantity >= 0),
+    PRIMARY KEY (lot_id, warehouse_id)
+);
+
+CREATE TABLE stock_movement_lots (
+    movement_id UUID NOT NULL REFERENCES stock_movements(id) ON DELETE CASCADE,
+    lot_id UUID NOT NULL REFERENCES stock_lots(id) ON DELETE CASCADE,
+    quantity NUMERIC(18,4) NOT NULL CHECK (quantity > 0),
+    PRIMARY KEY (movement_id, lot_id)
+);
+
+CREATE INDEX idx_stock_movement_lots_lot ON stock_movement_lots(lot_id);
@@ -0,0 +1,5 @@
+DROP TABLE stock_bin_moves;
+ALTER TABLE stock_movements DROP COLUMN location_id;
+DROP TABLE stock_bin_balances;
+DROP TABLE storage_locations;
+DROP TYPE location_kind;
@@ -0,0 +1,55 @@
+-- =====================================================
+-- Storage locations inside warehouses (zone > aisle >
+-- shelf > bin). Stock is kept per bin next to the
+-- warehouse ledger; stock not yet put away is the part
+-- of the ledger no bin holds.
+-- =====================================================
+
+CREATE TYPE location_kind AS ENUM ('ZONE', 'AISLE', 'SHELF', 'BIN');
+
+CREATE TABLE storage_locations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    warehouse_id UUID NOT NULL REFERENCES warehouses(id) ON DELETE CASCADE,
+    parent_id UUID REFERENCES storage_locations(id) ON DELETE RESTRICT,
+    kind location_kind NOT NULL,
+    code TEXT NOT NULL,
+    name TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (warehouse_id, code),
+    CHECK (parent_id IS DISTINCT FROM id)
+);
+
+CREATE INDEX idx_storage_locations_parent ON storage_locations(parent_id);
+
+CREATE TABLE stock_bin_balances (
+    location_id UUID NOT NULL REFERENCES storage_locations(id) ON DELETE RESTRICT,
+    variant_id UUID NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+    quantity NUMERIC(18,4) NOT NULL DEFAULT 0 CHECK (quantity >= 0),
+    PRIMARY KEY (location_id, variant_id)
+);
+
+CREATE INDEX idx_stock_bin_balances_variant ON stock_bin_balances(variant_id);
+
+-- Bin a receipt was put away to or a delivery picked from
+ALTER TABLE stock_movements
+    ADD COLUMN location_id UUID REFERENCES storage_locations(id) ON DELETE RESTRICT;
+
+-- Moves between bins of one warehouse; the warehouse ledger does not change.
+-- A missing side is stock not in any bin.
+CREATE TABLE stock_bin_moves (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    warehouse_id UUID NOT NULL REFERENCES warehouses(id) ON DELETE CASCADE,
+    variant_id UUID NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+    from_location_id UUID REFERENCES storage_locations(id) ON DELETE RESTRICT,
+    to_location_id UUID REFERENCES storage_locations(id) ON DELETE RESTRICT,
+    quantity NUMERIC(18,4) NOT NULL CHECK (quantity > 0),
+    moved_by UUID NOT NULL REFERENCES users(id),
+    moved_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (from_location_id IS NOT NULL OR to_location_id IS NOT NULL),
+    CHECK (from_location_id IS DISTINCT FROM to_location_id)
+);
+
+CREATE INDEX idx_stock_bin_moves_variant ON stock_bin_moves(variant_id, moved_at);
@@ -0,0 +1,2 @@
+DROP INDEX idx_stock_movements_replay;
+ALTER TABLE stock_movements DROP COLUMN sequence;
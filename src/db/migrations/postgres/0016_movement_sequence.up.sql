@@ -0,0 +1,12 @@
+-- =====================================================
+-- Posting order of stock movements. movement_date is
+-- the transaction's start time, so movements posted
+-- together tie on it; replaying history needs the order
+-- they were applied in.
+-- =====================================================
+
+ALTER TABLE stock_movements
+    ADD COLUMN sequence BIGINT GENERATED ALWAYS AS IDENTITY;
+
+CREATE INDEX idx_stock_movements_replay
+    ON stock_movements(company_id, variant_id, warehouse_id, sequence);
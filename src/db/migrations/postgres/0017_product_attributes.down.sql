@@ -0,0 +1,2 @@
+DROP INDEX uq_variants_product_attributes;
+DROP TABLE product_attributes;
@@ -0,0 +1,20 @@
+-- =====================================================
+-- Attribute definitions per product (size, colour, ...)
+-- with their allowed values. Variants of a product with
+-- definitions carry one allowed value per attribute, and
+-- no two variants of a product share the same set.
+-- =====================================================
+
+CREATE TABLE product_attributes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    name TEXT NOT NULL CHECK (name <> ''),
+    position INTEGER NOT NULL DEFAULT 0,
+    allowed_values JSONB NOT NULL DEFAULT '[]'
+        CHECK (jsonb_typeof(allowed_values) = 'array'),
+    UNIQUE (product_id, name)
+);
+
+CREATE UNIQUE INDEX uq_variants_product_attributes
+    ON product_variants(product_id, attributes)
+    WHERE attributes IS NOT NULL AND attributes <> '{}'::jsonb;
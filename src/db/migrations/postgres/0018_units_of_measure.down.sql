@@ -0,0 +1,7 @@
+DROP FUNCTION uom_factor(UUID, UUID);
+ALTER TABLE purchase_order_lines DROP COLUMN unit_id;
+ALTER TABLE sales_order_lines DROP COLUMN unit_id;
+DROP TABLE variant_units;
+ALTER TABLE product_variants DROP COLUMN stock_unit_id;
+DROP TABLE units_of_measure;
+DROP TABLE uom_categories;
@@ -0,0 +1,64 @@
+-- =====================================================
+-- Units of measure. Units of one category convert by
+-- their factor to the category's reference unit; a
+-- variant keeps stock in its stock unit and may allow
+-- other units for buying and selling, optionally with
+-- its own factor (a carton of this item holds 12).
+-- Order lines are entered in any allowed unit; stock
+-- movements are always in the stock unit.
+-- =====================================================
+
+CREATE TABLE uom_categories (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    UNIQUE (company_id, name)
+);
+
+CREATE TABLE units_of_measure (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES uom_categories(id) ON DELETE RESTRICT,
+    name TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    -- Reference units of the category per one of this unit
+    factor NUMERIC(18,6) NOT NULL CHECK (factor > 0),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    UNIQUE (company_id, symbol)
+);
+
+ALTER TABLE product_variants
+    ADD COLUMN stock_unit_id UUID REFERENCES units_of_measure(id) ON DELETE RESTRICT;
+
+CREATE TABLE variant_units (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    variant_id UUID NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+    unit_id UUID NOT NULL REFERENCES units_of_measure(id) ON DELETE RESTRICT,
+    -- Stock units per one of this unit; NULL converts through the category
+    factor NUMERIC(18,6) CHECK (factor > 0),
+    for_purchase BOOLEAN NOT NULL DEFAULT true,
+    for_sales BOOLEAN NOT NULL DEFAULT true,
+    UNIQUE (variant_id, unit_id)
+);
+
+-- NULL means the line is in the variant's stock unit
+ALTER TABLE sales_order_lines
+    ADD COLUMN unit_id UUID REFERENCES units_of_measure(id) ON DELETE RESTRICT;
+ALTER TABLE purchase_order_lines
+    ADD COLUMN unit_id UUID REFERENCES units_of_measure(id) ON DELETE RESTRICT;
+
+-- Stock units per one unit_id of the variant, NULL when they do not convert
+CREATE FUNCTION uom_factor(p_variant_id UUID, p_unit_id UUID) RETURNS NUMERIC
+LANGUAGE sql STABLE AS $$
+    SELECT CASE
+        WHEN p_unit_id IS NULL OR p_unit_id = v.stock_unit_id THEN 1
+        ELSE COALESCE(
+            (SELECT vu.factor FROM variant_units vu
+             WHERE vu.variant_id = v.id AND vu.unit_id = p_unit_id),
+            (SELECT u.factor / s.factor
+             FROM units_of_measure u JOIN units_of_measure s ON s.category_id = u.category_id
+             WHERE u.id = p_unit_id AND s.id = v.stock_unit_id))
+    END
+    FROM product_variants v
+    WHERE v.id = p_variant_id
+$$;
@@ -0,0 +1,4 @@
+DROP TABLE price_list_items;
+DROP TABLE price_lists;
+DROP TABLE partner_group_members;
+DROP TABLE partner_groups;
@@ -0,0 +1,51 @@
+-- =====================================================
+-- Price lists per partner, partner group or everyone,
+-- in one currency, optionally valid between two dates,
+-- with quantity breaks. The most specific list wins:
+-- partner over group over general, then priority.
+-- =====================================================
+
+CREATE TABLE partner_groups (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    UNIQUE (company_id, name)
+);
+
+CREATE TABLE partner_group_members (
+    group_id UUID NOT NULL REFERENCES partner_groups(id) ON DELETE CASCADE,
+    partner_id UUID NOT NULL REFERENCES partners(id) ON DELETE CASCADE,
+    PRIMARY KEY (group_id, partner_id)
+);
+
+CREATE INDEX idx_partner_group_members_partner ON partner_group_members(partner_id);
+
+CREATE TABLE price_lists (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    currency_id UUID NOT NULL REFERENCES currencies(id),
+    partner_id UUID REFERENCES partners(id) ON DELETE CASCADE,
+    partner_group_id UUID REFERENCES partner_groups(id) ON DELETE CASCADE,
+    valid_from DATE,
+    valid_to DATE,
+    priority INTEGER NOT NULL DEFAULT 0,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (partner_id IS NULL OR partner_group_id IS NULL),
+    CHECK (valid_to IS NULL OR valid_from IS NULL OR valid_to >= valid_from)
+);
+
+CREATE INDEX idx_price_lists_company ON price_lists(company_id, currency_id) WHERE is_active;
+
+-- Prices are per stock unit; min_quantity is in the stock unit too
+CREATE TABLE price_list_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    price_list_id UUID NOT NULL REFERENCES price_lists(id) ON DELETE CASCADE,
+    variant_id UUID NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+    min_quantity NUMERIC(18,4) NOT NULL DEFAULT 0 CHECK (min_quantity >= 0),
+    unit_price NUMERIC(18,4) NOT NULL CHECK (unit_price >= 0),
+    UNIQUE (price_list_id, variant_id, min_quantity)
+);
+
+CREATE INDEX idx_price_list_items_variant ON price_list_items(variant_id);
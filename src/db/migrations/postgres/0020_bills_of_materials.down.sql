@@ -0,0 +1,7 @@
+DROP INDEX idx_stock_reservations_line;
+CREATE UNIQUE INDEX idx_stock_reservations_line
+    ON stock_reservations(sales_order_line_id) WHERE released_at IS NULL;
+
+DROP TABLE bom_lines;
+DROP TABLE boms;
+DROP TYPE bom_type;
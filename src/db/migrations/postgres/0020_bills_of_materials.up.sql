@@ -0,0 +1,36 @@
+-- =====================================================
+-- Bills of materials: a parent variant made of component
+-- variants. A kit is never stocked itself; selling it
+-- reserves and ships its components instead.
+-- =====================================================
+
+CREATE TYPE bom_type AS ENUM ('KIT', 'MANUFACTURE');
+
+CREATE TABLE boms (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    variant_id UUID NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+    bom_type bom_type NOT NULL DEFAULT 'KIT',
+    -- Parent units the component quantities make
+    quantity NUMERIC(18,4) NOT NULL DEFAULT 1 CHECK (quantity > 0),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- One bill in use per parent
+CREATE UNIQUE INDEX uq_boms_active_variant ON boms(variant_id) WHERE is_active;
+
+CREATE TABLE bom_lines (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    bom_id UUID NOT NULL REFERENCES boms(id) ON DELETE CASCADE,
+    component_id UUID NOT NULL REFERENCES product_variants(id),
+    quantity NUMERIC(18,4) NOT NULL CHECK (quantity > 0),
+    UNIQUE (bom_id, component_id)
+);
+
+CREATE INDEX idx_bom_lines_component ON bom_lines(component_id);
+
+-- A kit line reserves each of its components
+DROP INDEX idx_stock_reservations_line;
+CREATE UNIQUE INDEX idx_stock_reservations_line
+    ON stock_reservations(sales_order_line_id, variant_id) WHERE released_at IS NULL;
@@ -0,0 +1,11 @@
+DROP TABLE landed_cost_allocations;
+DROP TABLE landed_cost_charges;
+DROP TABLE landed_cost_receipts;
+DROP TABLE landed_costs;
+
+ALTER TABLE product_variants
+    DROP COLUMN volume,
+    DROP COLUMN weight;
+
+DROP TYPE allocation_method;
+DROP TYPE landed_cost_status;
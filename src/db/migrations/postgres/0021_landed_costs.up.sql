@@ -0,0 +1,56 @@
+-- =====================================================
+-- Landed costs: freight, duties and other charges on
+-- purchase receipts, allocated across the received lines
+-- and added to their unit cost and cost layers.
+-- =====================================================
+
+CREATE TYPE landed_cost_status AS ENUM ('DRAFT', 'POSTED', 'CANCELLED');
+CREATE TYPE allocation_method AS ENUM ('QUANTITY', 'VALUE', 'WEIGHT', 'VOLUME');
+
+-- Per stock unit, in whatever weight and volume units the company uses
+ALTER TABLE product_variants
+    ADD COLUMN weight NUMERIC(18,4) CHECK (weight >= 0),
+    ADD COLUMN volume NUMERIC(18,4) CHECK (volume >= 0);
+
+CREATE TABLE landed_costs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    vendor_id UUID REFERENCES partners(id),
+    reference TEXT,
+    cost_date DATE NOT NULL,
+    status landed_cost_status NOT NULL DEFAULT 'DRAFT',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_by UUID NOT NULL REFERENCES users(id),
+    posted_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_landed_costs_company ON landed_costs(company_id, cost_date);
+
+-- Purchase orders whose receipts share the charges
+CREATE TABLE landed_cost_receipts (
+    landed_cost_id UUID NOT NULL REFERENCES landed_costs(id) ON DELETE CASCADE,
+    purchase_order_id UUID NOT NULL REFERENCES purchase_orders(id),
+    PRIMARY KEY (landed_cost_id, purchase_order_id)
+);
+
+CREATE TABLE landed_cost_charges (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    landed_cost_id UUID NOT NULL REFERENCES landed_costs(id) ON DELETE CASCADE,
+    description TEXT NOT NULL,
+    amount NUMERIC(18,4) NOT NULL CHECK (amount > 0),
+    method allocation_method NOT NULL DEFAULT 'VALUE'
+);
+
+CREATE INDEX idx_landed_cost_charges_document ON landed_cost_charges(landed_cost_id);
+
+-- What each charge added to each receipt movement when posted
+CREATE TABLE landed_cost_allocations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    landed_cost_id UUID NOT NULL REFERENCES landed_costs(id) ON DELETE CASCADE,
+    charge_id UUID NOT NULL REFERENCES landed_cost_charges(id) ON DELETE CASCADE,
+    movement_id UUID NOT NULL REFERENCES stock_movements(id),
+    amount NUMERIC(18,4) NOT NULL,
+    UNIQUE (charge_id, movement_id)
+);
+
+CREATE INDEX idx_landed_cost_allocations_movement ON landed_cost_allocations(movement_id);
@@ -0,0 +1,8 @@
+DELETE FROM stock_count_lines WHERE location_id IS NOT NULL;
+
+DROP INDEX uq_stock_count_lines_location;
+
+ALTER TABLE stock_count_lines ADD CONSTRAINT stock_count_lines_count_id_variant_id_key
+    UNIQUE (count_id, variant_id);
+
+ALTER TABLE stock_count_lines DROP COLUMN location_id;
@@ -0,0 +1,13 @@
+-- =====================================================
+-- Counts by bin. Each bin holding a variant gets its own
+-- count line; the line without a location counts the
+-- variant's stock outside bins.
+-- =====================================================
+
+ALTER TABLE stock_count_lines ADD COLUMN location_id UUID REFERENCES storage_locations(id);
+
+ALTER TABLE stock_count_lines DROP CONSTRAINT stock_count_lines_count_id_variant_id_key;
+
+CREATE UNIQUE INDEX uq_stock_count_lines_location ON stock_count_lines (
+    count_id, variant_id, COALESCE(location_id, '00000000-0000-0000-0000-000000000000'::uuid)
+);
@@ -0,0 +1,2 @@
+DROP TRIGGER stock_transfer_lines_draft ON stock_transfer_lines;
+DROP FUNCTION check_transfer_line_draft();
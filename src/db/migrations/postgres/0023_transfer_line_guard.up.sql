@@ -0,0 +1,24 @@
+-- =====================================================
+-- Transfer lines can only be added or changed while the
+-- transfer is a draft; a line added after shipping would
+-- be received without ever leaving the source warehouse.
+-- The share lock queues behind a shipment in progress.
+-- =====================================================
+
+CREATE FUNCTION check_transfer_line_draft() RETURNS TRIGGER AS $$
+DECLARE
+    transfer_status transfer_status;
+BEGIN
+    SELECT status INTO transfer_status FROM stock_transfers
+    WHERE id = NEW.transfer_id FOR SHARE;
+    IF transfer_status <> 'DRAFT' THEN
+        RAISE EXCEPTION 'transfer is %, lines can only change while it is a draft', transfer_status
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER stock_transfer_lines_draft
+    BEFORE INSERT OR UPDATE OF transfer_id, variant_id, quantity ON stock_transfer_lines
+    FOR EACH ROW EXECUTE FUNCTION check_transfer_line_draft();
@@ -0,0 +1,2 @@
+DROP TRIGGER landed_cost_charges_draft ON landed_cost_charges;
+DROP FUNCTION check_landed_cost_charge_draft();
@@ -0,0 +1,24 @@
+-- =====================================================
+-- Charges can only be added or changed while their landed
+-- cost is a draft; a charge added after posting would
+-- never be allocated to the receipts. The share lock
+-- queues behind a posting in progress.
+-- =====================================================
+
+CREATE FUNCTION check_landed_cost_charge_draft() RETURNS TRIGGER AS $$
+DECLARE
+    document_status landed_cost_status;
+BEGIN
+    SELECT status INTO document_status FROM landed_costs
+    WHERE id = NEW.landed_cost_id FOR SHARE;
+    IF document_status <> 'DRAFT' THEN
+        RAISE EXCEPTION 'landed cost is %, charges can only change while it is a draft', document_status
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER landed_cost_charges_draft
+    BEFORE INSERT OR UPDATE OF landed_cost_id, amount, method ON landed_cost_charges
+    FOR EACH ROW EXECUTE FUNCTION check_landed_cost_charge_draft();
@@ -0,0 +1,3 @@
+DROP TRIGGER purchase_order_lines_unit ON purchase_order_lines;
+DROP TRIGGER sales_order_lines_unit ON sales_order_lines;
+DROP FUNCTION check_order_line_unit();
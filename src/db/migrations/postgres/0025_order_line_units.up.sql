@@ -0,0 +1,40 @@
+-- =====================================================
+-- Order lines may only be entered in the variant's stock
+-- unit or a unit it allows for that side of the business,
+-- and the unit must convert to the stock unit. Checked here
+-- so every way of writing a line goes through it.
+-- =====================================================
+
+CREATE FUNCTION check_order_line_unit() RETURNS TRIGGER AS $$
+DECLARE
+    purchase BOOLEAN := TG_ARGV[0] = 'purchase';
+BEGIN
+    IF NEW.unit_id IS NULL THEN
+        RETURN NEW;
+    END IF;
+    IF NOT EXISTS (
+        SELECT 1 FROM product_variants v
+        WHERE v.id = NEW.variant_id AND (NEW.unit_id = v.stock_unit_id OR EXISTS (
+            SELECT 1 FROM variant_units vu
+            WHERE vu.variant_id = v.id AND vu.unit_id = NEW.unit_id
+            AND CASE WHEN purchase THEN vu.for_purchase ELSE vu.for_sales END))
+    ) THEN
+        RAISE EXCEPTION 'the unit is not allowed for % of this variant',
+            CASE WHEN purchase THEN 'purchases' ELSE 'sales' END
+            USING ERRCODE = 'check_violation';
+    END IF;
+    IF uom_factor(NEW.variant_id, NEW.unit_id) IS NULL THEN
+        RAISE EXCEPTION 'the unit does not convert to the variant''s stock unit'
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER sales_order_lines_unit
+    BEFORE INSERT OR UPDATE OF variant_id, unit_id ON sales_order_lines
+    FOR EACH ROW EXECUTE FUNCTION check_order_line_unit('sales');
+
+CREATE TRIGGER purchase_order_lines_unit
+    BEFORE INSERT OR UPDATE OF variant_id, unit_id ON purchase_order_lines
+    FOR EACH ROW EXECUTE FUNCTION check_order_line_unit('purchase');
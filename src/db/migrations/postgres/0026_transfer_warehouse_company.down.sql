@@ -0,0 +1,9 @@
+ALTER TABLE stock_transfers
+    DROP CONSTRAINT stock_transfers_from_warehouse_fkey,
+    DROP CONSTRAINT stock_transfers_to_warehouse_fkey,
+    ADD CONSTRAINT stock_transfers_from_warehouse_id_fkey
+        FOREIGN KEY (from_warehouse_id) REFERENCES warehouses(id),
+    ADD CONSTRAINT stock_transfers_to_warehouse_id_fkey
+        FOREIGN KEY (to_warehouse_id) REFERENCES warehouses(id);
+
+ALTER TABLE warehouses DROP CONSTRAINT warehouses_id_company_key;
@@ -0,0 +1,14 @@
+-- =====================================================
+-- Both warehouses of a transfer belong to its company.
+-- =====================================================
+
+ALTER TABLE warehouses
+    ADD CONSTRAINT warehouses_id_company_key UNIQUE (id, company_id);
+
+ALTER TABLE stock_transfers
+    DROP CONSTRAINT stock_transfers_from_warehouse_id_fkey,
+    DROP CONSTRAINT stock_transfers_to_warehouse_id_fkey,
+    ADD CONSTRAINT stock_transfers_from_warehouse_fkey
+        FOREIGN KEY (from_warehouse_id, company_id) REFERENCES warehouses(id, company_id),
+    ADD CONSTRAINT stock_transfers_to_warehouse_fkey
+        FOREIGN KEY (to_warehouse_id, company_id) REFERENCES warehouses(id, company_id);
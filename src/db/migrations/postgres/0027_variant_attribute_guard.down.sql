@@ -0,0 +1,4 @@
+DROP TRIGGER product_attributes_definition ON product_attributes;
+DROP FUNCTION check_attribute_definition();
+DROP TRIGGER product_variants_attributes ON product_variants;
+DROP FUNCTION check_variant_attributes();
@@ -0,0 +1,65 @@
+-- =====================================================
+-- Variants of a product with attribute definitions carry
+-- exactly one allowed value per attribute, however they are
+-- written. Definitions are fixed once a product has
+-- variants, except that values can be allowed and the
+-- attributes reordered; the product's row lock keeps both
+-- sides in step.
+-- =====================================================
+
+CREATE FUNCTION check_variant_attributes() RETURNS TRIGGER AS $$
+DECLARE
+    definition RECORD;
+    defined INTEGER := 0;
+BEGIN
+    PERFORM 1 FROM products WHERE id = NEW.product_id FOR SHARE;
+    FOR definition IN
+        SELECT name, allowed_values FROM product_attributes WHERE product_id = NEW.product_id
+    LOOP
+        defined := defined + 1;
+        IF jsonb_typeof(NEW.attributes -> definition.name) IS DISTINCT FROM 'string'
+            OR NOT definition.allowed_values ? (NEW.attributes ->> definition.name) THEN
+            RAISE EXCEPTION 'variants of this product need an allowed value for %', definition.name
+                USING ERRCODE = 'check_violation';
+        END IF;
+    END LOOP;
+    IF defined > 0 AND (SELECT count(*) FROM jsonb_object_keys(NEW.attributes)) <> defined THEN
+        RAISE EXCEPTION 'the variant has attributes the product does not define'
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER product_variants_attributes
+    BEFORE INSERT OR UPDATE OF product_id, attributes ON product_variants
+    FOR EACH ROW EXECUTE FUNCTION check_variant_attributes();
+
+CREATE FUNCTION check_attribute_definition() RETURNS TRIGGER AS $$
+DECLARE
+    product UUID := CASE WHEN TG_OP = 'DELETE' THEN OLD.product_id ELSE NEW.product_id END;
+BEGIN
+    PERFORM 1 FROM products WHERE id = product FOR UPDATE;
+    IF NOT FOUND THEN
+        -- The product itself is being deleted
+        RETURN CASE WHEN TG_OP = 'DELETE' THEN OLD ELSE NEW END;
+    END IF;
+    IF TG_OP = 'UPDATE' AND NEW.product_id = OLD.product_id AND NEW.name = OLD.name THEN
+        IF EXISTS (
+            SELECT 1 FROM product_variants
+            WHERE product_id = product AND NOT NEW.allowed_values ? (attributes ->> NEW.name)
+        ) THEN
+            RAISE EXCEPTION 'a variant still has a value of % that would no longer be allowed', NEW.name
+                USING ERRCODE = 'check_violation';
+        END IF;
+    ELSIF EXISTS (SELECT 1 FROM product_variants WHERE product_id = product) THEN
+        RAISE EXCEPTION 'attributes cannot be added, renamed or removed once the product has variants'
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN CASE WHEN TG_OP = 'DELETE' THEN OLD ELSE NEW END;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER product_attributes_definition
+    BEFORE INSERT OR UPDATE OR DELETE ON product_attributes
+    FOR EACH ROW EXECUTE FUNCTION check_attribute_definition();
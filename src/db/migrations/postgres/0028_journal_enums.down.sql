@@ -0,0 +1,8 @@
+ALTER TABLE chart_of_accounts
+    ALTER COLUMN account_type TYPE VARCHAR(20) USING lower(account_type::text);
+ALTER TABLE chart_of_accounts ADD CONSTRAINT chart_of_accounts_account_type_check
+    CHECK (account_type IN ('asset','liability','equity','income','expense'));
+
+ALTER TABLE journal_entries ADD COLUMN posted BOOLEAN NOT NULL DEFAULT false;
+UPDATE journal_entries SET posted = true WHERE status = 'POSTED';
+ALTER TABLE journal_entries DROP COLUMN status;
@@ -0,0 +1,13 @@
+-- =====================================================
+-- Journal entries carry the journal_status declared in
+-- 0001 instead of a posted flag, and accounts the
+-- account_type enum instead of checked free text.
+-- =====================================================
+
+ALTER TABLE journal_entries ADD COLUMN status journal_status NOT NULL DEFAULT 'DRAFT';
+UPDATE journal_entries SET status = 'POSTED' WHERE posted;
+ALTER TABLE journal_entries DROP COLUMN posted;
+
+ALTER TABLE chart_of_accounts DROP CONSTRAINT chart_of_accounts_account_type_check;
+ALTER TABLE chart_of_accounts
+    ALTER COLUMN account_type TYPE account_type USING upper(account_type)::account_type;
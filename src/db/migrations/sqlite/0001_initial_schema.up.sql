@@ -0,0 +1,265 @@
+-- =====================================================
+-- Embedded SQLite schema. Mirrors the Postgres tables used by
+-- the repositories: UUIDs are BLOBs, enum types are TEXT with
+-- CHECK constraints and timestamps are ISO-8601 TEXT.
+-- =====================================================
+
+-- =====================================================
+-- CURRENCIES
+-- =====================================================
+CREATE TABLE currencies (
+    id BLOB PRIMARY KEY,
+    code TEXT UNIQUE NOT NULL,
+    name TEXT NOT NULL,
+    symbol TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT 1
+);
+
+-- =====================================================
+-- COMPANIES
+-- =====================================================
+CREATE TABLE companies (
+    id BLOB PRIMARY KEY,
+    name TEXT NOT NULL,
+    address TEXT,
+    base_currency_id BLOB REFERENCES currencies(id),
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- =====================================================
+-- USERS
+-- =====================================================
+CREATE TABLE users (
+    id BLOB PRIMARY KEY,
+    email TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    password_hash TEXT NOT NULL,
+    role TEXT NOT NULL DEFAULT 'inventory'
+        CHECK (role IN ('admin', 'accountant', 'inventory', 'sales', 'purchasing')),
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- =====================================================
+-- PARTNERS (Customers / Vendors)
+-- =====================================================
+CREATE TABLE partners (
+    id BLOB PRIMARY KEY,
+    company_id BLOB NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    type TEXT NOT NULL CHECK (type IN ('customer', 'vendor', 'both')),
+    email TEXT,
+    phone TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- =====================================================
+-- PRODUCTS
+-- =====================================================
+CREATE TABLE products (
+    id BLOB PRIMARY KEY,
+    company_id BLOB NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT,
+    category TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE product_variants (
+    id BLOB PRIMARY KEY,
+    product_id BLOB NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    sku TEXT UNIQUE,
+    barcode TEXT,
+    attributes TEXT,
+    cost_price REAL NOT NULL DEFAULT 0,
+    selling_price REAL NOT NULL DEFAULT 0,
+    inventory_account_id BLOB,
+    cogs_account_id BLOB,
+    revenue_account_id BLOB,
+    is_active BOOLEAN NOT NULL DEFAULT 1
+);
+
+-- =====================================================
+-- WAREHOUSES
+-- =====================================================
+CREATE TABLE warehouses (
+    id BLOB PRIMARY KEY,
+    company_id BLOB NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT 1
+);
+
+-- =====================================================
+-- STOCK MOVEMENTS
+-- =====================================================
+CREATE TABLE stock_movements (
+    id BLOB PRIMARY KEY,
+    company_id BLOB NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    variant_id BLOB NOT NULL REFERENCES product_variants(id),
+    warehouse_id BLOB NOT NULL REFERENCES warehouses(id),
+    quantity REAL NOT NULL,
+    movement_type TEXT NOT NULL CHECK (movement_type IN ('in', 'out', 'adjustment')),
+    reference_type TEXT,
+    reference_id BLOB,
+    unit_cost REAL,
+    movement_date DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_stock_variant ON stock_movements(variant_id);
+CREATE INDEX idx_stock_warehouse ON stock_movements(warehouse_id);
+
+CREATE TABLE stock_ledger (
+    variant_id BLOB REFERENCES product_variants(id) ON DELETE CASCADE,
+    warehouse_id BLOB REFERENCES warehouses(id) ON DELETE CASCADE,
+    quantity REAL NOT NULL DEFAULT 0,
+    PRIMARY KEY (variant_id, warehouse_id)
+);
+
+-- =====================================================
+-- SALES
+-- =====================================================
+CREATE TABLE sales_orders (
+    id BLOB PRIMARY KEY,
+    company_id BLOB NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    customer_id BLOB NOT NULL REFERENCES partners(id),
+    order_date DATE NOT NULL,
+    currency_id BLOB NOT NULL REFERENCES currencies(id),
+    status TEXT NOT NULL DEFAULT 'DRAFT',
+    total_amount REAL NOT NULL DEFAULT 0,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by BLOB NOT NULL REFERENCES users(id)
+);
+
+CREATE TABLE sales_order_lines (
+    id BLOB PRIMARY KEY,
+    sales_order_id BLOB NOT NULL REFERENCES sales_orders(id) ON DELETE CASCADE,
+    variant_id BLOB NOT NULL REFERENCES product_variants(id),
+    quantity REAL NOT NULL,
+    unit_price REAL NOT NULL,
+    subtotal REAL GENERATED ALWAYS AS (quantity * unit_price) STORED
+);
+
+CREATE INDEX idx_so_lines_order ON sales_order_lines(sales_order_id);
+CREATE INDEX idx_so_lines_variant ON sales_order_lines(variant_id);
+
+-- =====================================================
+-- PURCHASE
+-- =====================================================
+CREATE TABLE purchase_orders (
+    id BLOB PRIMARY KEY,
+    company_id BLOB NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    vendor_id BLOB NOT NULL REFERENCES partners(id),
+    order_date DATE NOT NULL,
+    currency_id BLOB NOT NULL REFERENCES currencies(id),
+    status TEXT NOT NULL DEFAULT 'DRAFT',
+    total_amount REAL NOT NULL DEFAULT 0,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by BLOB NOT NULL REFERENCES users(id)
+);
+
+CREATE TABLE purchase_order_lines (
+    id BLOB PRIMARY KEY,
+    purchase_order_id BLOB NOT NULL REFERENCES purchase_orders(id) ON DELETE CASCADE,
+    variant_id BLOB NOT NULL REFERENCES product_variants(id),
+    quantity REAL NOT NULL,
+    unit_cost REAL NOT NULL,
+    subtotal REAL GENERATED ALWAYS AS (quantity * unit_cost) STORED
+);
+
+CREATE INDEX idx_po_lines_order ON purchase_order_lines(purchase_order_id);
+CREATE INDEX idx_po_lines_variant ON purchase_order_lines(variant_id);
+
+-- =====================================================
+-- ORDER TOTALS (Postgres keeps these with plpgsql triggers)
+-- =====================================================
+CREATE TRIGGER sales_order_lines_insert_total AFTER INSERT ON sales_order_lines
+BEGIN
+    UPDATE sales_orders SET total_amount = (
+        SELECT COALESCE(SUM(subtotal), 0) FROM sales_order_lines WHERE sales_order_id = NEW.sales_order_id
+    ) WHERE id = NEW.sales_order_id;
+END;
+
+CREATE TRIGGER sales_order_lines_update_total AFTER UPDATE ON sales_order_lines
+BEGIN
+    UPDATE sales_orders SET total_amount = (
+        SELECT COALESCE(SUM(subtotal), 0) FROM sales_order_lines WHERE sales_order_id = NEW.sales_order_id
+    ) WHERE id = NEW.sales_order_id;
+END;
+
+CREATE TRIGGER sales_order_lines_delete_total AFTER DELETE ON sales_order_lines
+BEGIN
+    UPDATE sales_orders SET total_amount = (
+        SELECT COALESCE(SUM(subtotal), 0) FROM sales_order_lines WHERE sales_order_id = OLD.sales_order_id
+    ) WHERE id = OLD.sales_order_id;
+END;
+
+CREATE TRIGGER purchase_order_lines_insert_total AFTER INSERT ON purchase_order_lines
+BEGIN
+    UPDATE purchase_orders SET total_amount = (
+        SELECT COALESCE(SUM(subtotal), 0) FROM purchase_order_lines WHERE purchase_order_id = NEW.purchase_order_id
+    ) WHERE id = NEW.purchase_order_id;
+END;
+
+CREATE TRIGGER purchase_order_lines_update_total AFTER UPDATE ON purchase_order_lines
+BEGIN
+    UPDATE purchase_orders SET total_amount = (
+        SELECT COALESCE(SUM(subtotal), 0) FROM purchase_order_lines WHERE purchase_order_id = NEW.purchase_order_id
+    ) WHERE id = NEW.purchase_order_id;
+END;
+
+CREATE TRIGGER purchase_order_lines_delete_total AFTER DELETE ON purchase_order_lines
+BEGIN
+    UPDATE purchase_orders SET total_amount = (
+        SELECT COALESCE(SUM(subtotal), 0) FROM purchase_order_lines WHERE purchase_order_id = OLD.purchase_order_id
+    ) WHERE id = OLD.purchase_order_id;
+END;
+
+-- =====================================================
+-- CHART OF ACCOUNTS
+-- =====================================================
+CREATE TABLE chart_of_accounts (
+    id BLOB PRIMARY KEY,
+    company_id BLOB NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    code TEXT NOT NULL,
+    name TEXT NOT NULL,
+    account_type TEXT NOT NULL CHECK (
+        account_type IN ('asset', 'liability', 'equity', 'income', 'expense')
+    ),
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    UNIQUE(company_id, code)
+);
+
+-- =====================================================
+-- JOURNAL ENTRIES
+-- =====================================================
+CREATE TABLE journal_entries (
+    id BLOB PRIMARY KEY,
+    company_id BLOB NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    entry_date DATE NOT NULL,
+    reference TEXT,
+    currency_id BLOB REFERENCES currencies(id),
+    exchange_rate REAL,
+    posted BOOLEAN NOT NULL DEFAULT 0,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by BLOB NOT NULL REFERENCES users(id)
+);
+
+CREATE TABLE journal_lines (
+    id BLOB PRIMARY KEY,
+    journal_entry_id BLOB NOT NULL REFERENCES journal_entries(id) ON DELETE CASCADE,
+    account_id BLOB NOT NULL REFERENCES chart_of_accounts(id),
+    partner_id BLOB REFERENCES partners(id),
+    debit REAL NOT NULL DEFAULT 0,
+    credit REAL NOT NULL DEFAULT 0,
+    currency_amount REAL,
+    description TEXT,
+    CHECK (debit >= 0 AND credit >= 0)
+);
+
+CREATE INDEX idx_journal_account ON journal_lines(account_id);
+CREATE INDEX idx_journal_lines_entry ON journal_lines(journal_entry_id);
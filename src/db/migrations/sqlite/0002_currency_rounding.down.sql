@@ -0,0 +1,2 @@
+ALTER TABLE currencies DROP COLUMN rounding_mode;
+ALTER TABLE currencies DROP COLUMN decimal_places;
@@ -0,0 +1,7 @@
+ALTER TABLE currencies ADD COLUMN decimal_places INTEGER NOT NULL DEFAULT 2
+    CHECK (decimal_places BETWEEN 0 AND 4);
+ALTER TABLE currencies ADD COLUMN rounding_mode TEXT NOT NULL DEFAULT 'HALF_UP'
+    CHECK (rounding_mode IN ('HALF_UP', 'HALF_EVEN', 'DOWN', 'UP'));
+
+UPDATE currencies SET decimal_places = 0 WHERE code IN ('JPY', 'KRW', 'VND', 'CLP', 'ISK', 'UZS');
+UPDATE currencies SET decimal_places = 3 WHERE code IN ('BHD', 'KWD', 'OMR', 'JOD', 'TND');
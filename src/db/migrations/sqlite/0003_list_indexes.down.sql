@@ -0,0 +1,11 @@
+DROP INDEX idx_journal_entries_company_date;
+DROP INDEX idx_purchase_orders_vendor;
+DROP INDEX idx_purchase_orders_company_date;
+DROP INDEX idx_sales_orders_customer;
+DROP INDEX idx_sales_orders_company_date;
+DROP INDEX idx_stock_company_date;
+DROP INDEX idx_warehouses_company;
+DROP INDEX idx_variants_product;
+DROP INDEX idx_products_company_name;
+DROP INDEX idx_partners_company_name;
+DROP INDEX idx_users_name;
@@ -0,0 +1,16 @@
+-- =====================================================
+-- Indexes matching the default list order of each
+-- entity, so keyset pages are index range scans.
+-- =====================================================
+
+CREATE INDEX idx_users_name ON users(name, id);
+CREATE INDEX idx_partners_company_name ON partners(company_id, name, id);
+CREATE INDEX idx_products_company_name ON products(company_id, name, id);
+CREATE INDEX idx_variants_product ON product_variants(product_id);
+CREATE INDEX idx_warehouses_company ON warehouses(company_id, name, id);
+CREATE INDEX idx_stock_company_date ON stock_movements(company_id, movement_date DESC, id DESC);
+CREATE INDEX idx_sales_orders_company_date ON sales_orders(company_id, order_date DESC, id DESC);
+CREATE INDEX idx_sales_orders_customer ON sales_orders(customer_id);
+CREATE INDEX idx_purchase_orders_company_date ON purchase_orders(company_id, order_date DESC, id DESC);
+CREATE INDEX idx_purchase_orders_vendor ON purchase_orders(vendor_id);
+CREATE INDEX idx_journal_entries_company_date ON journal_entries(company_id, entry_date DESC, id DESC);
@@ -0,0 +1,2 @@
+ALTER TABLE companies ADD COLUMN costing_method TEXT NOT NULL DEFAULT 'FIFO'
+    CHECK (costing_method IN ('FIFO', 'WEIGHTED_AVERAGE'));
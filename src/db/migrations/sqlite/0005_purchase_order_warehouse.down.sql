@@ -0,0 +1 @@
+ALTER TABLE purchase_orders DROP COLUMN warehouse_id;
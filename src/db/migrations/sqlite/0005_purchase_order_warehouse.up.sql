@@ -0,0 +1 @@
+ALTER TABLE purchase_orders ADD COLUMN warehouse_id BLOB REFERENCES warehouses(id);
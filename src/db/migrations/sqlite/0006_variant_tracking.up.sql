@@ -0,0 +1,2 @@
+ALTER TABLE product_variants ADD COLUMN tracking TEXT NOT NULL DEFAULT 'NONE'
+    CHECK (tracking IN ('NONE', 'LOT', 'SERIAL'));
@@ -0,0 +1,3 @@
+-- Storage locations are kept in Postgres only; the column keeps the shape of
+-- stock_movements shared by both backends.
+ALTER TABLE stock_movements ADD COLUMN location_id BLOB;
@@ -0,0 +1,3 @@
+ALTER TABLE purchase_order_lines DROP COLUMN unit_id;
+ALTER TABLE sales_order_lines DROP COLUMN unit_id;
+ALTER TABLE product_variants DROP COLUMN stock_unit_id;
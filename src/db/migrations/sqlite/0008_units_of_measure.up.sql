@@ -0,0 +1,5 @@
+-- Units of measure are kept in Postgres only; the columns keep the shape of
+-- the shared tables.
+ALTER TABLE product_variants ADD COLUMN stock_unit_id BLOB;
+ALTER TABLE sales_order_lines ADD COLUMN unit_id BLOB;
+ALTER TABLE purchase_order_lines ADD COLUMN unit_id BLOB;
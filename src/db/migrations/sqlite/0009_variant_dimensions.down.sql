@@ -0,0 +1,2 @@
+ALTER TABLE product_variants DROP COLUMN volume;
+ALTER TABLE product_variants DROP COLUMN weight;
@@ -0,0 +1,2 @@
+ALTER TABLE product_variants ADD COLUMN weight REAL;
+ALTER TABLE product_variants ADD COLUMN volume REAL;
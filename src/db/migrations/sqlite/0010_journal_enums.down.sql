@@ -0,0 +1,40 @@
+CREATE TABLE chart_of_accounts_old (
+    id BLOB PRIMARY KEY,
+    company_id BLOB NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    code TEXT NOT NULL,
+    name TEXT NOT NULL,
+    account_type TEXT NOT NULL CHECK (
+        account_type IN ('asset', 'liability', 'equity', 'income', 'expense')
+    ),
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    UNIQUE(company_id, code)
+);
+INSERT INTO chart_of_accounts_old (id, company_id, code, name, account_type, is_active)
+SELECT id, company_id, code, name, lower(account_type), is_active FROM chart_of_accounts;
+
+CREATE TABLE journal_lines_old (
+    id BLOB PRIMARY KEY,
+    journal_entry_id BLOB NOT NULL REFERENCES journal_entries(id) ON DELETE CASCADE,
+    account_id BLOB NOT NULL REFERENCES chart_of_accounts_old(id),
+    partner_id BLOB REFERENCES partners(id),
+    debit REAL NOT NULL DEFAULT 0,
+    credit REAL NOT NULL DEFAULT 0,
+    currency_amount REAL,
+    description TEXT,
+    CHECK (debit >= 0 AND credit >= 0)
+);
+INSERT INTO journal_lines_old
+SELECT id, journal_entry_id, account_id, partner_id, debit, credit, currency_amount, description
+FROM journal_lines;
+
+DROP TABLE journal_lines;
+DROP TABLE chart_of_accounts;
+ALTER TABLE chart_of_accounts_old RENAME TO chart_of_accounts;
+ALTER TABLE journal_lines_old RENAME TO journal_lines;
+
+CREATE INDEX idx_journal_account ON journal_lines(account_id);
+CREATE INDEX idx_journal_lines_entry ON journal_lines(journal_entry_id);
+
+ALTER TABLE journal_entries ADD COLUMN posted BOOLEAN NOT NULL DEFAULT 0;
+UPDATE journal_entries SET posted = 1 WHERE status = 'POSTED';
+ALTER TABLE journal_entries DROP COLUMN status;
@@ -0,0 +1,50 @@
+-- =====================================================
+-- Journal entries carry a status instead of a posted
+-- flag, and account types use the Postgres labels.
+-- =====================================================
+
+ALTER TABLE journal_entries ADD COLUMN status TEXT NOT NULL DEFAULT 'DRAFT'
+    CHECK (status IN ('DRAFT', 'POSTED', 'CANCELLED'));
+UPDATE journal_entries SET status = 'POSTED' WHERE posted;
+ALTER TABLE journal_entries DROP COLUMN posted;
+
+-- The CHECK on account_type cannot be altered, so the table is rebuilt.
+-- journal_lines is rebuilt with it: dropping accounts that lines still
+-- reference fails the foreign key even when deferred, and renaming
+-- chart_of_accounts_new carries the lines' reference along.
+CREATE TABLE chart_of_accounts_new (
+    id BLOB PRIMARY KEY,
+    company_id BLOB NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    code TEXT NOT NULL,
+    name TEXT NOT NULL,
+    account_type TEXT NOT NULL CHECK (
+        account_type IN ('ASSET', 'LIABILITY', 'EQUITY', 'INCOME', 'EXPENSE')
+    ),
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    UNIQUE(company_id, code)
+);
+INSERT INTO chart_of_accounts_new (id, company_id, code, name, account_type, is_active)
+SELECT id, company_id, code, name, upper(account_type), is_active FROM chart_of_accounts;
+
+CREATE TABLE journal_lines_new (
+    id BLOB PRIMARY KEY,
+    journal_entry_id BLOB NOT NULL REFERENCES journal_entries(id) ON DELETE CASCADE,
+    account_id BLOB NOT NULL REFERENCES chart_of_accounts_new(id),
+    partner_id BLOB REFERENCES partners(id),
+    debit REAL NOT NULL DEFAULT 0,
+    credit REAL NOT NULL DEFAULT 0,
+    currency_amount REAL,
+    description TEXT,
+    CHECK (debit >= 0 AND credit >= 0)
+);
+INSERT INTO journal_lines_new
+SELECT id, journal_entry_id, account_id, partner_id, debit, credit, currency_amount, description
+FROM journal_lines;
+
+DROP TABLE journal_lines;
+DROP TABLE chart_of_accounts;
+ALTER TABLE chart_of_accounts_new RENAME TO chart_of_accounts;
+ALTER TABLE journal_lines_new RENAME TO journal_lines;
+
+CREATE INDEX idx_journal_account ON journal_lines(account_id);
+CREATE INDEX idx_journal_lines_entry ON journal_lines(journal_entry_id);
@@ -1,5 +1,21 @@
+pub mod backend;
+pub mod barcode;
+pub mod catalog;
+pub mod connection;
+pub mod enums;
+pub mod inventory;
+pub mod migrations;
 pub mod models;
+pub mod money;
+pub mod pricing;
+pub mod repositories;
 pub mod repository;
+pub mod search;
+pub mod storage;
+pub mod transaction;
 
+#[cfg(test)]
+pub(crate) mod testing;
+
+pub use enums::*;
 pub use models::*;
-pub use repository::*;
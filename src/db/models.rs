@@ -1,13 +1,21 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::db::enums::{
+    AccountType, AllocationMethod, BomType, CostingMethod, CountStatus, JournalStatus,
+    LandedCostStatus, LocationKind, MovementType, OrderStatus, PartnerType, RoundingMode,
+    TrackingMode, TransferStatus, UserRole,
+};
+use crate::db::money::{Factor, Money, Quantity, Rate};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub id: Uuid,
     pub email: String,
     pub name: String,
-    pub role: String,
+    pub role: UserRole,
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -18,25 +26,26 @@ pub struct CreateUser {
     pub email: String,
     pub name: String,
     pub password_hash: String,
-    pub role: String,
+    pub role: UserRole,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateUser {
     pub email: Option<String>,
     pub name: Option<String>,
-    pub role: Option<String>,
+    pub role: Option<UserRole>,
     pub is_active: Option<bool>,
 }
 
 // Additional models for the new schema
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Company {
     pub id: Uuid,
     pub name: String,
     pub address: Option<String>,
     pub base_currency_id: Option<Uuid>,
+    pub costing_method: CostingMethod,
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -47,14 +56,26 @@ pub struct CreateCompany {
     pub name: String,
     pub address: Option<String>,
     pub base_currency_id: Option<Uuid>,
+    pub costing_method: CostingMethod,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateCompany {
+    pub name: Option<String>,
+    pub address: Option<Option<String>>,
+    pub base_currency_id: Option<Option<Uuid>>,
+    pub costing_method: Option<CostingMethod>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Currency {
     pub id: Uuid,
     pub code: String,
     pub name: String,
     pub symbol: Option<String>,
+    /// Minor units, e.g. 2 for cents.
+    pub decimal_places: i16,
+    pub rounding_mode: RoundingMode,
     pub is_active: bool,
 }
 
@@ -63,16 +84,28 @@ pub struct CreateCurrency {
     pub code: String,
     pub name: String,
     pub symbol: Option<String>,
+    pub decimal_places: i16,
+    pub rounding_mode: RoundingMode,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateCurrency {
+    pub code: Option<String>,
+    pub name: Option<String>,
+    pub symbol: Option<Option<String>>,
+    pub decimal_places: Option<i16>,
+    pub rounding_mode: Option<RoundingMode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Partner {
     pub id: Uuid,
     pub company_id: Uuid,
     pub name: String,
-    pub partner_type: String,
+    pub partner_type: PartnerType,
     pub email: Option<String>,
     pub phone: Option<String>,
+    pub is_active: bool,
     pub created_at: DateTime<Utc>,
 }
 
@@ -80,12 +113,20 @@ pub struct Partner {
 pub struct CreatePartner {
     pub company_id: Uuid,
     pub name: String,
-    pub partner_type: String,
+    pub partner_type: PartnerType,
     pub email: Option<String>,
     pub phone: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdatePartner {
+    pub name: Option<String>,
+    pub partner_type: Option<PartnerType>,
+    pub email: Option<Option<String>>,
+    pub phone: Option<Option<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Product {
     pub id: Uuid,
     pub company_id: Uuid,
@@ -104,18 +145,31 @@ pub struct CreateProduct {
     pub category: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateProduct {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub category: Option<Option<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct ProductVariant {
     pub id: Uuid,
     pub product_id: Uuid,
     pub sku: Option<String>,
     pub barcode: Option<String>,
     pub attributes: Option<serde_json::Value>,
-    pub cost_price: f64,
-    pub selling_price: f64,
+    pub cost_price: Money,
+    pub selling_price: Money,
     pub inventory_account_id: Option<Uuid>,
     pub cogs_account_id: Option<Uuid>,
     pub revenue_account_id: Option<Uuid>,
+    pub tracking: TrackingMode,
+    /// Unit stock is kept and moved in; `None` for plain pieces.
+    pub stock_unit_id: Option<Uuid>,
+    /// Per stock unit; used to allocate landed costs.
+    pub weight: Option<Quantity>,
+    pub volume: Option<Quantity>,
     pub is_active: bool,
 }
 
@@ -125,18 +179,180 @@ pub struct CreateProductVariant {
     pub sku: Option<String>,
     pub barcode: Option<String>,
     pub attributes: Option<serde_json::Value>,
-    pub cost_price: f64,
-    pub selling_price: f64,
+    pub cost_price: Money,
+    pub selling_price: Money,
     pub inventory_account_id: Option<Uuid>,
     pub cogs_account_id: Option<Uuid>,
     pub revenue_account_id: Option<Uuid>,
+    pub tracking: TrackingMode,
+    #[serde(default)]
+    pub stock_unit_id: Option<Uuid>,
+    #[serde(default)]
+    pub weight: Option<Quantity>,
+    #[serde(default)]
+    pub volume: Option<Quantity>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateProductVariant {
+    pub sku: Option<Option<String>>,
+    pub barcode: Option<Option<String>>,
+    pub attributes: Option<Option<serde_json::Value>>,
+    pub cost_price: Option<Money>,
+    pub selling_price: Option<Money>,
+    pub inventory_account_id: Option<Option<Uuid>>,
+    pub cogs_account_id: Option<Option<Uuid>>,
+    pub revenue_account_id: Option<Option<Uuid>>,
+    pub tracking: Option<TrackingMode>,
+    pub stock_unit_id: Option<Option<Uuid>>,
+    pub weight: Option<Option<Quantity>>,
+    pub volume: Option<Option<Quantity>>,
+}
+
+/// A group of units that convert into each other, e.g. weight.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UomCategory {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateUomCategory {
+    pub company_id: Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateUomCategory {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UnitOfMeasure {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub category_id: Uuid,
+    pub name: String,
+    pub symbol: String,
+    /// Reference units of the category per one of this unit.
+    pub factor: Factor,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateUnitOfMeasure {
+    pub company_id: Uuid,
+    pub category_id: Uuid,
+    pub name: String,
+    pub symbol: String,
+    pub factor: Factor,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateUnitOfMeasure {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+}
+
+/// A unit a variant may be bought or sold in besides its stock unit.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct VariantUnit {
+    pub id: Uuid,
+    pub variant_id: Uuid,
+    pub unit_id: Uuid,
+    /// Stock units per one of this unit, when it differs from what the
+    /// category gives or the units are in different categories.
+    pub factor: Option<Factor>,
+    pub for_purchase: bool,
+    pub for_sales: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateVariantUnit {
+    pub variant_id: Uuid,
+    pub unit_id: Uuid,
+    pub factor: Option<Factor>,
+    pub for_purchase: bool,
+    pub for_sales: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateVariantUnit {
+    pub factor: Option<Option<Factor>>,
+    pub for_purchase: Option<bool>,
+    pub for_sales: Option<bool>,
+}
+
+/// A bill of materials: what `quantity` units of the parent variant are
+/// made of.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Bom {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub variant_id: Uuid,
+    pub bom_type: BomType,
+    pub quantity: Quantity,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateBom {
+    pub company_id: Uuid,
+    pub variant_id: Uuid,
+    pub bom_type: BomType,
+    pub quantity: Quantity,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateBom {
+    pub bom_type: Option<BomType>,
+    pub quantity: Option<Quantity>,
+}
+
+/// Component quantity, in the component's stock unit.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BomLine {
+    pub id: Uuid,
+    pub bom_id: Uuid,
+    pub component_id: Uuid,
+    pub quantity: Quantity,
+}
+
+/// An attribute a product's variants differ by, e.g. size, and the values
+/// it can take.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProductAttribute {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub name: String,
+    /// Order of the attribute in generated SKUs and pickers.
+    pub position: i32,
+    pub allowed_values: sqlx::types::Json<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateProductAttribute {
+    pub product_id: Uuid,
+    pub name: String,
+    pub position: i32,
+    pub allowed_values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateProductAttribute {
+    pub name: Option<String>,
+    pub position: Option<i32>,
+    pub allowed_values: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Warehouse {
     pub id: Uuid,
     pub company_id: Uuid,
     pub name: String,
+    pub is_active: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -145,17 +361,24 @@ pub struct CreateWarehouse {
     pub name: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateWarehouse {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct StockMovement {
     pub id: Uuid,
     pub company_id: Uuid,
     pub variant_id: Uuid,
     pub warehouse_id: Uuid,
-    pub quantity: f64,
-    pub movement_type: String,
+    pub quantity: Quantity,
+    pub movement_type: MovementType,
     pub reference_type: Option<String>,
     pub reference_id: Option<Uuid>,
-    pub unit_cost: Option<f64>,
+    pub unit_cost: Option<Money>,
+    /// Bin the stock was put away to or picked from.
+    pub location_id: Option<Uuid>,
     pub movement_date: DateTime<Utc>,
 }
 
@@ -164,29 +387,397 @@ pub struct CreateStockMovement {
     pub company_id: Uuid,
     pub variant_id: Uuid,
     pub warehouse_id: Uuid,
-    pub quantity: f64,
-    pub movement_type: String,
+    pub quantity: Quantity,
+    pub movement_type: MovementType,
     pub reference_type: Option<String>,
     pub reference_id: Option<Uuid>,
-    pub unit_cost: Option<f64>,
+    pub unit_cost: Option<Money>,
+    /// Bin to put away to or pick from. Left out, receipts go to the
+    /// suggested putaway bin of a warehouse that has bins, and adjustments
+    /// change stock outside bins.
+    #[serde(default)]
+    pub location_id: Option<Uuid>,
+    /// Required for lot and serial tracked variants; quantities add up to
+    /// `quantity`.
+    #[serde(default)]
+    pub lots: Vec<LotQuantity>,
+}
+
+/// Quantity of one lot or serial number in a movement. Receipts create
+/// lots that do not exist yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotQuantity {
+    pub number: String,
+    pub expires_on: Option<chrono::NaiveDate>,
+    pub quantity: Quantity,
+}
+
+/// A zone, aisle, shelf or bin of a warehouse. Stock is only held in bins.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StorageLocation {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub kind: LocationKind,
+    pub code: String,
+    pub name: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateStorageLocation {
+    pub company_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub kind: LocationKind,
+    pub code: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateStorageLocation {
+    pub code: Option<String>,
+    pub name: Option<Option<String>>,
+}
+
+/// Stock moved between bins of one warehouse. `None` on either side is
+/// stock not yet put away in a bin.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StockBinMove {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub variant_id: Uuid,
+    pub from_location_id: Option<Uuid>,
+    pub to_location_id: Option<Uuid>,
+    pub quantity: Quantity,
+    pub moved_by: Uuid,
+    pub moved_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateStockBinMove {
+    pub company_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub variant_id: Uuid,
+    pub from_location_id: Option<Uuid>,
+    pub to_location_id: Option<Uuid>,
+    pub quantity: Quantity,
+    pub moved_by: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StockLot {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub variant_id: Uuid,
+    /// Lot or serial number.
+    pub number: String,
+    pub expires_on: Option<chrono::NaiveDate>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct StockLedger {
     pub variant_id: Uuid,
     pub warehouse_id: Uuid,
-    pub quantity: f64,
+    pub quantity: Quantity,
+    /// Book value of `quantity` under the company's costing method.
+    pub value: Money,
+    /// Held for confirmed sales orders.
+    pub reserved: Quantity,
+}
+
+impl StockLedger {
+    /// What can still be promised to new orders.
+    pub fn available(&self) -> Quantity {
+        self.quantity - self.reserved
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StockReservation {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub sales_order_line_id: Uuid,
+    pub variant_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub quantity: Quantity,
+    pub created_at: DateTime<Utc>,
+    pub released_at: Option<DateTime<Utc>>,
+}
+
+/// Quantity received at one cost, consumed oldest first by outgoing stock.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CostLayer {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub variant_id: Uuid,
+    pub warehouse_id: Uuid,
+    /// `None` for the opening layer of stock from before costing.
+    pub movement_id: Option<Uuid>,
+    pub received_at: DateTime<Utc>,
+    pub quantity: Quantity,
+    pub remaining: Quantity,
+    pub unit_cost: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StockTransfer {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub from_warehouse_id: Uuid,
+    pub to_warehouse_id: Uuid,
+    pub status: TransferStatus,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub created_by: Uuid,
+    pub shipped_at: Option<DateTime<Utc>>,
+    pub received_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateStockTransfer {
+    pub company_id: Uuid,
+    pub from_warehouse_id: Uuid,
+    pub to_warehouse_id: Uuid,
+    pub notes: Option<String>,
+    pub created_by: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateStockTransfer {
+    pub from_warehouse_id: Option<Uuid>,
+    pub to_warehouse_id: Option<Uuid>,
+    pub notes: Option<Option<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StockTransferLine {
+    pub id: Uuid,
+    pub transfer_id: Uuid,
+    pub variant_id: Uuid,
+    pub quantity: Quantity,
+    /// Cost taken out of the source warehouse, set when shipped.
+    pub cost: Option<Money>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateStockTransferLine {
+    pub transfer_id: Uuid,
+    pub variant_id: Uuid,
+    pub quantity: Quantity,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateStockTransferLine {
+    pub variant_id: Option<Uuid>,
+    pub quantity: Option<Quantity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StockCount {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub status: CountStatus,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub created_by: Uuid,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub approved_by: Option<Uuid>,
+    pub posted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateStockCount {
+    pub company_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub notes: Option<String>,
+    pub created_by: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateStockCount {
+    pub notes: Option<Option<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StockCountLine {
+    pub id: Uuid,
+    pub count_id: Uuid,
+    pub variant_id: Uuid,
+    /// Bin counted, or `None` for the variant's stock outside bins.
+    pub location_id: Option<Uuid>,
+    /// Ledger quantity of the bin, or outside bins, when the count started.
+    pub expected: Quantity,
+    pub counted: Option<Quantity>,
+    /// Average cost when the count started, used to value the variance.
+    pub unit_cost: Money,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateStockCountLine {
+    pub counted: Option<Option<Quantity>>,
+}
+
+/// Charges such as freight or duties added to the cost of the stock
+/// received on one or more purchase orders.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LandedCost {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    /// Who invoiced the charges, e.g. the carrier.
+    pub vendor_id: Option<Uuid>,
+    pub reference: Option<String>,
+    pub cost_date: chrono::NaiveDate,
+    pub status: LandedCostStatus,
+    pub created_at: DateTime<Utc>,
+    pub created_by: Uuid,
+    pub posted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateLandedCost {
+    pub company_id: Uuid,
+    pub vendor_id: Option<Uuid>,
+    pub reference: Option<String>,
+    pub cost_date: chrono::NaiveDate,
+    pub created_by: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateLandedCost {
+    pub vendor_id: Option<Option<Uuid>>,
+    pub reference: Option<Option<String>>,
+    pub cost_date: Option<chrono::NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LandedCostCharge {
+    pub id: Uuid,
+    pub landed_cost_id: Uuid,
+    pub description: String,
+    pub amount: Money,
+    pub method: AllocationMethod,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateLandedCostCharge {
+    pub landed_cost_id: Uuid,
+    pub description: String,
+    pub amount: Money,
+    pub method: AllocationMethod,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateLandedCostCharge {
+    pub description: Option<String>,
+    pub amount: Option<Money>,
+    pub method: Option<AllocationMethod>,
+}
+
+/// The part of a charge added to one receipt movement.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LandedCostAllocation {
+    pub id: Uuid,
+    pub landed_cost_id: Uuid,
+    pub charge_id: Uuid,
+    pub movement_id: Uuid,
+    pub amount: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PartnerGroup {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePartnerGroup {
+    pub company_id: Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdatePartnerGroup {
+    pub name: Option<String>,
+}
+
+/// Prices in one currency for one partner, the members of a partner group,
+/// or everyone when both are `None`.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PriceList {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub name: String,
+    pub currency_id: Uuid,
+    pub partner_id: Option<Uuid>,
+    pub partner_group_id: Option<Uuid>,
+    pub valid_from: Option<chrono::NaiveDate>,
+    pub valid_to: Option<chrono::NaiveDate>,
+    /// Decides between lists equally specific to a partner.
+    pub priority: i32,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePriceList {
+    pub company_id: Uuid,
+    pub name: String,
+    pub currency_id: Uuid,
+    pub partner_id: Option<Uuid>,
+    pub partner_group_id: Option<Uuid>,
+    pub valid_from: Option<chrono::NaiveDate>,
+    pub valid_to: Option<chrono::NaiveDate>,
+    pub priority: i32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdatePriceList {
+    pub name: Option<String>,
+    pub valid_from: Option<Option<chrono::NaiveDate>>,
+    pub valid_to: Option<Option<chrono::NaiveDate>>,
+    pub priority: Option<i32>,
+}
+
+/// Price per stock unit from `min_quantity` stock units up.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PriceListItem {
+    pub id: Uuid,
+    pub price_list_id: Uuid,
+    pub variant_id: Uuid,
+    pub min_quantity: Quantity,
+    pub unit_price: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePriceListItem {
+    pub price_list_id: Uuid,
+    pub variant_id: Uuid,
+    pub min_quantity: Quantity,
+    pub unit_price: Money,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdatePriceListItem {
+    pub min_quantity: Option<Quantity>,
+    pub unit_price: Option<Money>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct SalesOrder {
     pub id: Uuid,
     pub company_id: Uuid,
     pub customer_id: Uuid,
     pub order_date: chrono::NaiveDate,
     pub currency_id: Uuid,
-    pub status: String,
-    pub total_amount: f64,
+    pub status: OrderStatus,
+    pub total_amount: Money,
     pub created_at: DateTime<Utc>,
     pub created_by: Uuid,
 }
@@ -197,36 +788,60 @@ pub struct CreateSalesOrder {
     pub customer_id: Uuid,
     pub order_date: chrono::NaiveDate,
     pub currency_id: Uuid,
-    pub status: String,
+    pub status: OrderStatus,
+    pub created_by: Uuid,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Status changes go through `ReservationService`, which keeps the order's
+/// reservations in step.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateSalesOrder {
+    pub customer_id: Option<Uuid>,
+    pub order_date: Option<chrono::NaiveDate>,
+    pub currency_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct SalesOrderLine {
     pub id: Uuid,
     pub sales_order_id: Uuid,
     pub variant_id: Uuid,
-    pub quantity: f64,
-    pub unit_price: f64,
-    pub subtotal: f64,
+    /// In `unit_id`, or the variant's stock unit when that is `None`.
+    pub quantity: Quantity,
+    pub unit_id: Option<Uuid>,
+    pub unit_price: Money,
+    pub subtotal: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateSalesOrderLine {
     pub sales_order_id: Uuid,
     pub variant_id: Uuid,
-    pub quantity: f64,
-    pub unit_price: f64,
+    pub quantity: Quantity,
+    #[serde(default)]
+    pub unit_id: Option<Uuid>,
+    pub unit_price: Money,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateSalesOrderLine {
+    pub variant_id: Option<Uuid>,
+    pub quantity: Option<Quantity>,
+    pub unit_id: Option<Option<Uuid>>,
+    pub unit_price: Option<Money>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct PurchaseOrder {
     pub id: Uuid,
     pub company_id: Uuid,
     pub vendor_id: Uuid,
     pub order_date: chrono::NaiveDate,
     pub currency_id: Uuid,
-    pub status: String,
-    pub total_amount: f64,
+    /// Warehouse the goods will be received into.
+    pub warehouse_id: Option<Uuid>,
+    pub status: OrderStatus,
+    pub total_amount: Money,
     pub created_at: DateTime<Utc>,
     pub created_by: Uuid,
 }
@@ -237,34 +852,57 @@ pub struct CreatePurchaseOrder {
     pub vendor_id: Uuid,
     pub order_date: chrono::NaiveDate,
     pub currency_id: Uuid,
-    pub status: String,
+    pub warehouse_id: Option<Uuid>,
+    pub status: OrderStatus,
+    pub created_by: Uuid,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdatePurchaseOrder {
+    pub vendor_id: Option<Uuid>,
+    pub order_date: Option<chrono::NaiveDate>,
+    pub currency_id: Option<Uuid>,
+    pub warehouse_id: Option<Option<Uuid>>,
+    pub status: Option<OrderStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct PurchaseOrderLine {
     pub id: Uuid,
     pub purchase_order_id: Uuid,
     pub variant_id: Uuid,
-    pub quantity: f64,
-    pub unit_cost: f64,
-    pub subtotal: f64,
+    /// In `unit_id`, or the variant's stock unit when that is `None`.
+    pub quantity: Quantity,
+    pub unit_id: Option<Uuid>,
+    pub unit_cost: Money,
+    pub subtotal: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreatePurchaseOrderLine {
     pub purchase_order_id: Uuid,
     pub variant_id: Uuid,
-    pub quantity: f64,
-    pub unit_cost: f64,
+    pub quantity: Quantity,
+    #[serde(default)]
+    pub unit_id: Option<Uuid>,
+    pub unit_cost: Money,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdatePurchaseOrderLine {
+    pub variant_id: Option<Uuid>,
+    pub quantity: Option<Quantity>,
+    pub unit_id: Option<Option<Uuid>>,
+    pub unit_cost: Option<Money>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct ChartOfAccount {
     pub id: Uuid,
     pub company_id: Uuid,
     pub code: String,
     pub name: String,
-    pub account_type: String,
+    pub account_type: AccountType,
     pub is_active: bool,
 }
 
@@ -273,18 +911,65 @@ pub struct CreateChartOfAccount {
     pub company_id: Uuid,
     pub code: String,
     pub name: String,
-    pub account_type: String,
+    pub account_type: AccountType,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateChartOfAccount {
+    pub code: Option<String>,
+    pub name: Option<String>,
+    pub account_type: Option<AccountType>,
+}
+
+/// When and how much to reorder of a variant for one warehouse. Set
+/// `max_quantity` to fill up to a level, or `reorder_quantity` to order a
+/// fixed amount.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ReorderRule {
+    pub id: Uuid,
+    pub company_id: Uuid,
+    pub variant_id: Uuid,
+    pub warehouse_id: Uuid,
+    /// Reorder point.
+    pub min_quantity: Quantity,
+    pub max_quantity: Option<Quantity>,
+    pub reorder_quantity: Option<Quantity>,
+    pub order_multiple: Quantity,
+    /// Preferred vendor.
+    pub vendor_id: Option<Uuid>,
+    pub is_active: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateReorderRule {
+    pub company_id: Uuid,
+    pub variant_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub min_quantity: Quantity,
+    pub max_quantity: Option<Quantity>,
+    pub reorder_quantity: Option<Quantity>,
+    pub order_multiple: Quantity,
+    pub vendor_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateReorderRule {
+    pub min_quantity: Option<Quantity>,
+    pub max_quantity: Option<Option<Quantity>>,
+    pub reorder_quantity: Option<Option<Quantity>>,
+    pub order_multiple: Option<Quantity>,
+    pub vendor_id: Option<Option<Uuid>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct JournalEntry {
     pub id: Uuid,
     pub company_id: Uuid,
     pub entry_date: chrono::NaiveDate,
     pub reference: Option<String>,
     pub currency_id: Option<Uuid>,
-    pub exchange_rate: Option<f64>,
-    pub posted: bool,
+    pub exchange_rate: Option<Rate>,
+    pub status: JournalStatus,
     pub created_at: DateTime<Utc>,
     pub created_by: Uuid,
 }
@@ -295,19 +980,28 @@ pub struct CreateJournalEntry {
     pub entry_date: chrono::NaiveDate,
     pub reference: Option<String>,
     pub currency_id: Option<Uuid>,
-    pub exchange_rate: Option<f64>,
+    pub exchange_rate: Option<Rate>,
     pub created_by: Uuid,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateJournalEntry {
+    pub entry_date: Option<chrono::NaiveDate>,
+    pub reference: Option<Option<String>>,
+    pub currency_id: Option<Option<Uuid>>,
+    pub exchange_rate: Option<Option<Rate>>,
+    pub status: Option<JournalStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct JournalLine {
     pub id: Uuid,
     pub journal_entry_id: Uuid,
     pub account_id: Uuid,
     pub partner_id: Option<Uuid>,
-    pub debit: f64,
-    pub credit: f64,
-    pub currency_amount: Option<f64>,
+    pub debit: Money,
+    pub credit: Money,
+    pub currency_amount: Option<Money>,
     pub description: Option<String>,
 }
 
@@ -316,8 +1010,18 @@ pub struct CreateJournalLine {
     pub journal_entry_id: Uuid,
     pub account_id: Uuid,
     pub partner_id: Option<Uuid>,
-    pub debit: f64,
-    pub credit: f64,
-    pub currency_amount: Option<f64>,
+    pub debit: Money,
+    pub credit: Money,
+    pub currency_amount: Option<Money>,
     pub description: Option<String>,
 }
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateJournalLine {
+    pub account_id: Option<Uuid>,
+    pub partner_id: Option<Option<Uuid>>,
+    pub debit: Option<Money>,
+    pub credit: Option<Money>,
+    pub currency_amount: Option<Option<Money>>,
+    pub description: Option<Option<String>>,
+}
@@ -0,0 +1,312 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef};
+use sqlx::sqlite::{SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef};
+use sqlx::{Decode, Encode, Postgres, Sqlite, Type, TypeInfo, ValueRef};
+
+use crate::db::enums::RoundingMode;
+use crate::db::models::Currency;
+use crate::db::repositories::SqlValue;
+use crate::error::AppError;
+
+/// Declares an exact decimal newtype for a NUMERIC column with `$scale`
+/// fractional digits. Postgres stores it as NUMERIC; SQLite has no decimal
+/// type, so values are written as text and read back rounded to the scale.
+macro_rules! decimal_type {
+    ($(#[$meta:meta])* $name:ident, scale = $scale:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(Decimal);
+
+        impl $name {
+            pub const ZERO: Self = Self(Decimal::ZERO);
+            /// Fractional digits stored by the database column.
+            pub const SCALE: u32 = $scale;
+
+            pub const fn new(value: Decimal) -> Self {
+                Self(value)
+            }
+
+            pub const fn value(self) -> Decimal {
+                self.0
+            }
+
+            pub fn is_zero(self) -> bool {
+                self.0.is_zero()
+            }
+
+            pub fn is_negative(self) -> bool {
+                self.0.is_sign_negative() && !self.0.is_zero()
+            }
+
+            pub fn abs(self) -> Self {
+                Self(self.0.abs())
+            }
+
+            pub fn round(self, decimal_places: u32, mode: RoundingMode) -> Self {
+                Self(self.0.round_dp_with_strategy(decimal_places, mode.strategy()))
+            }
+
+            /// Lossy conversion for display code such as charts.
+            pub fn to_f64(self) -> f64 {
+                self.0.try_into().unwrap_or_default()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = AppError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.trim()
+                    .parse::<Decimal>()
+                    .map(Self)
+                    .map_err(|e| AppError::App(format!("invalid amount {:?}: {}", s, e)))
+            }
+        }
+
+        impl From<Decimal> for $name {
+            fn from(value: Decimal) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<i64> for $name {
+            fn from(value: i64) -> Self {
+                Self(Decimal::from(value))
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self {
+                Self(self.0 + rhs.0)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                Self(self.0 - rhs.0)
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self(-self.0)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, rhs: Self) {
+                self.0 += rhs.0;
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, rhs: Self) {
+                self.0 -= rhs.0;
+            }
+        }
+
+        impl Sum for $name {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                iter.fold(Self::ZERO, Add::add)
+            }
+        }
+
+        impl<'a> Sum<&'a $name> for $name {
+            fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+                iter.copied().sum()
+            }
+        }
+
+        impl Type<Postgres> for $name {
+            fn type_info() -> PgTypeInfo {
+                <Decimal as Type<Postgres>>::type_info()
+            }
+
+            fn compatible(ty: &PgTypeInfo) -> bool {
+                <Decimal as Type<Postgres>>::compatible(ty)
+            }
+        }
+
+        impl Encode<'_, Postgres> for $name {
+            fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+                <Decimal as Encode<Postgres>>::encode_by_ref(&self.0, buf)
+            }
+        }
+
+        impl Decode<'_, Postgres> for $name {
+            fn decode(value: PgValueRef<'_>) -> Result<Self, BoxDynError> {
+                <Decimal as Decode<Postgres>>::decode(value).map(Self)
+            }
+        }
+
+        impl Type<Sqlite> for $name {
+            fn type_info() -> SqliteTypeInfo {
+                <f64 as Type<Sqlite>>::type_info()
+            }
+
+            fn compatible(ty: &SqliteTypeInfo) -> bool {
+                <f64 as Type<Sqlite>>::compatible(ty)
+                    || <i64 as Type<Sqlite>>::compatible(ty)
+                    || <&str as Type<Sqlite>>::compatible(ty)
+            }
+        }
+
+        impl<'q> Encode<'q, Sqlite> for $name {
+            fn encode_by_ref(
+                &self,
+                buf: &mut Vec<SqliteArgumentValue<'q>>,
+            ) -> Result<IsNull, BoxDynError> {
+                <String as Encode<Sqlite>>::encode(self.0.to_string(), buf)
+            }
+        }
+
+        impl Decode<'_, Sqlite> for $name {
+            fn decode(value: SqliteValueRef<'_>) -> Result<Self, BoxDynError> {
+                decode_sqlite(value, Self::SCALE).map(Self)
+            }
+        }
+
+        impl From<$name> for SqlValue {
+            fn from(value: $name) -> Self {
+                SqlValue::Decimal(Some(value.0))
+            }
+        }
+
+        impl From<Option<$name>> for SqlValue {
+            fn from(value: Option<$name>) -> Self {
+                SqlValue::Decimal(value.map(|v| v.0))
+            }
+        }
+    };
+}
+
+decimal_type!(
+    /// A monetary amount, NUMERIC(18,4).
+    Money,
+    scale = 4
+);
+
+decimal_type!(
+    /// A stock or order quantity, NUMERIC(18,4).
+    Quantity,
+    scale = 4
+);
+
+decimal_type!(
+    /// An exchange rate, NUMERIC(18,8).
+    Rate,
+    scale = 8
+);
+
+decimal_type!(
+    /// A unit of measure conversion factor, NUMERIC(18,6).
+    Factor,
+    scale = 6
+);
+
+impl Mul<Money> for Quantity {
+    type Output = Money;
+
+    fn mul(self, price: Money) -> Money {
+        Money(self.0 * price.0)
+    }
+}
+
+impl Mul<Quantity> for Money {
+    type Output = Money;
+
+    fn mul(self, quantity: Quantity) -> Money {
+        Money(self.0 * quantity.0)
+    }
+}
+
+impl Mul<Rate> for Money {
+    type Output = Money;
+
+    fn mul(self, rate: Rate) -> Money {
+        Money(self.0 * rate.0)
+    }
+}
+
+impl Mul<Factor> for Quantity {
+    type Output = Quantity;
+
+    fn mul(self, factor: Factor) -> Quantity {
+        Quantity(self.0 * factor.0)
+    }
+}
+
+impl Mul<Factor> for Money {
+    type Output = Money;
+
+    /// Price or cost of one unit holding `factor` stock units.
+    fn mul(self, factor: Factor) -> Money {
+        Money(self.0 * factor.0)
+    }
+}
+
+impl Div<Factor> for Money {
+    type Output = Option<Money>;
+
+    /// Price or cost per converted unit; `None` when dividing by zero.
+    fn div(self, factor: Factor) -> Option<Money> {
+        self.0.checked_div(factor.0).map(Money)
+    }
+}
+
+impl Div<Quantity> for Money {
+    type Output = Option<Money>;
+
+    /// Unit price or cost; `None` when dividing by zero.
+    fn div(self, quantity: Quantity) -> Option<Money> {
+        self.0.checked_div(quantity.0).map(Money)
+    }
+}
+
+impl RoundingMode {
+    pub fn strategy(self) -> RoundingStrategy {
+        match self {
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            RoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+            RoundingMode::Down => RoundingStrategy::ToZero,
+            RoundingMode::Up => RoundingStrategy::AwayFromZero,
+        }
+    }
+}
+
+impl Currency {
+    /// Rounds `amount` to what can actually be paid in this currency.
+    pub fn round(&self, amount: Money) -> Money {
+        amount.round(self.decimal_places as u32, self.rounding_mode)
+    }
+}
+
+fn decode_sqlite(value: SqliteValueRef<'_>, scale: u32) -> Result<Decimal, BoxDynError> {
+    let decimal = match value.type_info().name() {
+        "TEXT" => <&str as Decode<Sqlite>>::decode(value)?.parse::<Decimal>()?,
+        _ => Decimal::try_from(<f64 as Decode<Sqlite>>::decode(value)?)?,
+    };
+    // REAL columns and trigger arithmetic carry binary noise past the scale
+    Ok(decimal.round_dp(scale))
+}
@@ -0,0 +1,301 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::db::enums::RoundingMode;
+use crate::db::inventory::UnitService;
+use crate::db::inventory::units::UnitUse;
+use crate::db::models::PartnerGroup;
+use crate::db::money::{Money, Quantity};
+use crate::error::{AppError, Result};
+
+/// What a price is asked for. `quantity` is in `unit_id`, or in the
+/// variant's stock unit when that is `None`.
+#[derive(Debug, Clone)]
+pub struct PriceRequest {
+    pub company_id: Uuid,
+    pub partner_id: Option<Uuid>,
+    pub variant_id: Uuid,
+    pub currency_id: Uuid,
+    pub unit_id: Option<Uuid>,
+    pub quantity: Quantity,
+    pub date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    PriceList,
+    /// No list applied; the variant's own selling price.
+    SellingPrice,
+}
+
+/// A suggested unit price for an order line, per the line's unit.
+#[derive(Debug, Clone, Serialize)]
+pub struct SuggestedPrice {
+    pub unit_price: Money,
+    /// Always the requested currency.
+    pub currency_id: Uuid,
+    pub source: PriceSource,
+    pub price_list_id: Option<Uuid>,
+    /// Quantity break the price came from, in the stock unit.
+    pub min_quantity: Quantity,
+}
+
+#[derive(FromRow)]
+struct ListPrice {
+    price_list_id: Uuid,
+    min_quantity: Quantity,
+    unit_price: Money,
+}
+
+#[derive(FromRow)]
+struct SellingPrice {
+    selling_price: Money,
+    base_currency_id: Option<Uuid>,
+}
+
+/// Picks sales prices from price lists. A list for the partner beats one
+/// for a group the partner is in, which beats a list for everyone; among
+/// equally specific lists the higher priority wins. Within a list the
+/// highest quantity break not above the line quantity applies, so a list
+/// whose first break is above it is passed over. Postgres only.
+pub struct PricingService<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PricingService<'c> {
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+
+    /// `None` when no list applies and the variant's selling price, kept
+    /// in the company's base currency, is not in the requested one.
+    pub async fn resolve(&mut self, request: &PriceRequest) -> Result<Option<SuggestedPrice>> {
+        let mut units = UnitService::new(&mut *self.conn);
+        let quantity = units
+            .stock_quantity(
+                request.variant_id,
+                request.unit_id,
+                request.quantity,
+                UnitUse::Sales,
+            )
+            .await?;
+        let factor = match request.unit_id {
+            Some(_) => Some(
+                units
+                    .factor(request.variant_id, request.unit_id, UnitUse::Sales)
+                    .await?,
+            ),
+            None => None,
+        };
+        let per_line_unit = |price: Money| match factor {
+            Some(factor) => (price * factor).round(Money::SCALE, RoundingMode::HalfUp),
+            None => price,
+        };
+
+        let listed: Option<ListPrice> = sqlx::query_as(
+            "SELECT l.id AS price_list_id, i.min_quantity, i.unit_price \
+             FROM price_list_items i JOIN price_lists l ON l.id = i.price_list_id \
+             WHERE l.company_id = $1 AND l.is_active AND l.currency_id = $2 \
+             AND i.variant_id = $3 AND i.min_quantity <= $4 \
+             AND (l.valid_from IS NULL OR l.valid_from <= $5) \
+             AND (l.valid_to IS NULL OR l.valid_to >= $5) \
+             AND (l.partner_id = $6 \
+                  OR l.partner_group_id IN ( \
+                      SELECT group_id FROM partner_group_members WHERE partner_id = $6) \
+                  OR (l.partner_id IS NULL AND l.partner_group_id IS NULL)) \
+             ORDER BY CASE WHEN l.partner_id IS NOT NULL THEN 0 \
+                           WHEN l.partner_group_id IS NOT NULL THEN 1 ELSE 2 END, \
+                      l.priority DESC, l.created_at DESC, i.min_quantity DESC \
+             LIMIT 1",
+        )
+        .bind(request.company_id)
+        .bind(request.currency_id)
+        .bind(request.variant_id)
+        .bind(quantity)
+        .bind(request.date)
+        .bind(request.partner_id)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        if let Some(listed) = listed {
+            return Ok(Some(SuggestedPrice {
+                unit_price: per_line_unit(listed.unit_price),
+                currency_id: request.currency_id,
+                source: PriceSource::PriceList,
+                price_list_id: Some(listed.price_list_id),
+                min_quantity: listed.min_quantity,
+            }));
+        }
+
+        let fallback: SellingPrice = sqlx::query_as(
+            "SELECT v.selling_price, c.base_currency_id \
+             FROM product_variants v \
+             JOIN products p ON p.id = v.product_id \
+             JOIN companies c ON c.id = p.company_id \
+             WHERE v.id = $1 AND p.company_id = $2",
+        )
+        .bind(request.variant_id)
+        .bind(request.company_id)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::App(format!("variant {} not found", request.variant_id)))?;
+
+        if fallback.base_currency_id != Some(request.currency_id) {
+            return Ok(None);
+        }
+        Ok(Some(SuggestedPrice {
+            unit_price: per_line_unit(fallback.selling_price),
+            currency_id: request.currency_id,
+            source: PriceSource::SellingPrice,
+            price_list_id: None,
+            min_quantity: Quantity::ZERO,
+        }))
+    }
+
+    /// Price for a line being entered on a sales order, for the order's
+    /// customer, currency and date.
+    pub async fn suggest_line_price(
+        &mut self,
+        order_id: Uuid,
+        variant_id: Uuid,
+        quantity: Quantity,
+        unit_id: Option<Uuid>,
+    ) -> Result<Option<SuggestedPrice>> {
+        let (company_id, customer_id, currency_id, order_date): (Uuid, Uuid, Uuid, NaiveDate) =
+            sqlx::query_as(
+                "SELECT company_id, customer_id, currency_id, order_date \
+                 FROM sales_orders WHERE id = $1",
+            )
+            .bind(order_id)
+            .fetch_optional(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?
+            .ok_or_else(|| AppError::App(format!("sales order {} not found", order_id)))?;
+
+        self.resolve(&PriceRequest {
+            company_id,
+            partner_id: Some(customer_id),
+            variant_id,
+            currency_id,
+            unit_id,
+            quantity,
+            date: order_date,
+        })
+        .await
+    }
+
+    pub async fn add_to_group(&mut self, group_id: Uuid, partner_id: Uuid) -> Result<()> {
+        let added = sqlx::query(
+            "INSERT INTO partner_group_members (group_id, partner_id) \
+             SELECT g.id, p.id FROM partner_groups g \
+             JOIN partners p ON p.company_id = g.company_id \
+             WHERE g.id = $1 AND p.id = $2 \
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(group_id)
+        .bind(partner_id)
+        .execute(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        if added.rows_affected() == 0 {
+            let member: bool = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM partner_group_members \
+                 WHERE group_id = $1 AND partner_id = $2)",
+            )
+            .bind(group_id)
+            .bind(partner_id)
+            .fetch_one(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+            if !member {
+                return Err(AppError::App(
+                    "partner and group must exist in the same company".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn remove_from_group(&mut self, group_id: Uuid, partner_id: Uuid) -> Result<bool> {
+        let removed = sqlx::query(
+            "DELETE FROM partner_group_members WHERE group_id = $1 AND partner_id = $2",
+        )
+        .bind(group_id)
+        .bind(partner_id)
+        .execute(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)?;
+        Ok(removed.rows_affected() > 0)
+    }
+
+    /// Groups the partner belongs to, by name.
+    pub async fn partner_groups(&mut self, partner_id: Uuid) -> Result<Vec<PartnerGroup>> {
+        sqlx::query_as(
+            "SELECT g.id, g.company_id, g.name FROM partner_group_members m \
+             JOIN partner_groups g ON g.id = m.group_id \
+             WHERE m.partner_id = $1 ORDER BY g.name",
+        )
+        .bind(partner_id)
+        .fetch_all(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    use super::{PriceRequest, PriceSource, PricingService};
+    use crate::db::enums::TrackingMode;
+    use crate::db::money::{Money, Quantity};
+    use crate::db::testing;
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn selling_price_is_only_suggested_in_the_base_currency() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let variant = testing::variant(conn, company, TrackingMode::Untracked, None, None).await;
+        sqlx::query("UPDATE product_variants SET selling_price = 25 WHERE id = $1")
+            .bind(variant)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        let (base, foreign): (Uuid, Uuid) = sqlx::query_as(
+            "WITH foreign_currency AS ( \
+                 INSERT INTO currencies (code, name) VALUES ('FRN', 'Foreign') \
+                 ON CONFLICT (code) DO UPDATE SET name = EXCLUDED.name RETURNING id \
+             ) SELECT c.base_currency_id, f.id FROM companies c, foreign_currency f \
+             WHERE c.id = $1",
+        )
+        .bind(company)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+
+        let request = |currency_id| PriceRequest {
+            company_id: company,
+            partner_id: None,
+            variant_id: variant,
+            currency_id,
+            unit_id: None,
+            quantity: Quantity::from(1),
+            date: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap(),
+        };
+        let mut pricing = PricingService::new(conn);
+        let suggested = pricing.resolve(&request(base)).await.unwrap().unwrap();
+        assert_eq!(suggested.unit_price, Money::from(25));
+        assert_eq!(suggested.source, PriceSource::SellingPrice);
+        assert!(pricing.resolve(&request(foreign)).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,38 @@
+use uuid::Uuid;
+
+use crate::db::models::{ChartOfAccount, CreateChartOfAccount, UpdateChartOfAccount};
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue, changed};
+
+impl Entity for ChartOfAccount {
+    const TABLE: &'static str = "chart_of_accounts";
+    const COLUMNS: &'static str = "id, company_id, code, name, account_type, is_active";
+    const SORT: Sort = Sort::asc("code");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const FILTERABLE: &'static [&'static str] = &["company_id", "account_type", "is_active"];
+    const SORTABLE: &'static [&'static str] = &["code", "name"];
+    const SEARCHABLE: &'static [&'static str] = &["code", "name"];
+
+    type Create = CreateChartOfAccount;
+    type Update = UpdateChartOfAccount;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateChartOfAccount) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("code", input.code.into()),
+            ("name", input.name.into()),
+            ("account_type", input.account_type.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateChartOfAccount) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "code", input.code);
+        changed(&mut values, "name", input.name);
+        changed(&mut values, "account_type", input.account_type);
+        values
+    }
+}
@@ -0,0 +1,44 @@
+use uuid::Uuid;
+
+use crate::db::models::{CreateProductAttribute, ProductAttribute, UpdateProductAttribute};
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue, changed};
+
+impl Entity for ProductAttribute {
+    const TABLE: &'static str = "product_attributes";
+    const COLUMNS: &'static str = "id, product_id, name, position, allowed_values";
+    const SORT: Sort = Sort::asc("position");
+    const ARCHIVE: Archive = Archive::Delete;
+    const FILTERABLE: &'static [&'static str] = &["product_id"];
+    const SORTABLE: &'static [&'static str] = &["position", "name"];
+
+    type Create = CreateProductAttribute;
+    type Update = UpdateProductAttribute;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateProductAttribute) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("product_id", input.product_id.into()),
+            ("name", input.name.into()),
+            ("position", i64::from(input.position).into()),
+            (
+                "allowed_values",
+                serde_json::Value::from(input.allowed_values).into(),
+            ),
+        ]
+    }
+
+    fn update_values(input: UpdateProductAttribute) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "name", input.name);
+        changed(&mut values, "position", input.position.map(i64::from));
+        changed(
+            &mut values,
+            "allowed_values",
+            input.allowed_values.map(serde_json::Value::from),
+        );
+        values
+    }
+}
@@ -0,0 +1,40 @@
+use uuid::Uuid;
+
+use crate::db::models::{Bom, CreateBom, UpdateBom};
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue, changed};
+
+// Lines are added through `BomService`, which keeps bills from containing
+// themselves.
+impl Entity for Bom {
+    const TABLE: &'static str = "boms";
+    const COLUMNS: &'static str =
+        "id, company_id, variant_id, bom_type, quantity, is_active, created_at";
+    const SORT: Sort = Sort::desc("created_at");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const FILTERABLE: &'static [&'static str] =
+        &["company_id", "variant_id", "bom_type", "is_active"];
+    const SORTABLE: &'static [&'static str] = &["created_at"];
+
+    type Create = CreateBom;
+    type Update = UpdateBom;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateBom) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("variant_id", input.variant_id.into()),
+            ("bom_type", input.bom_type.into()),
+            ("quantity", input.quantity.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateBom) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "bom_type", input.bom_type);
+        changed(&mut values, "quantity", input.quantity);
+        values
+    }
+}
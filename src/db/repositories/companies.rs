@@ -0,0 +1,42 @@
+use uuid::Uuid;
+
+use crate::db::models::{Company, CreateCompany, UpdateCompany};
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue, changed};
+
+impl Entity for Company {
+    const TABLE: &'static str = "companies";
+    const COLUMNS: &'static str =
+        "id, name, address, base_currency_id, costing_method, is_active, created_at, updated_at";
+    const SORT: Sort = Sort::asc("name");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const HAS_UPDATED_AT: bool = true;
+    const FILTERABLE: &'static [&'static str] =
+        &["base_currency_id", "costing_method", "is_active"];
+    const SORTABLE: &'static [&'static str] = &["name", "created_at"];
+    const SEARCHABLE: &'static [&'static str] = &["name"];
+
+    type Create = CreateCompany;
+    type Update = UpdateCompany;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateCompany) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("name", input.name.into()),
+            ("address", input.address.into()),
+            ("base_currency_id", input.base_currency_id.into()),
+            ("costing_method", input.costing_method.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateCompany) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "name", input.name);
+        changed(&mut values, "address", input.address);
+        changed(&mut values, "base_currency_id", input.base_currency_id);
+        changed(&mut values, "costing_method", input.costing_method);
+        values
+    }
+}
@@ -0,0 +1,82 @@
+use std::convert::Infallible;
+
+use uuid::Uuid;
+
+use crate::db::models::{
+    CreateStockCount, StockCount, StockCountLine, UpdateStockCount, UpdateStockCountLine,
+};
+use crate::db::repositories::{Archive, Entity, Repository, Sort, SqlValue, changed};
+use crate::error::Result;
+
+// Counts are started, submitted and approved through `CountService`.
+impl Entity for StockCount {
+    const TABLE: &'static str = "stock_counts";
+    const COLUMNS: &'static str = "id, company_id, warehouse_id, status, notes, created_at, \
+        created_by, submitted_at, approved_by, posted_at";
+    const SORT: Sort = Sort::desc("created_at");
+    const ARCHIVE: Archive = Archive::Update("status = 'CANCELLED'");
+    const EDITABLE: Option<&'static str> = Some("status IN ('OPEN', 'SUBMITTED')");
+    const FILTERABLE: &'static [&'static str] =
+        &["company_id", "warehouse_id", "status", "created_by"];
+    const SORTABLE: &'static [&'static str] = &["created_at", "posted_at"];
+    const SEARCHABLE: &'static [&'static str] = &["notes"];
+
+    type Create = CreateStockCount;
+    type Update = UpdateStockCount;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateStockCount) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("warehouse_id", input.warehouse_id.into()),
+            ("notes", input.notes.into()),
+            ("created_by", input.created_by.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateStockCount) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "notes", input.notes);
+        values
+    }
+}
+
+impl Entity for StockCountLine {
+    const TABLE: &'static str = "stock_count_lines";
+    const COLUMNS: &'static str =
+        "id, count_id, variant_id, location_id, expected, counted, unit_cost";
+    const SORT: Sort = Sort::asc("id");
+    const ARCHIVE: Archive = Archive::Unsupported;
+    const EDITABLE: Option<&'static str> =
+        Some("count_id IN (SELECT id FROM stock_counts WHERE status = 'OPEN')");
+    const FILTERABLE: &'static [&'static str] = &["count_id", "variant_id", "location_id"];
+
+    // Lines are frozen from the ledger when the count starts.
+    type Create = Infallible;
+    type Update = UpdateStockCountLine;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: Infallible) -> Vec<(&'static str, SqlValue)> {
+        match input {}
+    }
+
+    fn update_values(input: UpdateStockCountLine) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "counted", input.counted);
+        values
+    }
+}
+
+impl Repository<'_, StockCount> {
+    pub async fn lines(&mut self, count_id: Uuid) -> Result<Vec<StockCountLine>> {
+        Repository::<StockCountLine>::new(&mut *self.conn)
+            .find_by("count_id", count_id)
+            .await
+    }
+}
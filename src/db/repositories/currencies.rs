@@ -0,0 +1,46 @@
+use uuid::Uuid;
+
+use crate::db::models::{CreateCurrency, Currency, UpdateCurrency};
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue, changed};
+
+impl Entity for Currency {
+    const TABLE: &'static str = "currencies";
+    const COLUMNS: &'static str =
+        "id, code, name, symbol, decimal_places, rounding_mode, is_active";
+    const SORT: Sort = Sort::asc("code");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const FILTERABLE: &'static [&'static str] = &["is_active"];
+    const SORTABLE: &'static [&'static str] = &["code", "name"];
+    const SEARCHABLE: &'static [&'static str] = &["code", "name"];
+
+    type Create = CreateCurrency;
+    type Update = UpdateCurrency;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateCurrency) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("code", input.code.into()),
+            ("name", input.name.into()),
+            ("symbol", input.symbol.into()),
+            ("decimal_places", i64::from(input.decimal_places).into()),
+            ("rounding_mode", input.rounding_mode.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateCurrency) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "code", input.code);
+        changed(&mut values, "name", input.name);
+        changed(&mut values, "symbol", input.symbol);
+        changed(
+            &mut values,
+            "decimal_places",
+            input.decimal_places.map(i64::from),
+        );
+        changed(&mut values, "rounding_mode", input.rounding_mode);
+        values
+    }
+}
@@ -0,0 +1,194 @@
+use uuid::Uuid;
+
+use crate::db::models::{
+    CreateJournalEntry, CreateJournalLine, JournalEntry, JournalLine, UpdateJournalEntry,
+    UpdateJournalLine,
+};
+use crate::db::repositories::{Archive, Entity, Repository, Sort, SqlValue, changed};
+use crate::error::Result;
+
+// Posted entries are final; only drafts can be edited or removed.
+impl Entity for JournalEntry {
+    const TABLE: &'static str = "journal_entries";
+    const COLUMNS: &'static str = "id, company_id, entry_date, reference, currency_id, \
+        exchange_rate, status, created_at, created_by";
+    const SORT: Sort = Sort::desc("entry_date");
+    const ARCHIVE: Archive = Archive::Delete;
+    const EDITABLE: Option<&'static str> = Some("status = 'DRAFT'");
+    const FILTERABLE: &'static [&'static str] =
+        &["company_id", "currency_id", "entry_date", "status"];
+    const SORTABLE: &'static [&'static str] = &["entry_date", "created_at"];
+    const SEARCHABLE: &'static [&'static str] = &["reference"];
+
+    type Create = CreateJournalEntry;
+    type Update = UpdateJournalEntry;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateJournalEntry) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("entry_date", input.entry_date.into()),
+            ("reference", input.reference.into()),
+            ("currency_id", input.currency_id.into()),
+            ("exchange_rate", input.exchange_rate.into()),
+            ("created_by", input.created_by.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateJournalEntry) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "entry_date", input.entry_date);
+        changed(&mut values, "reference", input.reference);
+        changed(&mut values, "currency_id", input.currency_id);
+        changed(&mut values, "exchange_rate", input.exchange_rate);
+        changed(&mut values, "status", input.status);
+        values
+    }
+}
+
+impl Entity for JournalLine {
+    const TABLE: &'static str = "journal_lines";
+    const COLUMNS: &'static str = "id, journal_entry_id, account_id, partner_id, \
+        debit, credit, currency_amount, description";
+    const SORT: Sort = Sort::asc("id");
+    const ARCHIVE: Archive = Archive::Delete;
+    const EDITABLE: Option<&'static str> =
+        Some("journal_entry_id IN (SELECT id FROM journal_entries WHERE status = 'DRAFT')");
+    const FILTERABLE: &'static [&'static str] = &["journal_entry_id", "account_id", "partner_id"];
+    const SEARCHABLE: &'static [&'static str] = &["description"];
+
+    type Create = CreateJournalLine;
+    type Update = UpdateJournalLine;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateJournalLine) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("journal_entry_id", input.journal_entry_id.into()),
+            ("account_id", input.account_id.into()),
+            ("partner_id", input.partner_id.into()),
+            ("debit", input.debit.into()),
+            ("credit", input.credit.into()),
+            ("currency_amount", input.currency_amount.into()),
+            ("description", input.description.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateJournalLine) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "account_id", input.account_id);
+        changed(&mut values, "partner_id", input.partner_id);
+        changed(&mut values, "debit", input.debit);
+        changed(&mut values, "credit", input.credit);
+        changed(&mut values, "currency_amount", input.currency_amount);
+        changed(&mut values, "description", input.description);
+        values
+    }
+}
+
+impl Repository<'_, JournalEntry> {
+    pub async fn lines(&mut self, entry_id: Uuid) -> Result<Vec<JournalLine>> {
+        Repository::<JournalLine>::new(&mut *self.conn)
+            .find_by("journal_entry_id", entry_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::enums::{AccountType, JournalStatus, UserRole};
+    use crate::db::models::{
+        ChartOfAccount, CreateChartOfAccount, CreateJournalEntry, CreateJournalLine, JournalEntry,
+        JournalLine, UpdateJournalEntry, UpdateJournalLine,
+    };
+    use crate::db::money::Money;
+    use crate::db::repositories::Repository;
+    use crate::db::testing;
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn only_draft_entries_can_change() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let user = testing::user(conn, UserRole::Accountant).await;
+        let account = Repository::<ChartOfAccount>::new(conn)
+            .create(CreateChartOfAccount {
+                company_id: company,
+                code: "1000".into(),
+                name: "Cash".into(),
+                account_type: AccountType::Asset,
+            })
+            .await
+            .unwrap();
+        assert_eq!(account.account_type, AccountType::Asset);
+
+        let entry = Repository::<JournalEntry>::new(conn)
+            .create(CreateJournalEntry {
+                company_id: company,
+                entry_date: chrono::NaiveDate::from_ymd_opt(2026, 1, 31).unwrap(),
+                reference: None,
+                currency_id: None,
+                exchange_rate: None,
+                created_by: user,
+            })
+            .await
+            .unwrap();
+        assert_eq!(entry.status, JournalStatus::Draft);
+        let line = Repository::<JournalLine>::new(conn)
+            .create(CreateJournalLine {
+                journal_entry_id: entry.id,
+                account_id: account.id,
+                partner_id: None,
+                debit: Money::from(5),
+                credit: Money::ZERO,
+                currency_amount: None,
+                description: None,
+            })
+            .await
+            .unwrap();
+
+        let mut entries = Repository::<JournalEntry>::new(conn);
+        let posted = entries
+            .update(
+                entry.id,
+                UpdateJournalEntry {
+                    status: Some(JournalStatus::Posted),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(posted.status, JournalStatus::Posted);
+        let renamed = entries
+            .update(
+                entry.id,
+                UpdateJournalEntry {
+                    reference: Some(Some("late".into())),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(renamed.is_none());
+        assert!(!entries.archive(entry.id).await.unwrap());
+
+        let changed = Repository::<JournalLine>::new(conn)
+            .update(
+                line.id,
+                UpdateJournalLine {
+                    debit: Some(Money::from(6)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(changed.is_none());
+    }
+}
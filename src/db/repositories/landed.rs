@@ -0,0 +1,91 @@
+use uuid::Uuid;
+
+use crate::db::models::{
+    CreateLandedCost, CreateLandedCostCharge, LandedCost, LandedCostCharge, UpdateLandedCost,
+    UpdateLandedCostCharge,
+};
+use crate::db::repositories::{Archive, Entity, Repository, Sort, SqlValue, changed};
+use crate::error::Result;
+
+// Receipts are attached and the document posted through
+// `LandedCostService`.
+impl Entity for LandedCost {
+    const TABLE: &'static str = "landed_costs";
+    const COLUMNS: &'static str = "id, company_id, vendor_id, reference, cost_date, status, \
+        created_at, created_by, posted_at";
+    const SORT: Sort = Sort::desc("cost_date");
+    const ARCHIVE: Archive = Archive::Update("status = 'CANCELLED'");
+    const EDITABLE: Option<&'static str> = Some("status = 'DRAFT'");
+    const FILTERABLE: &'static [&'static str] = &["company_id", "vendor_id", "status"];
+    const SORTABLE: &'static [&'static str] = &["cost_date", "created_at", "posted_at"];
+    const SEARCHABLE: &'static [&'static str] = &["reference"];
+
+    type Create = CreateLandedCost;
+    type Update = UpdateLandedCost;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateLandedCost) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("vendor_id", input.vendor_id.into()),
+            ("reference", input.reference.into()),
+            ("cost_date", input.cost_date.into()),
+            ("created_by", input.created_by.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateLandedCost) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "vendor_id", input.vendor_id);
+        changed(&mut values, "reference", input.reference);
+        changed(&mut values, "cost_date", input.cost_date);
+        values
+    }
+}
+
+impl Entity for LandedCostCharge {
+    const TABLE: &'static str = "landed_cost_charges";
+    const COLUMNS: &'static str = "id, landed_cost_id, description, amount, method";
+    const SORT: Sort = Sort::asc("id");
+    const ARCHIVE: Archive = Archive::Delete;
+    const EDITABLE: Option<&'static str> =
+        Some("landed_cost_id IN (SELECT id FROM landed_costs WHERE status = 'DRAFT')");
+    const FILTERABLE: &'static [&'static str] = &["landed_cost_id", "method"];
+    const SORTABLE: &'static [&'static str] = &["amount"];
+    const SEARCHABLE: &'static [&'static str] = &["description"];
+
+    type Create = CreateLandedCostCharge;
+    type Update = UpdateLandedCostCharge;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateLandedCostCharge) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("landed_cost_id", input.landed_cost_id.into()),
+            ("description", input.description.into()),
+            ("amount", input.amount.into()),
+            ("method", input.method.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateLandedCostCharge) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "description", input.description);
+        changed(&mut values, "amount", input.amount);
+        changed(&mut values, "method", input.method);
+        values
+    }
+}
+
+impl Repository<'_, LandedCost> {
+    pub async fn charges(&mut self, landed_cost_id: Uuid) -> Result<Vec<LandedCostCharge>> {
+        Repository::<LandedCostCharge>::new(&mut *self.conn)
+            .find_by("landed_cost_id", landed_cost_id)
+            .await
+    }
+}
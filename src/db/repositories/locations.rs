@@ -0,0 +1,55 @@
+use uuid::Uuid;
+
+use crate::db::models::{CreateStorageLocation, StorageLocation, UpdateStorageLocation};
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue, changed};
+
+// Create through `LocationService`, which checks the hierarchy.
+impl Entity for StorageLocation {
+    const TABLE: &'static str = "storage_locations";
+    const COLUMNS: &'static str =
+        "id, company_id, warehouse_id, parent_id, kind, code, name, is_active, created_at";
+    const SORT: Sort = Sort::asc("code");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    /// Stock in the location or anywhere below it must be moved out first.
+    const ARCHIVABLE: Option<&'static str> = Some(
+        "NOT EXISTS (WITH RECURSIVE tree (id) AS ( \
+             SELECT storage_locations.id \
+             UNION ALL SELECT c.id FROM storage_locations c JOIN tree t ON c.parent_id = t.id) \
+         SELECT 1 FROM stock_bin_balances b JOIN tree t ON t.id = b.location_id \
+         WHERE b.quantity > 0)",
+    );
+    const FILTERABLE: &'static [&'static str] = &[
+        "company_id",
+        "warehouse_id",
+        "parent_id",
+        "kind",
+        "is_active",
+    ];
+    const SORTABLE: &'static [&'static str] = &["code", "created_at"];
+    const SEARCHABLE: &'static [&'static str] = &["code", "name"];
+
+    type Create = CreateStorageLocation;
+    type Update = UpdateStorageLocation;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateStorageLocation) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("warehouse_id", input.warehouse_id.into()),
+            ("parent_id", input.parent_id.into()),
+            ("kind", input.kind.into()),
+            ("code", input.code.into()),
+            ("name", input.name.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateStorageLocation) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "code", input.code);
+        changed(&mut values, "name", input.name);
+        values
+    }
+}
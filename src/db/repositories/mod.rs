@@ -0,0 +1,533 @@
+mod accounts;
+mod attributes;
+mod boms;
+mod companies;
+mod counts;
+mod currencies;
+mod journals;
+mod landed;
+mod locations;
+mod partners;
+mod prices;
+mod products;
+mod purchases;
+pub mod query;
+mod reorder;
+mod sales;
+mod stock;
+mod transfers;
+mod units;
+mod users;
+mod value;
+mod warehouses;
+
+use std::marker::PhantomData;
+
+use sqlx::postgres::PgRow;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Executor, FromRow, IntoArguments, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::db::backend::Backend;
+use crate::error::{AppError, Result};
+
+pub use query::{ListQuery, Page, Sort};
+pub use users::UserRepository;
+pub use value::SqlValue;
+
+/// How `Repository::archive` retires a row.
+pub enum Archive {
+    /// Runs `UPDATE ... SET <set>`, e.g. clearing `is_active` or cancelling a document.
+    Update(&'static str),
+    Delete,
+    /// Rows are permanent history; corrections are new rows.
+    Unsupported,
+}
+
+/// Table mapping shared by every repository. Implementations only describe
+/// the table; `Repository` builds the SQL, so `COLUMNS` and `EDITABLE` must
+/// be valid on every backend.
+pub trait Entity:
+    for<'r> FromRow<'r, PgRow> + for<'r> FromRow<'r, SqliteRow> + Send + Unpin
+{
+    const TABLE: &'static str;
+    /// Select list, including any casts needed to decode into the model.
+    const COLUMNS: &'static str;
+    /// Default list order; `id` breaks ties.
+    const SORT: Sort;
+    const ARCHIVE: Archive;
+    /// Predicate a row must satisfy to be updated or archived.
+    const EDITABLE: Option<&'static str> = None;
+    /// Further predicate a row must satisfy to be archived.
+    const ARCHIVABLE: Option<&'static str> = None;
+    const HAS_UPDATED_AT: bool = false;
+    /// Columns a `ListQuery` may filter on.
+    const FILTERABLE: &'static [&'static str] = &[];
+    /// Columns a `ListQuery` may sort on besides `SORT`. They must be NOT
+    /// NULL, or keyset cursors skip rows.
+    const SORTABLE: &'static [&'static str] = &[];
+    /// Text columns matched by `ListQuery::search`.
+    const SEARCHABLE: &'static [&'static str] = &[];
+
+    type Create: Send;
+    type Update: Send;
+
+    fn id(&self) -> Uuid;
+    fn insert_values(input: Self::Create) -> Vec<(&'static str, SqlValue)>;
+    fn update_values(input: Self::Update) -> Vec<(&'static str, SqlValue)>;
+}
+
+pub struct Repository<'c, E, DB: Backend = Postgres> {
+    conn: &'c mut DB::Connection,
+    entity: PhantomData<E>,
+}
+
+impl<'c, E, DB> Repository<'c, E, DB>
+where
+    E: Entity + for<'r> FromRow<'r, DB::Row>,
+    DB: Backend,
+    for<'e> &'e mut DB::Connection: Executor<'e, Database = DB>,
+    for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+    pub fn new(conn: &'c mut DB::Connection) -> Self {
+        Self {
+            conn,
+            entity: PhantomData,
+        }
+    }
+
+    pub async fn create(&mut self, input: E::Create) -> Result<E> {
+        let mut values = vec![("id", SqlValue::from(Uuid::new_v4()))];
+        values.extend(E::insert_values(input));
+
+        let mut query = QueryBuilder::<DB>::new("INSERT INTO ");
+        query.push(E::TABLE).push(" (");
+        query.push(
+            values
+                .iter()
+                .map(|(column, _)| *column)
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        query.push(") VALUES (");
+        for (i, (_, value)) in values.into_iter().enumerate() {
+            if i > 0 {
+                query.push(", ");
+            }
+            value.push_bind(&mut query);
+        }
+        query.push(") RETURNING ").push(E::COLUMNS);
+
+        DB::build(&mut query)
+            .try_map(|row| E::from_row(&row))
+            .fetch_one(&mut *self.conn)
+            .await
+            .map_err(map_error::<E>)
+    }
+
+    pub async fn get(&mut self, id: Uuid) -> Result<Option<E>> {
+        let mut query = QueryBuilder::<DB>::new("SELECT ");
+        query
+            .push(E::COLUMNS)
+            .push(" FROM ")
+            .push(E::TABLE)
+            .push(" WHERE id = ");
+        SqlValue::from(id).push_bind(&mut query);
+
+        DB::build(&mut query)
+            .try_map(|row| E::from_row(&row))
+            .fetch_optional(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)
+    }
+
+    /// Returns one page of rows matching `spec`.
+    pub async fn list(&mut self, spec: &ListQuery) -> Result<Page<E>> {
+        let sort = match &spec.sort {
+            Some((column, direction)) => Sort {
+                column: allowed::<E>(E::SORTABLE, column, "sort")?,
+                direction: *direction,
+            },
+            None => E::SORT,
+        };
+        let limit = spec.page_size() as usize;
+
+        let mut query = QueryBuilder::<DB>::new("SELECT ");
+        query.push(E::COLUMNS).push(" FROM ").push(E::TABLE);
+
+        let mut separator = " WHERE ";
+        for filter in &spec.filters {
+            let column = allowed::<E>(E::FILTERABLE, &filter.column, "filter")?;
+            query.push(separator).push(column).push(filter.op.sql());
+            if filter.op.takes_value() {
+                filter.value.clone().push_bind(&mut query);
+            }
+            separator = " AND ";
+        }
+
+        if let Some(text) = &spec.search {
+            if E::SEARCHABLE.is_empty() {
+                return Err(AppError::InvalidQuery(format!(
+                    "{} does not support text search",
+                    E::TABLE
+                )));
+            }
+            let pattern = query::contains_pattern(text);
+            query.push(separator).push("(");
+            for (i, column) in E::SEARCHABLE.iter().enumerate() {
+                if i > 0 {
+                    query.push(" OR ");
+                }
+                query.push("LOWER(").push(column).push(") LIKE ");
+                SqlValue::from(pattern.as_str()).push_bind(&mut query);
+                query.push(" ESCAPE '\\'");
+            }
+            query.push(")");
+            separator = " AND ";
+        }
+
+        // Keyset pagination: rows after the cursor row in sort order
+        if let Some(after) = spec.after {
+            let keys = sort.keys();
+            query
+                .push(separator)
+                .push(format!(
+                    "({}) {} (SELECT {} FROM ",
+                    keys,
+                    sort.direction.after(),
+                    keys
+                ))
+                .push(E::TABLE)
+                .push(" WHERE id = ");
+            SqlValue::from(after).push_bind(&mut query);
+            query.push(")");
+        }
+
+        query
+            .push(" ORDER BY ")
+            .push(sort.order_by())
+            .push(" LIMIT ");
+        SqlValue::from(limit as i64 + 1).push_bind(&mut query);
+
+        let mut items = DB::build(&mut query)
+            .try_map(|row| E::from_row(&row))
+            .fetch_all(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        let next = if items.len() > limit {
+            items.truncate(limit);
+            items.last().map(E::id)
+        } else {
+            None
+        };
+
+        Ok(Page { items, next })
+    }
+
+    /// Lists the rows whose `column` equals `value`, e.g. the lines of one order.
+    pub async fn find_by(
+        &mut self,
+        column: &'static str,
+        value: impl Into<SqlValue>,
+    ) -> Result<Vec<E>> {
+        let mut query = QueryBuilder::<DB>::new("SELECT ");
+        query
+            .push(E::COLUMNS)
+            .push(" FROM ")
+            .push(E::TABLE)
+            .push(" WHERE ")
+            .push(column)
+            .push(" = ");
+        value.into().push_bind(&mut query);
+        query.push(" ORDER BY ").push(E::SORT.order_by());
+
+        DB::build(&mut query)
+            .try_map(|row| E::from_row(&row))
+            .fetch_all(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)
+    }
+
+    /// Updates the fields set in `input`. Returns `None` when the row does not
+    /// exist or is no longer editable.
+    pub async fn update(&mut self, id: Uuid, input: E::Update) -> Result<Option<E>> {
+        let values = E::update_values(input);
+        if values.is_empty() {
+            return self.get(id).await;
+        }
+
+        let mut query = QueryBuilder::<DB>::new("UPDATE ");
+        query.push(E::TABLE).push(" SET ");
+        for (i, (column, value)) in values.into_iter().enumerate() {
+            if i > 0 {
+                query.push(", ");
+            }
+            query.push(column).push(" = ");
+            value.push_bind(&mut query);
+        }
+        if E::HAS_UPDATED_AT {
+            query.push(", updated_at = CURRENT_TIMESTAMP");
+        }
+        query.push(" WHERE id = ");
+        SqlValue::from(id).push_bind(&mut query);
+        if let Some(editable) = E::EDITABLE {
+            query.push(" AND (").push(editable).push(")");
+        }
+        query.push(" RETURNING ").push(E::COLUMNS);
+
+        DB::build(&mut query)
+            .try_map(|row| E::from_row(&row))
+            .fetch_optional(&mut *self.conn)
+            .await
+            .map_err(map_error::<E>)
+    }
+
+    /// Retires a row according to `E::ARCHIVE`. Returns `false` when nothing
+    /// matched, including rows that are no longer editable or archivable.
+    pub async fn archive(&mut self, id: Uuid) -> Result<bool> {
+        let mut query = match E::ARCHIVE {
+            Archive::Update(set) => {
+                let mut query = QueryBuilder::<DB>::new("UPDATE ");
+                query.push(E::TABLE).push(" SET ").push(set);
+                if E::HAS_UPDATED_AT {
+                    query.push(", updated_at = CURRENT_TIMESTAMP");
+                }
+                query
+            }
+            Archive::Delete => {
+                let mut query = QueryBuilder::<DB>::new("DELETE FROM ");
+                query.push(E::TABLE);
+                query
+            }
+            Archive::Unsupported => {
+                return Err(AppError::App(format!("{} cannot be archived", E::TABLE)));
+            }
+        };
+
+        query.push(" WHERE id = ");
+        SqlValue::from(id).push_bind(&mut query);
+        for guard in [E::EDITABLE, E::ARCHIVABLE].into_iter().flatten() {
+            query.push(" AND (").push(guard).push(")");
+        }
+        query.push(" RETURNING id");
+
+        let archived = DB::build(&mut query)
+            .fetch_optional(&mut *self.conn)
+            .await
+            .map_err(map_error::<E>)?;
+
+        Ok(archived.is_some())
+    }
+}
+
+/// Maps a column name from a `ListQuery` to the entity's declared column.
+fn allowed<E: Entity>(
+    columns: &'static [&'static str],
+    column: &str,
+    purpose: &str,
+) -> Result<&'static str> {
+    columns
+        .iter()
+        .chain([&E::SORT.column])
+        .find(|c| **c == column)
+        .copied()
+        .ok_or_else(|| {
+            AppError::InvalidQuery(format!("cannot {} {} by {}", purpose, E::TABLE, column))
+        })
+}
+
+/// Records `column` in an update only when the caller set it.
+fn changed<T: Into<SqlValue>>(
+    values: &mut Vec<(&'static str, SqlValue)>,
+    column: &'static str,
+    value: Option<T>,
+) {
+    if let Some(value) = value {
+        values.push((column, value.into()));
+    }
+}
+
+fn map_error<E: Entity>(err: sqlx::Error) -> AppError {
+    if let sqlx::Error::Database(db_err) = &err
+        && db_err.is_unique_violation()
+    {
+        return AppError::Conflict {
+            table: E::TABLE,
+            constraint: db_err.constraint().unwrap_or_default().to_string(),
+        };
+    }
+    AppError::Database(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Sqlite;
+    use sqlx::pool::PoolConnection;
+    use uuid::Uuid;
+
+    use super::query::{Direction, FilterOp};
+    use super::{ListQuery, Repository};
+    use crate::db::enums::{CostingMethod, PartnerType, RoundingMode};
+    use crate::db::models::{
+        Company, CreateCompany, CreateCurrency, CreatePartner, Currency, Partner, UpdatePartner,
+    };
+    use crate::db::testing;
+    use crate::error::AppError;
+
+    async fn company(conn: &mut PoolConnection<Sqlite>) -> Uuid {
+        Repository::<Company, Sqlite>::new(conn)
+            .create(CreateCompany {
+                name: "Test company".into(),
+                address: None,
+                base_currency_id: None,
+                costing_method: CostingMethod::Fifo,
+            })
+            .await
+            .unwrap()
+            .id
+    }
+
+    fn partner(company_id: Uuid, name: &str) -> CreatePartner {
+        CreatePartner {
+            company_id,
+            name: name.into(),
+            partner_type: PartnerType::Customer,
+            email: None,
+            phone: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn rows_are_created_changed_and_archived() {
+        let pool = testing::sqlite().await;
+        let mut conn = pool.acquire().await.unwrap();
+        let company = company(&mut conn).await;
+        let mut partners = Repository::<Partner, Sqlite>::new(&mut conn);
+        let created = partners.create(partner(company, "Acme")).await.unwrap();
+        assert!(created.is_active);
+
+        let updated = partners
+            .update(
+                created.id,
+                UpdatePartner {
+                    partner_type: Some(PartnerType::Vendor),
+                    email: Some(Some("sales@acme.test".into())),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.name, "Acme");
+        assert_eq!(updated.partner_type, PartnerType::Vendor);
+        assert_eq!(updated.email.as_deref(), Some("sales@acme.test"));
+
+        assert!(partners.archive(created.id).await.unwrap());
+        assert!(!partners.get(created.id).await.unwrap().unwrap().is_active);
+        let missing = partners.update(Uuid::new_v4(), UpdatePartner::default());
+        assert!(missing.await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn unique_violations_are_conflicts() {
+        let pool = testing::sqlite().await;
+        let mut conn = pool.acquire().await.unwrap();
+        let currency = || CreateCurrency {
+            code: "XTS".into(),
+            name: "Test".into(),
+            symbol: None,
+            decimal_places: 2,
+            rounding_mode: RoundingMode::HalfUp,
+        };
+        let mut currencies = Repository::<Currency, Sqlite>::new(&mut conn);
+        currencies.create(currency()).await.unwrap();
+        let again = currencies.create(currency()).await;
+        assert!(matches!(
+            again,
+            Err(AppError::Conflict {
+                table: "currencies",
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn keyset_pages_walk_every_row_once() {
+        let pool = testing::sqlite().await;
+        let mut conn = pool.acquire().await.unwrap();
+        let company = company(&mut conn).await;
+        let mut partners = Repository::<Partner, Sqlite>::new(&mut conn);
+        // Two rows tie on the sort column, so `id` has to order them
+        for name in ["Delta", "Alpha", "Echo", "Bravo", "Bravo"] {
+            partners.create(partner(company, name)).await.unwrap();
+        }
+
+        for direction in [Direction::Asc, Direction::Desc] {
+            let query = ListQuery::new()
+                .eq("company_id", company)
+                .sort_by("name", direction)
+                .limit(2);
+            let mut names = Vec::new();
+            let mut ids = Vec::new();
+            let mut cursor = None;
+            loop {
+                let page = partners.list(&query.clone().after(cursor)).await.unwrap();
+                names.extend(page.items.iter().map(|p| p.name.clone()));
+                ids.extend(page.items.iter().map(|p| p.id));
+                cursor = page.next;
+                if cursor.is_none() {
+                    break;
+                }
+            }
+            let mut expected = vec!["Alpha", "Bravo", "Bravo", "Delta", "Echo"];
+            if direction == Direction::Desc {
+                expected.reverse();
+            }
+            assert_eq!(names, expected);
+            ids.sort();
+            ids.dedup();
+            assert_eq!(ids.len(), 5);
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_filter_and_search_declared_columns_only() {
+        let pool = testing::sqlite().await;
+        let mut conn = pool.acquire().await.unwrap();
+        let company = company(&mut conn).await;
+        let mut partners = Repository::<Partner, Sqlite>::new(&mut conn);
+        for name in ["Save 50% Ltd", "Save 500 Ltd", "Other"] {
+            partners.create(partner(company, name)).await.unwrap();
+        }
+        let other = partners.create(partner(company, "Archived")).await.unwrap();
+        partners.archive(other.id).await.unwrap();
+
+        // `%` is matched literally, not as a wildcard
+        let found = partners
+            .list(&ListQuery::new().search("50%"))
+            .await
+            .unwrap();
+        let names: Vec<_> = found.items.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Save 50% Ltd"]);
+
+        let active = partners
+            .list(&ListQuery::new().filter("is_active", FilterOp::Eq, true))
+            .await
+            .unwrap();
+        assert_eq!(active.items.len(), 3);
+        let customers = partners
+            .list(&ListQuery::new().filter("type", FilterOp::Ne, PartnerType::Vendor))
+            .await
+            .unwrap();
+        assert_eq!(customers.items.len(), 4);
+
+        for query in [
+            ListQuery::new().eq("password_hash", "x"),
+            ListQuery::new().sort_by("name; DROP TABLE partners", Direction::Asc),
+        ] {
+            let refused = partners.list(&query).await;
+            assert!(matches!(refused, Err(AppError::InvalidQuery(_))));
+        }
+    }
+}
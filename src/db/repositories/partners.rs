@@ -0,0 +1,41 @@
+use uuid::Uuid;
+
+use crate::db::models::{CreatePartner, Partner, UpdatePartner};
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue, changed};
+
+impl Entity for Partner {
+    const TABLE: &'static str = "partners";
+    const COLUMNS: &'static str =
+        "id, company_id, name, type AS partner_type, email, phone, is_active, created_at";
+    const SORT: Sort = Sort::asc("name");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const FILTERABLE: &'static [&'static str] = &["company_id", "type", "is_active"];
+    const SORTABLE: &'static [&'static str] = &["name", "created_at"];
+    const SEARCHABLE: &'static [&'static str] = &["name", "email", "phone"];
+
+    type Create = CreatePartner;
+    type Update = UpdatePartner;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreatePartner) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("name", input.name.into()),
+            ("type", input.partner_type.into()),
+            ("email", input.email.into()),
+            ("phone", input.phone.into()),
+        ]
+    }
+
+    fn update_values(input: UpdatePartner) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "name", input.name);
+        changed(&mut values, "type", input.partner_type);
+        changed(&mut values, "email", input.email);
+        changed(&mut values, "phone", input.phone);
+        values
+    }
+}
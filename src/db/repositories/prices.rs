@@ -0,0 +1,116 @@
+use uuid::Uuid;
+
+use crate::db::models::{
+    CreatePartnerGroup, CreatePriceList, CreatePriceListItem, PartnerGroup, PriceList,
+    PriceListItem, UpdatePartnerGroup, UpdatePriceList, UpdatePriceListItem,
+};
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue, changed};
+
+// Members are added and removed through `PricingService`.
+impl Entity for PartnerGroup {
+    const TABLE: &'static str = "partner_groups";
+    const COLUMNS: &'static str = "id, company_id, name";
+    const SORT: Sort = Sort::asc("name");
+    const ARCHIVE: Archive = Archive::Delete;
+    const FILTERABLE: &'static [&'static str] = &["company_id"];
+    const SORTABLE: &'static [&'static str] = &["name"];
+    const SEARCHABLE: &'static [&'static str] = &["name"];
+
+    type Create = CreatePartnerGroup;
+    type Update = UpdatePartnerGroup;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreatePartnerGroup) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("name", input.name.into()),
+        ]
+    }
+
+    fn update_values(input: UpdatePartnerGroup) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "name", input.name);
+        values
+    }
+}
+
+impl Entity for PriceList {
+    const TABLE: &'static str = "price_lists";
+    const COLUMNS: &'static str = "id, company_id, name, currency_id, partner_id, \
+        partner_group_id, valid_from, valid_to, priority, is_active, created_at";
+    const SORT: Sort = Sort::asc("name");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const FILTERABLE: &'static [&'static str] = &[
+        "company_id",
+        "currency_id",
+        "partner_id",
+        "partner_group_id",
+        "is_active",
+    ];
+    const SORTABLE: &'static [&'static str] = &["name", "valid_from", "priority", "created_at"];
+    const SEARCHABLE: &'static [&'static str] = &["name"];
+
+    type Create = CreatePriceList;
+    type Update = UpdatePriceList;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreatePriceList) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("name", input.name.into()),
+            ("currency_id", input.currency_id.into()),
+            ("partner_id", input.partner_id.into()),
+            ("partner_group_id", input.partner_group_id.into()),
+            ("valid_from", input.valid_from.into()),
+            ("valid_to", input.valid_to.into()),
+            ("priority", i64::from(input.priority).into()),
+        ]
+    }
+
+    fn update_values(input: UpdatePriceList) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "name", input.name);
+        changed(&mut values, "valid_from", input.valid_from);
+        changed(&mut values, "valid_to", input.valid_to);
+        changed(&mut values, "priority", input.priority.map(i64::from));
+        values
+    }
+}
+
+impl Entity for PriceListItem {
+    const TABLE: &'static str = "price_list_items";
+    const COLUMNS: &'static str = "id, price_list_id, variant_id, min_quantity, unit_price";
+    const SORT: Sort = Sort::asc("min_quantity");
+    const ARCHIVE: Archive = Archive::Delete;
+    const FILTERABLE: &'static [&'static str] = &["price_list_id", "variant_id"];
+    const SORTABLE: &'static [&'static str] = &["min_quantity", "unit_price"];
+
+    type Create = CreatePriceListItem;
+    type Update = UpdatePriceListItem;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreatePriceListItem) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("price_list_id", input.price_list_id.into()),
+            ("variant_id", input.variant_id.into()),
+            ("min_quantity", input.min_quantity.into()),
+            ("unit_price", input.unit_price.into()),
+        ]
+    }
+
+    fn update_values(input: UpdatePriceListItem) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "min_quantity", input.min_quantity);
+        changed(&mut values, "unit_price", input.unit_price);
+        values
+    }
+}
@@ -0,0 +1,167 @@
+use uuid::Uuid;
+
+use crate::db::barcode;
+use crate::db::models::{
+    CreateProduct, CreateProductVariant, Product, ProductVariant, UpdateProduct,
+    UpdateProductVariant,
+};
+use crate::db::repositories::{Archive, Entity, Repository, Sort, SqlValue, changed};
+use crate::error::{AppError, Result};
+
+impl Entity for Product {
+    const TABLE: &'static str = "products";
+    const COLUMNS: &'static str =
+        "id, company_id, name, description, category, is_active, created_at";
+    const SORT: Sort = Sort::asc("name");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const FILTERABLE: &'static [&'static str] = &["company_id", "category", "is_active"];
+    const SORTABLE: &'static [&'static str] = &["name", "created_at"];
+    const SEARCHABLE: &'static [&'static str] = &["name", "description", "category"];
+
+    type Create = CreateProduct;
+    type Update = UpdateProduct;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateProduct) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("name", input.name.into()),
+            ("description", input.description.into()),
+            ("category", input.category.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateProduct) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "name", input.name);
+        changed(&mut values, "description", input.description);
+        changed(&mut values, "category", input.category);
+        values
+    }
+}
+
+// Create and update variants through `CatalogService`, which normalizes
+// barcodes and explains attribute problems; a trigger holds attributes to
+// the product's definitions whichever way a variant is written.
+impl Entity for ProductVariant {
+    const TABLE: &'static str = "product_variants";
+    const COLUMNS: &'static str = "id, product_id, sku, barcode, attributes, \
+        cost_price, selling_price, inventory_account_id, cogs_account_id, revenue_account_id, \
+        tracking, stock_unit_id, weight, volume, is_active";
+    // sku is optional, so it cannot be a keyset sort column
+    const SORT: Sort = Sort::asc("id");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const FILTERABLE: &'static [&'static str] = &["product_id", "tracking", "is_active"];
+    const SORTABLE: &'static [&'static str] = &["cost_price", "selling_price"];
+    const SEARCHABLE: &'static [&'static str] = &["sku", "barcode"];
+
+    type Create = CreateProductVariant;
+    type Update = UpdateProductVariant;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateProductVariant) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("product_id", input.product_id.into()),
+            ("sku", input.sku.into()),
+            ("barcode", input.barcode.into()),
+            ("attributes", input.attributes.into()),
+            ("cost_price", input.cost_price.into()),
+            ("selling_price", input.selling_price.into()),
+            ("inventory_account_id", input.inventory_account_id.into()),
+            ("cogs_account_id", input.cogs_account_id.into()),
+            ("revenue_account_id", input.revenue_account_id.into()),
+            ("tracking", input.tracking.into()),
+            ("stock_unit_id", input.stock_unit_id.into()),
+            ("weight", input.weight.into()),
+            ("volume", input.volume.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateProductVariant) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "sku", input.sku);
+        changed(&mut values, "barcode", input.barcode);
+        changed(&mut values, "attributes", input.attributes);
+        changed(&mut values, "cost_price", input.cost_price);
+        changed(&mut values, "selling_price", input.selling_price);
+        changed(
+            &mut values,
+            "inventory_account_id",
+            input.inventory_account_id,
+        );
+        changed(&mut values, "cogs_account_id", input.cogs_account_id);
+        changed(&mut values, "revenue_account_id", input.revenue_account_id);
+        changed(&mut values, "tracking", input.tracking);
+        changed(&mut values, "stock_unit_id", input.stock_unit_id);
+        changed(&mut values, "weight", input.weight);
+        changed(&mut values, "volume", input.volume);
+        values
+    }
+}
+
+impl Repository<'_, Product> {
+    pub async fn variants(&mut self, product_id: Uuid) -> Result<Vec<ProductVariant>> {
+        Repository::<ProductVariant>::new(&mut *self.conn)
+            .find_by("product_id", product_id)
+            .await
+    }
+}
+
+impl Repository<'_, ProductVariant> {
+    /// Active variant of a company whose barcode or SKU is exactly `code`,
+    /// as read by a scanner. A barcode match wins over a SKU match; UPC-A
+    /// and EAN-13 spellings of a code match each other.
+    pub async fn find_by_code(
+        &mut self,
+        company_id: Uuid,
+        code: &str,
+    ) -> Result<Option<ProductVariant>> {
+        sqlx::query_as(&format!(
+            "SELECT {} FROM product_variants \
+             WHERE is_active AND (barcode = ANY($3) OR sku = $2) \
+             AND product_id IN (SELECT id FROM products WHERE company_id = $1) \
+             ORDER BY (barcode = ANY($3)) IS TRUE DESC LIMIT 1",
+            ProductVariant::COLUMNS
+        ))
+        .bind(company_id)
+        .bind(code.trim())
+        .bind(barcode::equivalents(code))
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::enums::TrackingMode;
+    use crate::db::models::ProductVariant;
+    use crate::db::repositories::Repository;
+    use crate::db::testing;
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn barcode_match_wins_over_sku_match() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let code = "4006381333931";
+        let by_barcode =
+            testing::variant(conn, company, TrackingMode::Untracked, None, Some(code)).await;
+        // Without a barcode its comparison is NULL, which must not sort first
+        testing::variant(conn, company, TrackingMode::Untracked, Some(code), None).await;
+
+        let found = Repository::<ProductVariant>::new(conn)
+            .find_by_code(company, code)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, by_barcode);
+    }
+}
@@ -0,0 +1,104 @@
+use uuid::Uuid;
+
+use crate::db::models::{
+    CreatePurchaseOrder, CreatePurchaseOrderLine, PurchaseOrder, PurchaseOrderLine,
+    UpdatePurchaseOrder, UpdatePurchaseOrderLine,
+};
+use crate::db::repositories::{Archive, Entity, Repository, Sort, SqlValue, changed};
+use crate::error::Result;
+
+impl Entity for PurchaseOrder {
+    const TABLE: &'static str = "purchase_orders";
+    const COLUMNS: &'static str = "id, company_id, vendor_id, order_date, currency_id, \
+        warehouse_id, status, total_amount, created_at, created_by";
+    const SORT: Sort = Sort::desc("order_date");
+    const ARCHIVE: Archive = Archive::Update("status = 'CANCELLED'");
+    const EDITABLE: Option<&'static str> = Some("status NOT IN ('COMPLETED', 'CANCELLED')");
+    const FILTERABLE: &'static [&'static str] = &[
+        "company_id",
+        "vendor_id",
+        "currency_id",
+        "warehouse_id",
+        "status",
+        "order_date",
+        "created_by",
+    ];
+    const SORTABLE: &'static [&'static str] = &["order_date", "created_at", "total_amount"];
+
+    type Create = CreatePurchaseOrder;
+    type Update = UpdatePurchaseOrder;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreatePurchaseOrder) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("vendor_id", input.vendor_id.into()),
+            ("order_date", input.order_date.into()),
+            ("currency_id", input.currency_id.into()),
+            ("warehouse_id", input.warehouse_id.into()),
+            ("status", input.status.into()),
+            ("created_by", input.created_by.into()),
+        ]
+    }
+
+    fn update_values(input: UpdatePurchaseOrder) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "vendor_id", input.vendor_id);
+        changed(&mut values, "order_date", input.order_date);
+        changed(&mut values, "currency_id", input.currency_id);
+        changed(&mut values, "warehouse_id", input.warehouse_id);
+        changed(&mut values, "status", input.status);
+        values
+    }
+}
+
+// subtotal and the order's total_amount are maintained by triggers, which also
+// refuse a unit the variant is not bought in.
+impl Entity for PurchaseOrderLine {
+    const TABLE: &'static str = "purchase_order_lines";
+    const COLUMNS: &'static str =
+        "id, purchase_order_id, variant_id, quantity, unit_id, unit_cost, subtotal";
+    const SORT: Sort = Sort::asc("id");
+    const ARCHIVE: Archive = Archive::Delete;
+    const EDITABLE: Option<&'static str> = Some(
+        "purchase_order_id IN (SELECT id FROM purchase_orders WHERE status IN ('DRAFT', 'PENDING'))",
+    );
+    const FILTERABLE: &'static [&'static str] = &["purchase_order_id", "variant_id"];
+
+    type Create = CreatePurchaseOrderLine;
+    type Update = UpdatePurchaseOrderLine;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreatePurchaseOrderLine) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("purchase_order_id", input.purchase_order_id.into()),
+            ("variant_id", input.variant_id.into()),
+            ("quantity", input.quantity.into()),
+            ("unit_id", input.unit_id.into()),
+            ("unit_cost", input.unit_cost.into()),
+        ]
+    }
+
+    fn update_values(input: UpdatePurchaseOrderLine) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "variant_id", input.variant_id);
+        changed(&mut values, "quantity", input.quantity);
+        changed(&mut values, "unit_id", input.unit_id);
+        changed(&mut values, "unit_cost", input.unit_cost);
+        values
+    }
+}
+
+impl Repository<'_, PurchaseOrder> {
+    pub async fn lines(&mut self, order_id: Uuid) -> Result<Vec<PurchaseOrderLine>> {
+        Repository::<PurchaseOrderLine>::new(&mut *self.conn)
+            .find_by("purchase_order_id", order_id)
+            .await
+    }
+}
@@ -0,0 +1,220 @@
+use uuid::Uuid;
+
+use crate::db::repositories::SqlValue;
+
+/// Rows per page when a `ListQuery` does not set a limit.
+pub const DEFAULT_LIMIT: u32 = 50;
+pub const MAX_LIMIT: u32 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Asc,
+    Desc,
+}
+
+impl Direction {
+    pub(crate) fn keyword(self) -> &'static str {
+        match self {
+            Direction::Asc => "ASC",
+            Direction::Desc => "DESC",
+        }
+    }
+
+    /// Comparison that selects the rows after a cursor in this direction.
+    pub(crate) fn after(self) -> &'static str {
+        match self {
+            Direction::Asc => ">",
+            Direction::Desc => "<",
+        }
+    }
+}
+
+/// A sort column declared by an entity.
+#[derive(Debug, Clone, Copy)]
+pub struct Sort {
+    pub column: &'static str,
+    pub direction: Direction,
+}
+
+impl Sort {
+    pub const fn asc(column: &'static str) -> Self {
+        Self {
+            column,
+            direction: Direction::Asc,
+        }
+    }
+
+    pub const fn desc(column: &'static str) -> Self {
+        Self {
+            column,
+            direction: Direction::Desc,
+        }
+    }
+
+    /// Sort key columns. `id` always comes last so the order is total and
+    /// a cursor row pins an exact position.
+    pub(crate) fn keys(self) -> String {
+        if self.column == "id" {
+            "id".to_string()
+        } else {
+            format!("{}, id", self.column)
+        }
+    }
+
+    pub(crate) fn order_by(self) -> String {
+        let direction = self.direction.keyword();
+        if self.column == "id" {
+            format!("id {}", direction)
+        } else {
+            format!("{} {}, id {}", self.column, direction, direction)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    IsNull,
+    IsNotNull,
+}
+
+impl FilterOp {
+    pub(crate) fn sql(self) -> &'static str {
+        match self {
+            FilterOp::Eq => " = ",
+            FilterOp::Ne => " <> ",
+            FilterOp::Lt => " < ",
+            FilterOp::Le => " <= ",
+            FilterOp::Gt => " > ",
+            FilterOp::Ge => " >= ",
+            FilterOp::IsNull => " IS NULL",
+            FilterOp::IsNotNull => " IS NOT NULL",
+        }
+    }
+
+    pub(crate) fn takes_value(self) -> bool {
+        !matches!(self, FilterOp::IsNull | FilterOp::IsNotNull)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub column: String,
+    pub op: FilterOp,
+    pub value: SqlValue,
+}
+
+/// What a list screen asks for: filters, a text search, the sort order and
+/// the page. Column names are table columns and are checked against the
+/// entity's `FILTERABLE`/`SORTABLE` lists, so they can come from the UI.
+///
+/// Pages are keyset-based: pass the previous page's `next` cursor to `after`
+/// instead of an offset, which keeps deep pages as fast as the first one.
+#[derive(Debug, Clone, Default)]
+pub struct ListQuery {
+    pub filters: Vec<Filter>,
+    pub search: Option<String>,
+    pub sort: Option<(String, Direction)>,
+    pub limit: Option<u32>,
+    pub after: Option<Uuid>,
+}
+
+impl ListQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn filter(mut self, column: &str, op: FilterOp, value: impl Into<SqlValue>) -> Self {
+        self.filters.push(Filter {
+            column: column.to_string(),
+            op,
+            value: value.into(),
+        });
+        self
+    }
+
+    pub fn eq(self, column: &str, value: impl Into<SqlValue>) -> Self {
+        self.filter(column, FilterOp::Eq, value)
+    }
+
+    pub fn search(mut self, text: impl Into<String>) -> Self {
+        let text = text.into();
+        self.search = (!text.trim().is_empty()).then_some(text);
+        self
+    }
+
+    pub fn sort_by(mut self, column: &str, direction: Direction) -> Self {
+        self.sort = Some((column.to_string(), direction));
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn after(mut self, cursor: Option<Uuid>) -> Self {
+        self.after = cursor;
+        self
+    }
+
+    pub(crate) fn page_size(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Page<E> {
+    pub items: Vec<E>,
+    /// Cursor for the following page, `None` on the last page.
+    pub next: Option<Uuid>,
+}
+
+impl<E> Page<E> {
+    pub fn has_more(&self) -> bool {
+        self.next.is_some()
+    }
+}
+
+/// `LIKE` pattern matching `text` anywhere, with wildcards escaped.
+pub(crate) fn contains_pattern(text: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in text.trim().to_lowercase().chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ListQuery, MAX_LIMIT, Sort, contains_pattern};
+
+    #[test]
+    fn search_patterns_escape_like_wildcards() {
+        assert_eq!(contains_pattern("  Ab "), "%ab%");
+        assert_eq!(contains_pattern(r"50%_\"), r"%50\%\_\\%");
+    }
+
+    #[test]
+    fn id_breaks_ties_in_the_sort_order() {
+        assert_eq!(Sort::desc("name").order_by(), "name DESC, id DESC");
+        assert_eq!(Sort::asc("name").keys(), "name, id");
+        assert_eq!(Sort::asc("id").keys(), "id");
+    }
+
+    #[test]
+    fn page_sizes_stay_in_bounds() {
+        assert_eq!(ListQuery::new().limit(0).page_size(), 1);
+        assert_eq!(ListQuery::new().limit(10_000).page_size(), MAX_LIMIT);
+        assert!(ListQuery::new().search("   ").search.is_none());
+    }
+}
@@ -0,0 +1,49 @@
+use uuid::Uuid;
+
+use crate::db::models::{CreateReorderRule, ReorderRule, UpdateReorderRule};
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue, changed};
+
+impl Entity for ReorderRule {
+    const TABLE: &'static str = "reorder_rules";
+    const COLUMNS: &'static str = "id, company_id, variant_id, warehouse_id, min_quantity, \
+        max_quantity, reorder_quantity, order_multiple, vendor_id, is_active";
+    const SORT: Sort = Sort::asc("id");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const FILTERABLE: &'static [&'static str] = &[
+        "company_id",
+        "variant_id",
+        "warehouse_id",
+        "vendor_id",
+        "is_active",
+    ];
+
+    type Create = CreateReorderRule;
+    type Update = UpdateReorderRule;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateReorderRule) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("variant_id", input.variant_id.into()),
+            ("warehouse_id", input.warehouse_id.into()),
+            ("min_quantity", input.min_quantity.into()),
+            ("max_quantity", input.max_quantity.into()),
+            ("reorder_quantity", input.reorder_quantity.into()),
+            ("order_multiple", input.order_multiple.into()),
+            ("vendor_id", input.vendor_id.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateReorderRule) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "min_quantity", input.min_quantity);
+        changed(&mut values, "max_quantity", input.max_quantity);
+        changed(&mut values, "reorder_quantity", input.reorder_quantity);
+        changed(&mut values, "order_multiple", input.order_multiple);
+        changed(&mut values, "vendor_id", input.vendor_id);
+        values
+    }
+}
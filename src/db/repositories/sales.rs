@@ -0,0 +1,103 @@
+use uuid::Uuid;
+
+use crate::db::models::{
+    CreateSalesOrder, CreateSalesOrderLine, SalesOrder, SalesOrderLine, UpdateSalesOrder,
+    UpdateSalesOrderLine,
+};
+use crate::db::repositories::{Archive, Entity, Repository, Sort, SqlValue, changed};
+use crate::error::Result;
+
+// Confirm, cancel and deliver orders through `ReservationService` so their
+// stock reservations follow the status. Only draft and pending orders, which
+// hold no reservations, can be edited or archived here.
+impl Entity for SalesOrder {
+    const TABLE: &'static str = "sales_orders";
+    const COLUMNS: &'static str = "id, company_id, customer_id, order_date, currency_id, status, \
+        total_amount, created_at, created_by";
+    const SORT: Sort = Sort::desc("order_date");
+    const ARCHIVE: Archive = Archive::Update("status = 'CANCELLED'");
+    const EDITABLE: Option<&'static str> = Some("status IN ('DRAFT', 'PENDING')");
+    const FILTERABLE: &'static [&'static str] = &[
+        "company_id",
+        "customer_id",
+        "currency_id",
+        "status",
+        "order_date",
+        "created_by",
+    ];
+    const SORTABLE: &'static [&'static str] = &["order_date", "created_at", "total_amount"];
+
+    type Create = CreateSalesOrder;
+    type Update = UpdateSalesOrder;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateSalesOrder) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("customer_id", input.customer_id.into()),
+            ("order_date", input.order_date.into()),
+            ("currency_id", input.currency_id.into()),
+            ("status", input.status.into()),
+            ("created_by", input.created_by.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateSalesOrder) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "customer_id", input.customer_id);
+        changed(&mut values, "order_date", input.order_date);
+        changed(&mut values, "currency_id", input.currency_id);
+        values
+    }
+}
+
+// subtotal and the order's total_amount are maintained by triggers, which also
+// refuse a unit the variant is not sold in.
+impl Entity for SalesOrderLine {
+    const TABLE: &'static str = "sales_order_lines";
+    const COLUMNS: &'static str =
+        "id, sales_order_id, variant_id, quantity, unit_id, unit_price, subtotal";
+    const SORT: Sort = Sort::asc("id");
+    const ARCHIVE: Archive = Archive::Delete;
+    const EDITABLE: Option<&'static str> = Some(
+        "sales_order_id IN (SELECT id FROM sales_orders WHERE status IN ('DRAFT', 'PENDING'))",
+    );
+    const FILTERABLE: &'static [&'static str] = &["sales_order_id", "variant_id"];
+
+    type Create = CreateSalesOrderLine;
+    type Update = UpdateSalesOrderLine;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateSalesOrderLine) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("sales_order_id", input.sales_order_id.into()),
+            ("variant_id", input.variant_id.into()),
+            ("quantity", input.quantity.into()),
+            ("unit_id", input.unit_id.into()),
+            ("unit_price", input.unit_price.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateSalesOrderLine) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "variant_id", input.variant_id);
+        changed(&mut values, "quantity", input.quantity);
+        changed(&mut values, "unit_id", input.unit_id);
+        changed(&mut values, "unit_price", input.unit_price);
+        values
+    }
+}
+
+impl Repository<'_, SalesOrder> {
+    pub async fn lines(&mut self, order_id: Uuid) -> Result<Vec<SalesOrderLine>> {
+        Repository::<SalesOrderLine>::new(&mut *self.conn)
+            .find_by("sales_order_id", order_id)
+            .await
+    }
+}
@@ -0,0 +1,42 @@
+use std::convert::Infallible;
+
+use uuid::Uuid;
+
+use crate::db::models::StockMovement;
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue};
+
+impl Entity for StockMovement {
+    const TABLE: &'static str = "stock_movements";
+    const COLUMNS: &'static str = "id, company_id, variant_id, warehouse_id, \
+        quantity, movement_type, reference_type, reference_id, unit_cost, location_id, movement_date";
+    const SORT: Sort = Sort::desc("movement_date");
+    const ARCHIVE: Archive = Archive::Unsupported;
+    const FILTERABLE: &'static [&'static str] = &[
+        "company_id",
+        "variant_id",
+        "warehouse_id",
+        "movement_type",
+        "reference_type",
+        "reference_id",
+        "location_id",
+    ];
+    const SORTABLE: &'static [&'static str] = &["movement_date", "quantity"];
+
+    // Post movements with `StockService`, which keeps the ledger, cost
+    // layers, lots and bins in step; the repository only reads them.
+    type Create = Infallible;
+    // Movements are history: mistakes are fixed with a correcting movement.
+    type Update = Infallible;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: Infallible) -> Vec<(&'static str, SqlValue)> {
+        match input {}
+    }
+
+    fn update_values(input: Infallible) -> Vec<(&'static str, SqlValue)> {
+        match input {}
+    }
+}
@@ -0,0 +1,92 @@
+use uuid::Uuid;
+
+use crate::db::models::{
+    CreateStockTransfer, CreateStockTransferLine, StockTransfer, StockTransferLine,
+    UpdateStockTransfer, UpdateStockTransferLine,
+};
+use crate::db::repositories::{Archive, Entity, Repository, Sort, SqlValue, changed};
+use crate::error::Result;
+
+// Status moves forward through `TransferService`; only drafts are edited here.
+impl Entity for StockTransfer {
+    const TABLE: &'static str = "stock_transfers";
+    const COLUMNS: &'static str = "id, company_id, from_warehouse_id, to_warehouse_id, status, \
+        notes, created_at, created_by, shipped_at, received_at";
+    const SORT: Sort = Sort::desc("created_at");
+    const ARCHIVE: Archive = Archive::Update("status = 'CANCELLED'");
+    const EDITABLE: Option<&'static str> = Some("status = 'DRAFT'");
+    const FILTERABLE: &'static [&'static str] = &[
+        "company_id",
+        "from_warehouse_id",
+        "to_warehouse_id",
+        "status",
+        "created_by",
+    ];
+    const SORTABLE: &'static [&'static str] = &["created_at", "shipped_at", "received_at"];
+    const SEARCHABLE: &'static [&'static str] = &["notes"];
+
+    type Create = CreateStockTransfer;
+    type Update = UpdateStockTransfer;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateStockTransfer) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("from_warehouse_id", input.from_warehouse_id.into()),
+            ("to_warehouse_id", input.to_warehouse_id.into()),
+            ("notes", input.notes.into()),
+            ("created_by", input.created_by.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateStockTransfer) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "from_warehouse_id", input.from_warehouse_id);
+        changed(&mut values, "to_warehouse_id", input.to_warehouse_id);
+        changed(&mut values, "notes", input.notes);
+        values
+    }
+}
+
+impl Entity for StockTransferLine {
+    const TABLE: &'static str = "stock_transfer_lines";
+    const COLUMNS: &'static str = "id, transfer_id, variant_id, quantity, cost";
+    const SORT: Sort = Sort::asc("id");
+    const ARCHIVE: Archive = Archive::Delete;
+    const EDITABLE: Option<&'static str> =
+        Some("transfer_id IN (SELECT id FROM stock_transfers WHERE status = 'DRAFT')");
+    const FILTERABLE: &'static [&'static str] = &["transfer_id", "variant_id"];
+
+    type Create = CreateStockTransferLine;
+    type Update = UpdateStockTransferLine;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateStockTransferLine) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("transfer_id", input.transfer_id.into()),
+            ("variant_id", input.variant_id.into()),
+            ("quantity", input.quantity.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateStockTransferLine) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "variant_id", input.variant_id);
+        changed(&mut values, "quantity", input.quantity);
+        values
+    }
+}
+
+impl Repository<'_, StockTransfer> {
+    pub async fn lines(&mut self, transfer_id: Uuid) -> Result<Vec<StockTransferLine>> {
+        Repository::<StockTransferLine>::new(&mut *self.conn)
+            .find_by("transfer_id", transfer_id)
+            .await
+    }
+}
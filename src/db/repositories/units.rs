@@ -0,0 +1,107 @@
+use uuid::Uuid;
+
+use crate::db::models::{
+    CreateUnitOfMeasure, CreateUomCategory, CreateVariantUnit, UnitOfMeasure, UomCategory,
+    UpdateUnitOfMeasure, UpdateUomCategory, UpdateVariantUnit, VariantUnit,
+};
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue, changed};
+
+impl Entity for UomCategory {
+    const TABLE: &'static str = "uom_categories";
+    const COLUMNS: &'static str = "id, company_id, name";
+    const SORT: Sort = Sort::asc("name");
+    const ARCHIVE: Archive = Archive::Delete;
+    const FILTERABLE: &'static [&'static str] = &["company_id"];
+    const SORTABLE: &'static [&'static str] = &["name"];
+    const SEARCHABLE: &'static [&'static str] = &["name"];
+
+    type Create = CreateUomCategory;
+    type Update = UpdateUomCategory;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateUomCategory) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("name", input.name.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateUomCategory) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "name", input.name);
+        values
+    }
+}
+
+// The factor is fixed once created: quantities already entered in the unit
+// would silently change.
+impl Entity for UnitOfMeasure {
+    const TABLE: &'static str = "units_of_measure";
+    const COLUMNS: &'static str = "id, company_id, category_id, name, symbol, factor, is_active";
+    const SORT: Sort = Sort::asc("symbol");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const FILTERABLE: &'static [&'static str] = &["company_id", "category_id", "is_active"];
+    const SORTABLE: &'static [&'static str] = &["symbol", "name"];
+    const SEARCHABLE: &'static [&'static str] = &["name", "symbol"];
+
+    type Create = CreateUnitOfMeasure;
+    type Update = UpdateUnitOfMeasure;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateUnitOfMeasure) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("category_id", input.category_id.into()),
+            ("name", input.name.into()),
+            ("symbol", input.symbol.into()),
+            ("factor", input.factor.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateUnitOfMeasure) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "name", input.name);
+        changed(&mut values, "symbol", input.symbol);
+        values
+    }
+}
+
+impl Entity for VariantUnit {
+    const TABLE: &'static str = "variant_units";
+    const COLUMNS: &'static str = "id, variant_id, unit_id, factor, for_purchase, for_sales";
+    const SORT: Sort = Sort::asc("id");
+    const ARCHIVE: Archive = Archive::Delete;
+    const FILTERABLE: &'static [&'static str] =
+        &["variant_id", "unit_id", "for_purchase", "for_sales"];
+
+    type Create = CreateVariantUnit;
+    type Update = UpdateVariantUnit;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateVariantUnit) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("variant_id", input.variant_id.into()),
+            ("unit_id", input.unit_id.into()),
+            ("factor", input.factor.into()),
+            ("for_purchase", input.for_purchase.into()),
+            ("for_sales", input.for_sales.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateVariantUnit) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "factor", input.factor);
+        changed(&mut values, "for_purchase", input.for_purchase);
+        changed(&mut values, "for_sales", input.for_sales);
+        values
+    }
+}
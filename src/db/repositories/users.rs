@@ -0,0 +1,225 @@
+use sqlx::postgres::PgConnection;
+use uuid::Uuid;
+
+use crate::db::models::{CreateUser, UpdateUser, User};
+use crate::db::repositories::{
+    Archive, Entity, ListQuery, Page, Repository, Sort, SqlValue, changed,
+};
+use crate::error::{AppError, Result};
+
+impl Entity for User {
+    const TABLE: &'static str = "users";
+    const COLUMNS: &'static str = "id, email, name, role, is_active, created_at, updated_at";
+    const SORT: Sort = Sort::asc("name");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const HAS_UPDATED_AT: bool = true;
+    const FILTERABLE: &'static [&'static str] = &["role", "is_active"];
+    const SORTABLE: &'static [&'static str] = &["name", "email", "created_at"];
+    const SEARCHABLE: &'static [&'static str] = &["name", "email"];
+
+    type Create = CreateUser;
+    type Update = UpdateUser;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateUser) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("email", input.email.into()),
+            ("name", input.name.into()),
+            ("password_hash", input.password_hash.into()),
+            ("role", input.role.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateUser) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "email", input.email);
+        changed(&mut values, "name", input.name);
+        changed(&mut values, "role", input.role);
+        changed(&mut values, "is_active", input.is_active);
+        values
+    }
+}
+
+/// User access on top of the generic repository: email lookups, password
+/// hashes (never part of `User`) and typed duplicate-email errors.
+pub struct UserRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> UserRepository<'c> {
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+
+    fn users(&mut self) -> Repository<'_, User> {
+        Repository::new(&mut *self.conn)
+    }
+
+    pub async fn create(&mut self, user: CreateUser) -> Result<User> {
+        let email = user.email.clone();
+        self.users()
+            .create(user)
+            .await
+            .map_err(|e| map_email_conflict(e, &email))
+    }
+
+    pub async fn get(&mut self, id: Uuid) -> Result<Option<User>> {
+        self.users().get(id).await
+    }
+
+    pub async fn find_by_email(&mut self, email: &str) -> Result<Option<User>> {
+        sqlx::query_as::<_, User>(&format!(
+            "SELECT {} FROM users WHERE lower(email) = lower($1)",
+            User::COLUMNS
+        ))
+        .bind(email)
+        .fetch_optional(&mut *self.conn)
+        .await
+        .map_err(AppError::Database)
+    }
+
+    pub async fn list(&mut self, query: &ListQuery) -> Result<Page<User>> {
+        self.users().list(query).await
+    }
+
+    pub async fn count(&mut self) -> Result<i64> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)
+    }
+
+    /// Updates only the fields set in `update`, in a single statement.
+    pub async fn update(&mut self, id: Uuid, update: UpdateUser) -> Result<Option<User>> {
+        let email = update.email.clone();
+        self.users()
+            .update(id, update)
+            .await
+            .map_err(|e| match &email {
+                Some(email) => map_email_conflict(e, email),
+                None => e,
+            })
+    }
+
+    pub async fn password_hash(&mut self, id: Uuid) -> Result<Option<String>> {
+        sqlx::query_scalar("SELECT password_hash FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)
+    }
+
+    pub async fn set_password_hash(&mut self, id: Uuid, password_hash: &str) -> Result<bool> {
+        let result =
+            sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+                .bind(password_hash)
+                .bind(id)
+                .execute(&mut *self.conn)
+                .await
+                .map_err(AppError::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete(&mut self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(id)
+            .execute(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+fn map_email_conflict(err: AppError, email: &str) -> AppError {
+    match err {
+        AppError::Conflict { constraint, .. } if constraint == "users_email_key" => {
+            AppError::DuplicateEmail(email.to_string())
+        }
+        err => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Connection;
+
+    use super::UserRepository;
+    use crate::db::enums::UserRole;
+    use crate::db::models::{CreateUser, UpdateUser};
+    use crate::db::testing;
+    use crate::error::AppError;
+
+    fn user(email: &str) -> CreateUser {
+        CreateUser {
+            email: email.into(),
+            name: "Ada".into(),
+            password_hash: "first".into(),
+            role: UserRole::Sales,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn users_are_found_by_email_and_keep_their_hash_apart() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let email = format!("{}@Example.com", uuid::Uuid::new_v4());
+        let mut users = UserRepository::new(conn);
+        let created = users.create(user(&email)).await.unwrap();
+
+        let found = users
+            .find_by_email(&email.to_lowercase())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, created.id);
+        assert!(users.set_password_hash(created.id, "second").await.unwrap());
+        assert_eq!(
+            users.password_hash(created.id).await.unwrap().as_deref(),
+            Some("second")
+        );
+
+        let renamed = users
+            .update(
+                created.id,
+                UpdateUser {
+                    email: None,
+                    name: Some("Grace".into()),
+                    role: None,
+                    is_active: None,
+                },
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(renamed.name, "Grace");
+        assert_eq!(renamed.email, email);
+        assert_eq!(renamed.role, UserRole::Sales);
+        assert!(users.delete(created.id).await.unwrap());
+        assert!(users.get(created.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn a_taken_email_is_reported_as_such() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let email = format!("{}@example.com", uuid::Uuid::new_v4());
+        UserRepository::new(conn)
+            .create(user(&email))
+            .await
+            .unwrap();
+
+        let mut savepoint = conn.begin().await.unwrap();
+        let again = UserRepository::new(&mut savepoint)
+            .create(user(&email))
+            .await;
+        assert!(matches!(again, Err(AppError::DuplicateEmail(taken)) if taken == email));
+        savepoint.rollback().await.unwrap();
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use sqlx::QueryBuilder;
+use uuid::Uuid;
+
+use crate::db::backend::Backend;
+
+/// A typed column value, so repositories can build statements for any
+/// combination of fields while still binding every value as a parameter.
+#[derive(Debug, Clone)]
+pub enum SqlValue {
+    Uuid(Option<Uuid>),
+    Text(Option<String>),
+    Bool(Option<bool>),
+    Int(Option<i64>),
+    Decimal(Option<Decimal>),
+    Date(Option<NaiveDate>),
+    Json(Option<serde_json::Value>),
+    Bytes(Option<Vec<u8>>),
+    /// Text for a column of the named database enum type.
+    Enum(&'static str, Option<String>),
+}
+
+impl SqlValue {
+    pub fn push_bind<DB: Backend>(self, query: &mut QueryBuilder<'_, DB>) {
+        DB::push_value(query, self);
+    }
+}
+
+macro_rules! impl_from {
+    ($ty:ty, $variant:ident) => {
+        impl From<$ty> for SqlValue {
+            fn from(value: $ty) -> Self {
+                SqlValue::$variant(Some(value))
+            }
+        }
+
+        impl From<Option<$ty>> for SqlValue {
+            fn from(value: Option<$ty>) -> Self {
+                SqlValue::$variant(value)
+            }
+        }
+    };
+}
+
+impl_from!(Uuid, Uuid);
+impl_from!(String, Text);
+impl_from!(bool, Bool);
+impl_from!(i64, Int);
+impl_from!(Decimal, Decimal);
+impl_from!(NaiveDate, Date);
+impl_from!(serde_json::Value, Json);
+impl_from!(Vec<u8>, Bytes);
+
+impl From<&str> for SqlValue {
+    fn from(value: &str) -> Self {
+        SqlValue::Text(Some(value.to_string()))
+    }
+}
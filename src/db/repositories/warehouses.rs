@@ -0,0 +1,34 @@
+use uuid::Uuid;
+
+use crate::db::models::{CreateWarehouse, UpdateWarehouse, Warehouse};
+use crate::db::repositories::{Archive, Entity, Sort, SqlValue, changed};
+
+impl Entity for Warehouse {
+    const TABLE: &'static str = "warehouses";
+    const COLUMNS: &'static str = "id, company_id, name, is_active";
+    const SORT: Sort = Sort::asc("name");
+    const ARCHIVE: Archive = Archive::Update("is_active = false");
+    const FILTERABLE: &'static [&'static str] = &["company_id", "is_active"];
+    const SORTABLE: &'static [&'static str] = &["name"];
+    const SEARCHABLE: &'static [&'static str] = &["name"];
+
+    type Create = CreateWarehouse;
+    type Update = UpdateWarehouse;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn insert_values(input: CreateWarehouse) -> Vec<(&'static str, SqlValue)> {
+        vec![
+            ("company_id", input.company_id.into()),
+            ("name", input.name.into()),
+        ]
+    }
+
+    fn update_values(input: UpdateWarehouse) -> Vec<(&'static str, SqlValue)> {
+        let mut values = Vec::new();
+        changed(&mut values, "name", input.name);
+        values
+    }
+}
@@ -1,8 +1,26 @@
-use sqlx::Row;
-use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
+use std::time::Duration;
 
-use crate::config::DatabaseConfig;
-use crate::db::models::{CreateUser, UpdateUser, User};
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::Postgres;
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use uuid::Uuid;
+
+use crate::config::{DatabaseConfig, NegativeStockPolicy};
+use crate::db::inventory::history::{LedgerDrift, StockAsOf};
+use crate::db::inventory::valuation::VariantValue;
+use crate::db::inventory::{HistoryService, PurchaseReceipt, StockPosting, ValuationService};
+use crate::db::migrations::{MigrationReport, Migrator};
+use crate::db::models::{
+    CreateStockMovement, CreateUser, LandedCost, PurchaseOrder, StockCount, StockReservation,
+    StockTransfer, UpdateUser, User,
+};
+use crate::db::money::Quantity;
+use crate::db::pricing::{PricingService, SuggestedPrice};
+use crate::db::repositories::{ListQuery, Page, UserRepository};
+use crate::db::search::{SearchHit, SearchKind, SearchService};
+use crate::db::storage::PgStorage;
+use crate::db::transaction::{self, BoxFuture, Isolation, UnitOfWork};
 use crate::error::{AppError, Result};
 
 pub struct Database {
@@ -13,6 +31,8 @@ impl Database {
     pub async fn new(config: &DatabaseConfig) -> Result<Self> {
         let pool = PgPoolOptions::new()
             .max_connections(5)
+            // Fail fast while the server is down so reads can be queued for retry
+            .acquire_timeout(Duration::from_secs(5))
             .connect(&config.connection_string())
             .await
             .map_err(AppError::Database)?;
@@ -28,198 +48,263 @@ impl Database {
         &self.pool
     }
 
-    pub async fn init_schema(&self) -> Result<()> {
-        // Read and execute the schema SQL file
-        let schema_sql = include_str!("schema.sql");
-        
-        // Split the schema file into individual statements and execute them
-        let statements = schema_sql.split("-- =====================================================")
-            .filter(|s| !s.trim().is_empty())
-            .flat_map(|section| {
-                // Extract CREATE statements from each section
-                let mut statements = Vec::new();
-                let mut current_statement = String::new();
-                
-                for line in section.lines() {
-                    if line.trim().is_empty() && !current_statement.trim().is_empty() {
-                        statements.push(current_statement.trim().to_string());
-                        current_statement.clear();
-                    } else {
-                        if !line.trim().starts_with("--") && !line.trim().starts_with("CREATE EXTENSION") &&
-                           !line.trim().starts_with("CREATE TYPE") && !line.trim().starts_with("CREATE TABLE") &&
-                           !line.trim().starts_with("CREATE INDEX") && !line.trim().starts_with("ALTER TABLE") &&
-                           !line.trim().starts_with("CREATE OR REPLACE FUNCTION") {
-                            current_statement.push_str(line);
-                            current_statement.push('\n');
-                        } else if line.trim().starts_with("CREATE") || line.trim().starts_with("ALTER") {
-                            if !current_statement.trim().is_empty() {
-                                statements.push(current_statement.trim().to_string());
-                                current_statement.clear();
-                            }
-                            current_statement.push_str(line);
-                            current_statement.push('\n');
-                        } else if !line.trim().starts_with("--") {
-                            current_statement.push_str(line);
-                            current_statement.push('\n');
-                        }
-                    }
-                }
-                
-                if !current_statement.trim().is_empty() {
-                    statements.push(current_statement.trim().to_string());
-                }
-                
-                statements
-            })
-            .filter(|s: &String| {
-                let trimmed = s.trim();
-                !trimmed.is_empty() && 
-                (trimmed.starts_with("CREATE") || 
-                 trimmed.starts_with("ALTER") || 
-                 trimmed.starts_with("INSERT") ||
-                 trimmed.starts_with("UPDATE") ||
-                 trimmed.starts_with("DELETE"))
-            })
-            .collect::<Vec<String>>();
+    pub fn storage(&self) -> PgStorage {
+        PgStorage::new(self.pool.clone())
+    }
 
-        for statement in statements {
-            let stmt = statement.trim();
-            if !stmt.is_empty() {
-                sqlx::query(stmt)
-                    .execute(&self.pool)
-                    .await
-                    .map_err(AppError::Database)?;
-            }
-        }
+    /// Applies pending migrations, refusing to continue if the database
+    /// schema is newer than this build.
+    pub async fn migrate(&self) -> Result<MigrationReport> {
+        Migrator::<Postgres>::new(&self.pool).run().await
+    }
 
-        Ok(())
+    pub async fn migration_status(&self) -> Result<MigrationReport> {
+        Migrator::<Postgres>::new(&self.pool).status().await
     }
 
-    pub async fn create_user(&self, user: CreateUser) -> Result<User> {
-        let row = sqlx::query(
-            r#"
-            INSERT INTO users (name, email)
-            VALUES ($1, $2)
-            RETURNING id, name, email, created_at, updated_at
-            "#,
-        )
-        .bind(&user.name)
-        .bind(&user.email)
-        .fetch_one(&self.pool)
+    pub async fn acquire(&self) -> Result<PoolConnection<Postgres>> {
+        self.pool.acquire().await.map_err(AppError::Database)
+    }
+
+    /// Starts a transaction the caller commits or rolls back explicitly.
+    pub async fn begin(&self) -> Result<UnitOfWork<'static>> {
+        let tx = self.pool.begin().await.map_err(AppError::Database)?;
+        Ok(UnitOfWork::new(tx))
+    }
+
+    /// Runs `work` atomically: committed if it returns `Ok`, rolled back if
+    /// it fails, and retried on serialization failures and deadlocks.
+    ///
+    /// ```ignore
+    /// db.transaction(|uow| Box::pin(async move {
+    ///     let order = uow.repo::<SalesOrder>().create(order).await?;
+    ///     uow.repo::<SalesOrderLine>().create(line).await?;
+    ///     Ok(order)
+    /// }))
+    /// .await?;
+    /// ```
+    pub async fn transaction<T, F>(&self, work: F) -> Result<T>
+    where
+        F: for<'t> FnMut(&'t mut UnitOfWork<'static>) -> BoxFuture<'t, Result<T>>,
+    {
+        transaction::run(&self.pool, Isolation::default(), work).await
+    }
+
+    pub async fn transaction_with<T, F>(&self, isolation: Isolation, work: F) -> Result<T>
+    where
+        F: for<'t> FnMut(&'t mut UnitOfWork<'static>) -> BoxFuture<'t, Result<T>>,
+    {
+        transaction::run(&self.pool, isolation, work).await
+    }
+
+    /// Global search box: ranked partners, products and variants.
+    pub async fn search(
+        &self,
+        company_id: Uuid,
+        text: &str,
+        kinds: &[SearchKind],
+        limit: u32,
+    ) -> Result<Vec<SearchHit>> {
+        let mut conn = self.acquire().await?;
+        SearchService::new(&mut conn)
+            .search(company_id, text, kinds, limit)
+            .await
+    }
+
+    /// Records one stock movement and updates the ledger atomically.
+    pub async fn post_stock_movement(
+        &self,
+        movement: CreateStockMovement,
+        policy: NegativeStockPolicy,
+    ) -> Result<StockPosting> {
+        self.transaction(|uow| {
+            let movement = movement.clone();
+            Box::pin(async move { uow.stock(policy).post(movement).await })
+        })
         .await
-        .map_err(AppError::Database)?;
+    }
 
-        Ok(Self::row_to_user(row))
+    pub async fn ship_transfer(
+        &self,
+        transfer_id: Uuid,
+        policy: NegativeStockPolicy,
+    ) -> Result<StockTransfer> {
+        self.transaction(|uow| {
+            Box::pin(async move { uow.transfers(policy).ship(transfer_id).await })
+        })
+        .await
+    }
+
+    pub async fn receive_transfer(
+        &self,
+        transfer_id: Uuid,
+        policy: NegativeStockPolicy,
+    ) -> Result<StockTransfer> {
+        self.transaction(|uow| {
+            Box::pin(async move { uow.transfers(policy).receive(transfer_id).await })
+        })
+        .await
+    }
+
+    /// Cancels a draft or shipped transfer, returning shipped goods to the
+    /// source warehouse.
+    pub async fn cancel_transfer(
+        &self,
+        transfer_id: Uuid,
+        policy: NegativeStockPolicy,
+    ) -> Result<StockTransfer> {
+        self.transaction(|uow| {
+            Box::pin(async move { uow.transfers(policy).cancel(transfer_id).await })
+        })
+        .await
+    }
+
+    /// Posts a submitted count's variances as adjustments.
+    pub async fn approve_count(&self, count_id: Uuid, approver_id: Uuid) -> Result<StockCount> {
+        self.transaction(|uow| {
+            Box::pin(async move { uow.counts().approve(count_id, approver_id).await })
+        })
+        .await
     }
 
-    pub async fn get_user(&self, id: i64) -> Result<Option<User>> {
-        let row = sqlx::query(
-            r#"
-            SELECT id, name, email, created_at, updated_at
-            FROM users
-            WHERE id = $1
-            "#,
-        )
-        .bind(id)
-        .fetch_optional(&self.pool)
+    /// Books goods received on a confirmed purchase order into its warehouse.
+    pub async fn receive_purchase_order(
+        &self,
+        order_id: Uuid,
+        receipts: Vec<PurchaseReceipt>,
+        policy: NegativeStockPolicy,
+    ) -> Result<Vec<StockPosting>> {
+        self.transaction(|uow| {
+            let receipts = receipts.clone();
+            Box::pin(async move { uow.receipts(policy).receive(order_id, receipts).await })
+        })
         .await
-        .map_err(AppError::Database)?;
+    }
 
-        Ok(row.map(Self::row_to_user))
+    /// Adds a landed cost document's charges to the cost of its receipts.
+    pub async fn post_landed_cost(&self, landed_cost_id: Uuid) -> Result<LandedCost> {
+        self.transaction(|uow| {
+            Box::pin(async move { uow.landed_costs().post(landed_cost_id).await })
+        })
+        .await
     }
 
-    pub async fn get_all_users(&self) -> Result<Vec<User>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, name, email, created_at, updated_at
-            FROM users
-            ORDER BY id
-            "#,
-        )
-        .fetch_all(&self.pool)
+    pub async fn confirm_sales_order(
+        &self,
+        order_id: Uuid,
+        warehouse_id: Uuid,
+        policy: NegativeStockPolicy,
+    ) -> Result<Vec<StockReservation>> {
+        self.transaction(|uow| {
+            Box::pin(async move {
+                uow.reservations(policy)
+                    .confirm_order(order_id, warehouse_id)
+                    .await
+            })
+        })
         .await
-        .map_err(AppError::Database)?;
+    }
 
-        Ok(rows.into_iter().map(Self::row_to_user).collect())
+    /// Cancels a sales order, releasing whatever it had reserved.
+    pub async fn cancel_sales_order(&self, order_id: Uuid) -> Result<()> {
+        self.transaction(|uow| {
+            Box::pin(async move {
+                uow.reservations(NegativeStockPolicy::default())
+                    .cancel_order(order_id)
+                    .await
+            })
+        })
+        .await
     }
 
-    pub async fn update_user(
+    /// Unit price to prefill on a sales order line being entered, if there
+    /// is one in the order's currency.
+    pub async fn suggest_line_price(
         &self,
-        id: i64,
-        update: UpdateUser,
-    ) -> Result<Option<User>> {
-        if let (Some(name), Some(email)) = (&update.name, &update.email) {
-            let row = sqlx::query(
-                r#"
-                UPDATE users 
-                SET name = $1, email = $2, updated_at = NOW()
-                WHERE id = $3 
-                RETURNING id, name, email, created_at, updated_at
-                "#,
-            )
-            .bind(name)
-            .bind(email)
-            .bind(id)
-            .fetch_optional(&self.pool)
+        order_id: Uuid,
+        variant_id: Uuid,
+        quantity: Quantity,
+        unit_id: Option<Uuid>,
+    ) -> Result<Option<SuggestedPrice>> {
+        let mut conn = self.acquire().await?;
+        PricingService::new(&mut conn)
+            .suggest_line_price(order_id, variant_id, quantity, unit_id)
             .await
-            .map_err(AppError::Database)?;
-            return Ok(row.map(Self::row_to_user));
-        }
-
-        if let Some(name) = &update.name {
-            let row = sqlx::query(
-                r#"
-                UPDATE users 
-                SET name = $1, updated_at = NOW()
-                WHERE id = $2 
-                RETURNING id, name, email, created_at, updated_at
-                "#,
-            )
-            .bind(name)
-            .bind(id)
-            .fetch_optional(&self.pool)
+    }
+
+    /// Runs replenishment and drafts purchase orders for everything that
+    /// has a vendor, in one transaction.
+    pub async fn replenish(
+        &self,
+        company_id: Uuid,
+        order_date: NaiveDate,
+        created_by: Uuid,
+    ) -> Result<Vec<PurchaseOrder>> {
+        self.transaction(|uow| {
+            Box::pin(async move {
+                let mut replenishment = uow.replenishment();
+                let proposals = replenishment.run(company_id).await?;
+                replenishment
+                    .draft_orders(company_id, &proposals, order_date, created_by)
+                    .await
+            })
+        })
+        .await
+    }
+
+    /// Quantity and book value per variant across the company's warehouses.
+    pub async fn inventory_value(&self, company_id: Uuid) -> Result<Vec<VariantValue>> {
+        let mut conn = self.acquire().await?;
+        ValuationService::new(&mut conn)
+            .inventory_value(company_id)
             .await
-            .map_err(AppError::Database)?;
-            return Ok(row.map(Self::row_to_user));
-        }
-
-        if let Some(email) = &update.email {
-            let row = sqlx::query(
-                r#"
-                UPDATE users 
-                SET email = $1, updated_at = NOW()
-                WHERE id = $2 
-                RETURNING id, name, email, created_at, updated_at
-                "#,
-            )
-            .bind(email)
-            .bind(id)
-            .fetch_optional(&self.pool)
+    }
+
+    /// Stock per variant and warehouse as it stood at `at`, recomputed from
+    /// movement history.
+    pub async fn stock_as_of(
+        &self,
+        company_id: Uuid,
+        at: DateTime<Utc>,
+        warehouse_id: Option<Uuid>,
+    ) -> Result<Vec<StockAsOf>> {
+        let mut conn = self.acquire().await?;
+        HistoryService::new(&mut conn)
+            .as_of(company_id, at, warehouse_id)
             .await
-            .map_err(AppError::Database)?;
-            return Ok(row.map(Self::row_to_user));
-        }
+    }
 
-        self.get_user(id).await
+    /// Rebuilds the company's stock ledger from its movements and reports
+    /// the rows that had drifted.
+    pub async fn rebuild_stock_ledger(&self, company_id: Uuid) -> Result<Vec<LedgerDrift>> {
+        self.transaction(|uow| {
+            Box::pin(async move { uow.history().rebuild_ledger(company_id).await })
+        })
+        .await
     }
 
-    pub async fn delete_user(&self, id: i64) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM users WHERE id = $1")
-            .bind(id)
-            .execute(&self.pool)
-            .await
-            .map_err(AppError::Database)?;
+    pub async fn create_user(&self, user: CreateUser) -> Result<User> {
+        let mut conn = self.acquire().await?;
+        UserRepository::new(&mut conn).create(user).await
+    }
+
+    pub async fn get_user(&self, id: Uuid) -> Result<Option<User>> {
+        let mut conn = self.acquire().await?;
+        UserRepository::new(&mut conn).get(id).await
+    }
+
+    pub async fn list_users(&self, query: &ListQuery) -> Result<Page<User>> {
+        let mut conn = self.acquire().await?;
+        UserRepository::new(&mut conn).list(query).await
+    }
 
-        Ok(result.rows_affected() > 0)
+    pub async fn update_user(&self, id: Uuid, update: UpdateUser) -> Result<Option<User>> {
+        let mut conn = self.acquire().await?;
+        UserRepository::new(&mut conn).update(id, update).await
     }
 
-    fn row_to_user(row: PgRow) -> User {
-        User {
-            id: row.get("id"),
-            name: row.get("name"),
-            email: row.get("email"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        }
+    pub async fn delete_user(&self, id: Uuid) -> Result<bool> {
+        let mut conn = self.acquire().await?;
+        UserRepository::new(&mut conn).delete(id).await
     }
 }
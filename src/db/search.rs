@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgConnection;
+use sqlx::{FromRow, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::db::repositories::query::contains_pattern;
+use crate::error::{AppError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchKind {
+    Partner,
+    Product,
+    Variant,
+}
+
+impl SearchKind {
+    pub const ALL: &'static [SearchKind] = &[
+        SearchKind::Partner,
+        SearchKind::Product,
+        SearchKind::Variant,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            SearchKind::Partner => "partner",
+            SearchKind::Product => "product",
+            SearchKind::Variant => "variant",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|k| k.label() == label)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub kind: SearchKind,
+    pub id: Uuid,
+    pub title: String,
+    /// Secondary line for pickers: contact details, SKU or product name.
+    pub subtitle: Option<String>,
+    /// Between 0 and 1; exact code matches score 1.
+    pub score: f32,
+}
+
+#[derive(FromRow)]
+struct HitRow {
+    kind: String,
+    id: Uuid,
+    title: String,
+    subtitle: Option<String>,
+    score: f32,
+}
+
+/// Ranked lookup across partners, products and variants of one company,
+/// using the trigram and full-text indexes from migration 0007. Postgres only.
+pub struct SearchService<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> SearchService<'c> {
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+
+    pub async fn search(
+        &mut self,
+        company_id: Uuid,
+        text: &str,
+        kinds: &[SearchKind],
+        limit: u32,
+    ) -> Result<Vec<SearchHit>> {
+        let text = text.trim();
+        if text.is_empty() || kinds.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pattern = contains_pattern(text);
+
+        let mut query =
+            QueryBuilder::<Postgres>::new("SELECT kind, id, title, subtitle, score FROM (");
+        for (i, kind) in kinds.iter().enumerate() {
+            if i > 0 {
+                query.push(" UNION ALL ");
+            }
+            match kind {
+                SearchKind::Partner => push_partners(&mut query, company_id, text, &pattern),
+                SearchKind::Product => push_products(&mut query, company_id, text, &pattern),
+                SearchKind::Variant => push_variants(&mut query, company_id, text, &pattern),
+            }
+        }
+        query.push(") hits ORDER BY score DESC, title LIMIT ");
+        query.push_bind(i64::from(limit.max(1)));
+
+        let rows = query
+            .build_query_as::<HitRow>()
+            .fetch_all(&mut *self.conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(SearchHit {
+                    kind: SearchKind::from_label(&row.kind)?,
+                    id: row.id,
+                    title: row.title,
+                    subtitle: row.subtitle,
+                    score: row.score,
+                })
+            })
+            .collect())
+    }
+}
+
+// Each arm scores with the best of trigram similarity against the short
+// fields and full-text rank against the long ones, and matches on either
+// or on a plain substring so one- and two-letter queries still find rows.
+
+fn push_partners(
+    query: &mut QueryBuilder<'_, Postgres>,
+    company_id: Uuid,
+    text: &str,
+    pattern: &str,
+) {
+    query
+        .push("SELECT 'partner' AS kind, p.id, p.name AS title, ")
+        .push("NULLIF(concat_ws(' · ', p.email, p.phone), '') AS subtitle, ")
+        .push("CAST(GREATEST(word_similarity(")
+        .push_bind(text.to_string())
+        .push(", p.name), similarity(COALESCE(p.email, ''), ")
+        .push_bind(text.to_string())
+        .push("), ts_rank(p.search_vector, plainto_tsquery('simple', ")
+        .push_bind(text.to_string())
+        .push("))) AS REAL) AS score FROM partners p WHERE p.company_id = ")
+        .push_bind(company_id)
+        .push(" AND p.is_active AND (")
+        .push_bind(text.to_string())
+        .push(" <% p.name OR p.search_vector @@ plainto_tsquery('simple', ")
+        .push_bind(text.to_string())
+        .push(") OR p.name ILIKE ")
+        .push_bind(pattern.to_string())
+        .push(" OR p.email ILIKE ")
+        .push_bind(pattern.to_string())
+        .push(" OR p.phone ILIKE ")
+        .push_bind(pattern.to_string())
+        .push(")");
+}
+
+fn push_products(
+    query: &mut QueryBuilder<'_, Postgres>,
+    company_id: Uuid,
+    text: &str,
+    pattern: &str,
+) {
+    query
+        .push("SELECT 'product' AS kind, p.id, p.name AS title, p.category AS subtitle, ")
+        .push("CAST(GREATEST(word_similarity(")
+        .push_bind(text.to_string())
+        .push(", p.name), ts_rank(p.search_vector, plainto_tsquery('simple', ")
+        .push_bind(text.to_string())
+        .push("))) AS REAL) AS score FROM products p WHERE p.company_id = ")
+        .push_bind(company_id)
+        .push(" AND p.is_active AND (")
+        .push_bind(text.to_string())
+        .push(" <% p.name OR p.search_vector @@ plainto_tsquery('simple', ")
+        .push_bind(text.to_string())
+        .push(") OR p.name ILIKE ")
+        .push_bind(pattern.to_string())
+        .push(")");
+}
+
+fn push_variants(
+    query: &mut QueryBuilder<'_, Postgres>,
+    company_id: Uuid,
+    text: &str,
+    pattern: &str,
+) {
+    query
+        .push("SELECT 'variant' AS kind, v.id, COALESCE(v.sku, v.barcode, p.name) AS title, ")
+        .push("p.name AS subtitle, ")
+        .push("CAST(CASE WHEN lower(v.sku) = lower(")
+        .push_bind(text.to_string())
+        .push(") OR v.barcode = ")
+        .push_bind(text.to_string())
+        .push(" THEN 1 ELSE GREATEST(similarity(COALESCE(v.sku, ''), ")
+        .push_bind(text.to_string())
+        .push("), similarity(COALESCE(v.barcode, ''), ")
+        .push_bind(text.to_string())
+        .push(")) END AS REAL) AS score ")
+        .push(
+            "FROM product_variants v JOIN products p ON p.id = v.product_id WHERE p.company_id = ",
+        )
+        .push_bind(company_id)
+        .push(" AND v.is_active AND (v.sku % ")
+        .push_bind(text.to_string())
+        .push(" OR v.sku ILIKE ")
+        .push_bind(pattern.to_string())
+        .push(" OR v.barcode ILIKE ")
+        .push_bind(pattern.to_string())
+        .push(")");
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{SearchKind, SearchService};
+    use crate::db::enums::TrackingMode;
+    use crate::db::testing;
+
+    async fn partner(conn: &mut sqlx::PgConnection, company_id: Uuid, name: &str) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO partners (company_id, name, type) VALUES ($1, $2, 'customer') \
+             RETURNING id",
+        )
+        .bind(company_id)
+        .bind(name)
+        .fetch_one(conn)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn typos_still_find_the_closest_match() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let northwind = partner(conn, company, "Northwind Traders").await;
+        partner(conn, company, "Contoso").await;
+        let elsewhere = testing::company(conn).await;
+        partner(conn, elsewhere, "Northwind Traders").await;
+
+        let hits = SearchService::new(conn)
+            .search(company, "northwnd", SearchKind::ALL, 10)
+            .await
+            .unwrap();
+        let ids: Vec<_> = hits.iter().map(|hit| hit.id).collect();
+        assert_eq!(ids, [northwind]);
+        assert_eq!(hits[0].kind, SearchKind::Partner);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn an_exact_code_outranks_similar_ones() {
+        let mut tx = testing::transaction().await;
+        let conn = &mut *tx;
+        let company = testing::company(conn).await;
+        let sku = format!("NW-{}", Uuid::new_v4().simple());
+        let exact =
+            testing::variant(conn, company, TrackingMode::Untracked, Some(&sku), None).await;
+        let similar = format!("{}-B", sku);
+        testing::variant(conn, company, TrackingMode::Untracked, Some(&similar), None).await;
+
+        let mut search = SearchService::new(conn);
+        let hits = search
+            .search(company, &sku.to_lowercase(), &[SearchKind::Variant], 10)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].id, exact);
+        assert_eq!(hits[0].score, 1.0);
+        assert!(hits[1].score < 1.0);
+
+        let partners_only = search
+            .search(company, &sku, &[SearchKind::Partner], 10)
+            .await
+            .unwrap();
+        assert!(partners_only.is_empty());
+        assert!(
+            search
+                .search(company, "  ", SearchKind::ALL, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
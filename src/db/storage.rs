@@ -0,0 +1,287 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Executor, FromRow, IntoArguments, Pool, Postgres, Sqlite};
+use uuid::Uuid;
+
+use crate::config::{DatabaseBackend, DatabaseConfig};
+use crate::db::backend::Backend;
+use crate::db::migrations::{AppliedMigration, MigrationReport, Migrator};
+use crate::db::models::*;
+use crate::db::repositories::{Entity, ListQuery, Page, Repository};
+use crate::db::repository::Database;
+use crate::error::{AppError, Result};
+
+/// Backend-independent CRUD for one entity type.
+#[async_trait]
+pub trait Store<E: Entity>: Send + Sync {
+    async fn create(&self, input: E::Create) -> Result<E>;
+    async fn get(&self, id: Uuid) -> Result<Option<E>>;
+    async fn list(&self, query: &ListQuery) -> Result<Page<E>>;
+    async fn update(&self, id: Uuid, input: E::Update) -> Result<Option<E>>;
+    async fn archive(&self, id: Uuid) -> Result<bool>;
+}
+
+/// Everything the UI needs from a database, whichever backend is configured.
+/// Call entity operations through the store, e.g.
+/// `Store::<Partner>::list(storage.as_ref(), &ListQuery::new())`.
+///
+/// Only the entities both schemas keep in full belong here. The SQLite
+/// schema stops at these tables: stock movements and everything built on
+/// them (valuation, transfers, counts, reservations, lots, bins, units of
+/// measure, price lists, bills of materials, landed costs) are Postgres
+/// only, reached through `Database`. Columns that point into those tables,
+/// such as `product_variants.stock_unit_id`, exist in SQLite to keep the
+/// shared rows the same shape and are left NULL there. SQLite stores
+/// decimals as REAL; they read back rounded to the column's scale, exact up
+/// to 15 significant digits.
+#[async_trait]
+pub trait Storage:
+    Store<User>
+    + Store<Company>
+    + Store<Currency>
+    + Store<Partner>
+    + Store<Product>
+    + Store<ProductVariant>
+    + Store<Warehouse>
+    + Store<SalesOrder>
+    + Store<SalesOrderLine>
+    + Store<PurchaseOrder>
+    + Store<PurchaseOrderLine>
+    + Store<ChartOfAccount>
+    + Store<JournalEntry>
+    + Store<JournalLine>
+{
+    fn backend(&self) -> DatabaseBackend;
+    /// Round trip to the database, used by connection health checks.
+    async fn ping(&self) -> Result<()>;
+    async fn migrate(&self) -> Result<MigrationReport>;
+    async fn migration_status(&self) -> Result<MigrationReport>;
+}
+
+pub struct SqlStorage<DB: Backend> {
+    pool: Pool<DB>,
+}
+
+pub type PgStorage = SqlStorage<Postgres>;
+pub type SqliteStorage = SqlStorage<Sqlite>;
+
+impl<DB: Backend> SqlStorage<DB> {
+    pub fn new(pool: Pool<DB>) -> Self {
+        Self { pool }
+    }
+
+    pub fn pool(&self) -> &Pool<DB> {
+        &self.pool
+    }
+}
+
+impl SqliteStorage {
+    pub async fn connect(config: &DatabaseConfig) -> Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(&config.sqlite_path)
+            .create_if_missing(true)
+            .foreign_keys(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(Self::new(pool))
+    }
+}
+
+/// Opens the backend selected in `config`.
+pub async fn open(config: &DatabaseConfig) -> Result<Arc<dyn Storage>> {
+    match config.backend {
+        DatabaseBackend::Postgres => {
+            let database = Database::connect(config).await?;
+            Ok(Arc::new(database.storage()))
+        }
+        DatabaseBackend::Sqlite => Ok(Arc::new(SqliteStorage::connect(config).await?)),
+    }
+}
+
+#[async_trait]
+impl<E, DB> Store<E> for SqlStorage<DB>
+where
+    E: Entity + for<'r> FromRow<'r, DB::Row> + 'static,
+    E::Create: 'static,
+    E::Update: 'static,
+    DB: Backend,
+    for<'e> &'e mut DB::Connection: Executor<'e, Database = DB>,
+    for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+    async fn create(&self, input: E::Create) -> Result<E> {
+        let mut conn = self.pool.acquire().await.map_err(AppError::Database)?;
+        Repository::<E, DB>::new(&mut conn).create(input).await
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<E>> {
+        let mut conn = self.pool.acquire().await.map_err(AppError::Database)?;
+        Repository::<E, DB>::new(&mut conn).get(id).await
+    }
+
+    async fn list(&self, query: &ListQuery) -> Result<Page<E>> {
+        let mut conn = self.pool.acquire().await.map_err(AppError::Database)?;
+        Repository::<E, DB>::new(&mut conn).list(query).await
+    }
+
+    async fn update(&self, id: Uuid, input: E::Update) -> Result<Option<E>> {
+        let mut conn = self.pool.acquire().await.map_err(AppError::Database)?;
+        Repository::<E, DB>::new(&mut conn).update(id, input).await
+    }
+
+    async fn archive(&self, id: Uuid) -> Result<bool> {
+        let mut conn = self.pool.acquire().await.map_err(AppError::Database)?;
+        Repository::<E, DB>::new(&mut conn).archive(id).await
+    }
+}
+
+#[async_trait]
+impl<DB> Storage for SqlStorage<DB>
+where
+    DB: Backend,
+    for<'e> &'e mut DB::Connection: Executor<'e, Database = DB>,
+    for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+    AppliedMigration: for<'r> FromRow<'r, DB::Row>,
+    Self: Store<User>
+        + Store<Company>
+        + Store<Currency>
+        + Store<Partner>
+        + Store<Product>
+        + Store<ProductVariant>
+        + Store<Warehouse>
+        + Store<SalesOrder>
+        + Store<SalesOrderLine>
+        + Store<PurchaseOrder>
+        + Store<PurchaseOrderLine>
+        + Store<ChartOfAccount>
+        + Store<JournalEntry>
+        + Store<JournalLine>,
+{
+    fn backend(&self) -> DatabaseBackend {
+        DB::KIND
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let mut conn = self.pool.acquire().await.map_err(AppError::Database)?;
+        Executor::execute(&mut *conn, "SELECT 1")
+            .await
+            .map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    async fn migrate(&self) -> Result<MigrationReport> {
+        Migrator::new(&self.pool).run().await
+    }
+
+    async fn migration_status(&self) -> Result<MigrationReport> {
+        Migrator::new(&self.pool).status().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SqliteStorage, Store};
+    use crate::db::enums::{AccountType, CostingMethod, JournalStatus, UserRole};
+    use crate::db::models::*;
+    use crate::db::money::Money;
+    use crate::db::testing;
+
+    #[tokio::test]
+    async fn sqlite_keeps_journals_exact_and_final_once_posted() {
+        let storage = SqliteStorage::new(testing::sqlite().await);
+        let company = Store::<Company>::create(
+            &storage,
+            CreateCompany {
+                name: "Offline".into(),
+                address: None,
+                base_currency_id: None,
+                costing_method: CostingMethod::Fifo,
+            },
+        )
+        .await
+        .unwrap();
+        let user = Store::<User>::create(
+            &storage,
+            CreateUser {
+                email: "offline@example.com".into(),
+                name: "Offline".into(),
+                password_hash: "!".into(),
+                role: UserRole::Accountant,
+            },
+        )
+        .await
+        .unwrap();
+        let account = Store::<ChartOfAccount>::create(
+            &storage,
+            CreateChartOfAccount {
+                company_id: company.id,
+                code: "3000".into(),
+                name: "Equity".into(),
+                account_type: AccountType::Equity,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(account.account_type, AccountType::Equity);
+
+        let entry = Store::<JournalEntry>::create(
+            &storage,
+            CreateJournalEntry {
+                company_id: company.id,
+                entry_date: chrono::NaiveDate::from_ymd_opt(2026, 1, 31).unwrap(),
+                reference: None,
+                currency_id: None,
+                exchange_rate: None,
+                created_by: user.id,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(entry.status, JournalStatus::Draft);
+        let amount: Money = "1234567.89".parse().unwrap();
+        let line = Store::<JournalLine>::create(
+            &storage,
+            CreateJournalLine {
+                journal_entry_id: entry.id,
+                account_id: account.id,
+                partner_id: None,
+                debit: Money::ZERO,
+                credit: amount,
+                currency_amount: None,
+                description: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(line.credit, amount);
+
+        Store::<JournalEntry>::update(
+            &storage,
+            entry.id,
+            UpdateJournalEntry {
+                status: Some(JournalStatus::Posted),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        let changed = Store::<JournalLine>::update(
+            &storage,
+            line.id,
+            UpdateJournalLine {
+                credit: Some(Money::from(1)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(changed.is_none());
+    }
+}
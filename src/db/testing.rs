@@ -0,0 +1,168 @@
+//! Database fixtures for tests. Postgres tests are ignored by default; run
+//! them against the database named by `TEST_DATABASE_URL` with
+//! `cargo test -- --ignored`. SQLite tests run in memory and always run.
+
+use sqlx::postgres::{PgConnection, PgPool};
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::db::enums::{TrackingMode, UserRole};
+use crate::db::migrations::Migrator;
+use crate::db::money::{Money, Quantity};
+
+/// The migrated test database. Tests that commit must leave no rows behind.
+pub(crate) async fn pool() -> PgPool {
+    let url =
+        std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL names the test database");
+    let pool = PgPool::connect(&url)
+        .await
+        .expect("connect to TEST_DATABASE_URL");
+    Migrator::new(&pool)
+        .run()
+        .await
+        .expect("migrate the test database");
+    pool
+}
+
+/// A migrated database in a transaction that rolls back when dropped.
+pub(crate) async fn transaction() -> Transaction<'static, Postgres> {
+    pool()
+        .await
+        .begin()
+        .await
+        .expect("begin a test transaction")
+}
+
+/// A migrated in-memory SQLite database, dropped with the pool.
+pub(crate) async fn sqlite() -> SqlitePool {
+    // One connection, since each in-memory connection is its own database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("open an in-memory database");
+    Migrator::new(&pool)
+        .run()
+        .await
+        .expect("migrate the in-memory database");
+    pool
+}
+
+pub(crate) async fn company(conn: &mut PgConnection) -> Uuid {
+    let currency: Uuid = sqlx::query_scalar(
+        "INSERT INTO currencies (code, name) VALUES ($1, 'Test') \
+         ON CONFLICT (code) DO UPDATE SET name = EXCLUDED.name RETURNING id",
+    )
+    .bind("TST")
+    .fetch_one(&mut *conn)
+    .await
+    .unwrap();
+    sqlx::query_scalar(
+        "INSERT INTO companies (name, base_currency_id) VALUES ('Test company', $1) RETURNING id",
+    )
+    .bind(currency)
+    .fetch_one(conn)
+    .await
+    .unwrap()
+}
+
+pub(crate) async fn warehouse(conn: &mut PgConnection, company_id: Uuid) -> Uuid {
+    sqlx::query_scalar("INSERT INTO warehouses (company_id, name) VALUES ($1, 'Main') RETURNING id")
+        .bind(company_id)
+        .fetch_one(conn)
+        .await
+        .unwrap()
+}
+
+/// A variant of a new product, with a random SKU unless one is given.
+pub(crate) async fn variant(
+    conn: &mut PgConnection,
+    company_id: Uuid,
+    tracking: TrackingMode,
+    sku: Option<&str>,
+    barcode: Option<&str>,
+) -> Uuid {
+    let product_id: Uuid = sqlx::query_scalar(
+        "INSERT INTO products (company_id, name) VALUES ($1, 'Test product') RETURNING id",
+    )
+    .bind(company_id)
+    .fetch_one(&mut *conn)
+    .await
+    .unwrap();
+    sqlx::query_scalar(
+        "INSERT INTO product_variants (product_id, sku, barcode, tracking, cost_price) \
+         VALUES ($1, $2, $3, $4, $5) RETURNING id",
+    )
+    .bind(product_id)
+    .bind(sku.map_or_else(|| Uuid::new_v4().to_string(), str::to_string))
+    .bind(barcode)
+    .bind(tracking)
+    .bind(Money::from(10))
+    .fetch_one(conn)
+    .await
+    .unwrap()
+}
+
+pub(crate) async fn user(conn: &mut PgConnection, role: UserRole) -> Uuid {
+    sqlx::query_scalar(
+        "INSERT INTO users (email, name, password_hash, role) \
+         VALUES ($1, 'Test user', '!', $2) RETURNING id",
+    )
+    .bind(format!("{}@example.com", Uuid::new_v4()))
+    .bind(role)
+    .fetch_one(conn)
+    .await
+    .unwrap()
+}
+
+pub(crate) async fn partner(conn: &mut PgConnection, company_id: Uuid) -> Uuid {
+    sqlx::query_scalar(
+        "INSERT INTO partners (company_id, name, type) VALUES ($1, 'Test partner', 'both') \
+         RETURNING id",
+    )
+    .bind(company_id)
+    .fetch_one(conn)
+    .await
+    .unwrap()
+}
+
+/// A confirmed purchase order into `warehouse_id` from a new vendor, in the
+/// company's base currency, with one line of `quantity` at `unit_cost` in
+/// the variant's stock unit. Returns the order and the line.
+pub(crate) async fn purchase_order(
+    conn: &mut PgConnection,
+    company_id: Uuid,
+    warehouse_id: Uuid,
+    variant_id: Uuid,
+    quantity: i64,
+    unit_cost: i64,
+) -> (Uuid, Uuid) {
+    let vendor = partner(conn, company_id).await;
+    let buyer = user(conn, UserRole::Admin).await;
+    let order: Uuid = sqlx::query_scalar(
+        "INSERT INTO purchase_orders \
+         (company_id, vendor_id, order_date, currency_id, warehouse_id, status, created_by) \
+         SELECT id, $2, CURRENT_DATE, base_currency_id, $3, 'CONFIRMED', $4 \
+         FROM companies WHERE id = $1 RETURNING id",
+    )
+    .bind(company_id)
+    .bind(vendor)
+    .bind(warehouse_id)
+    .bind(buyer)
+    .fetch_one(&mut *conn)
+    .await
+    .unwrap();
+    let line = sqlx::query_scalar(
+        "INSERT INTO purchase_order_lines (purchase_order_id, variant_id, quantity, unit_cost) \
+         VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+    .bind(order)
+    .bind(variant_id)
+    .bind(Quantity::from(quantity))
+    .bind(Money::from(unit_cost))
+    .fetch_one(conn)
+    .await
+    .unwrap();
+    (order, line)
+}
@@ -0,0 +1,296 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use sqlx::postgres::{PgConnection, PgPool};
+use sqlx::{Connection, Postgres, Transaction};
+
+use crate::config::NegativeStockPolicy;
+use crate::db::catalog::CatalogService;
+use crate::db::inventory::{
+    BomService, CountService, HistoryService, LandedCostService, LocationService, LotService,
+    ReceiptService, ReplenishmentService, ReservationService, StockService, TransferService,
+    UnitService, ValuationService,
+};
+use crate::db::pricing::PricingService;
+use crate::db::repositories::{Entity, Repository, UserRepository};
+use crate::error::{AppError, Result};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Attempts made by `Database::transaction` before a serialization failure
+/// is returned to the caller.
+const MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Isolation {
+    #[default]
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl Isolation {
+    fn statement(self) -> &'static str {
+        match self {
+            Isolation::ReadCommitted => "SET TRANSACTION ISOLATION LEVEL READ COMMITTED",
+            Isolation::RepeatableRead => "SET TRANSACTION ISOLATION LEVEL REPEATABLE READ",
+            Isolation::Serializable => "SET TRANSACTION ISOLATION LEVEL SERIALIZABLE",
+        }
+    }
+}
+
+/// A database transaction handed to repositories. Dropping it without
+/// `commit` rolls everything back.
+pub struct UnitOfWork<'c> {
+    tx: Transaction<'c, Postgres>,
+}
+
+impl<'c> UnitOfWork<'c> {
+    pub(crate) fn new(tx: Transaction<'c, Postgres>) -> Self {
+        Self { tx }
+    }
+
+    pub fn conn(&mut self) -> &mut PgConnection {
+        &mut self.tx
+    }
+
+    pub fn repo<E: Entity>(&mut self) -> Repository<'_, E> {
+        Repository::new(&mut *self.tx)
+    }
+
+    pub fn boms(&mut self) -> BomService<'_> {
+        BomService::new(&mut self.tx)
+    }
+
+    pub fn catalog(&mut self) -> CatalogService<'_> {
+        CatalogService::new(&mut self.tx)
+    }
+
+    pub fn counts(&mut self) -> CountService<'_> {
+        CountService::new(&mut self.tx)
+    }
+
+    pub fn history(&mut self) -> HistoryService<'_> {
+        HistoryService::new(&mut self.tx)
+    }
+
+    pub fn landed_costs(&mut self) -> LandedCostService<'_> {
+        LandedCostService::new(&mut self.tx)
+    }
+
+    pub fn locations(&mut self) -> LocationService<'_> {
+        LocationService::new(&mut self.tx)
+    }
+
+    pub fn lots(&mut self) -> LotService<'_> {
+        LotService::new(&mut self.tx)
+    }
+
+    pub fn pricing(&mut self) -> PricingService<'_> {
+        PricingService::new(&mut self.tx)
+    }
+
+    pub fn receipts(&mut self, policy: NegativeStockPolicy) -> ReceiptService<'_> {
+        ReceiptService::new(&mut self.tx, policy)
+    }
+
+    pub fn replenishment(&mut self) -> ReplenishmentService<'_> {
+        ReplenishmentService::new(&mut self.tx)
+    }
+
+    pub fn reservations(&mut self, policy: NegativeStockPolicy) -> ReservationService<'_> {
+        ReservationService::new(&mut self.tx, policy)
+    }
+
+    pub fn stock(&mut self, policy: NegativeStockPolicy) -> StockService<'_> {
+        StockService::new(&mut self.tx, policy)
+    }
+
+    pub fn transfers(&mut self, policy: NegativeStockPolicy) -> TransferService<'_> {
+        TransferService::new(&mut self.tx, policy)
+    }
+
+    pub fn units(&mut self) -> UnitService<'_> {
+        UnitService::new(&mut self.tx)
+    }
+
+    pub fn valuation(&mut self) -> ValuationService<'_> {
+        ValuationService::new(&mut self.tx)
+    }
+
+    pub fn users(&mut self) -> UserRepository<'_> {
+        UserRepository::new(&mut self.tx)
+    }
+
+    pub async fn commit(self) -> Result<()> {
+        self.tx.commit().await.map_err(AppError::Database)
+    }
+
+    pub async fn rollback(self) -> Result<()> {
+        self.tx.rollback().await.map_err(AppError::Database)
+    }
+
+    /// Runs `work` inside a savepoint. If it fails only its own changes are
+    /// undone and the error is returned; the outer work can carry on.
+    pub async fn savepoint<T, F>(&mut self, work: F) -> Result<T>
+    where
+        F: for<'t> FnOnce(&'t mut UnitOfWork<'_>) -> BoxFuture<'t, Result<T>>,
+    {
+        let mut nested = UnitOfWork::new(
+            Connection::begin(&mut *self.tx)
+                .await
+                .map_err(AppError::Database)?,
+        );
+
+        match work(&mut nested).await {
+            Ok(value) => {
+                nested.commit().await?;
+                Ok(value)
+            }
+            Err(err) => {
+                nested.rollback().await?;
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Runs `work` in a new transaction, committing when it returns `Ok`
+/// and rolling back otherwise. Serialization failures and deadlocks retry
+/// the whole closure, so it must not have side effects outside the database.
+pub(crate) async fn run<T, F>(pool: &PgPool, isolation: Isolation, mut work: F) -> Result<T>
+where
+    F: for<'t> FnMut(&'t mut UnitOfWork<'static>) -> BoxFuture<'t, Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        let tx = pool.begin().await.map_err(AppError::Database)?;
+        let mut uow = UnitOfWork::new(tx);
+
+        let result = match sqlx::query(isolation.statement()).execute(uow.conn()).await {
+            Ok(_) => work(&mut uow).await,
+            Err(err) => Err(AppError::Database(err)),
+        };
+
+        let result = match result {
+            Ok(value) => uow.commit().await.map(|()| value),
+            Err(err) => {
+                // The transaction may already be aborted; dropping it rolls back
+                let _ = uow.rollback().await;
+                Err(err)
+            }
+        };
+
+        match result {
+            Err(err) if is_retryable(&err) && attempt < MAX_ATTEMPTS => {
+                tokio::time::sleep(Duration::from_millis(20 << attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// serialization_failure and deadlock_detected
+fn is_retryable(err: &AppError) -> bool {
+    match err {
+        AppError::Database(sqlx::Error::Database(db_err)) => {
+            matches!(db_err.code().as_deref(), Some("40001") | Some("40P01"))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Isolation, UnitOfWork, run};
+    use crate::db::enums::RoundingMode;
+    use crate::db::models::{CreateCurrency, Currency};
+    use crate::db::testing;
+    use crate::error::AppError;
+
+    const SERIALIZATION_FAILURE: &str =
+        "DO $$ BEGIN RAISE EXCEPTION 'conflict' USING ERRCODE = 'serialization_failure'; END $$";
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn serialization_failures_retry_the_whole_closure() {
+        let pool = testing::pool().await;
+        let mut attempts = 0;
+        let isolation = run(&pool, Isolation::Serializable, |uow| {
+            attempts += 1;
+            let conflict = attempts < 3;
+            Box::pin(async move {
+                if conflict {
+                    sqlx::query(SERIALIZATION_FAILURE)
+                        .execute(uow.conn())
+                        .await
+                        .map_err(AppError::Database)?;
+                }
+                sqlx::query_scalar::<_, String>("SHOW transaction_isolation")
+                    .fetch_one(uow.conn())
+                    .await
+                    .map_err(AppError::Database)
+            })
+        })
+        .await
+        .unwrap();
+        assert_eq!(attempts, 3);
+        assert_eq!(isolation, "serializable");
+
+        let mut attempts = 0;
+        let failed = run(&pool, Isolation::default(), |_| {
+            attempts += 1;
+            Box::pin(async { Err::<(), _>(AppError::App("refused".into())) })
+        })
+        .await;
+        assert!(failed.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn a_failed_savepoint_only_undoes_its_own_work() {
+        let mut uow = UnitOfWork::new(testing::transaction().await);
+        let currency = |code: &str| CreateCurrency {
+            code: code.into(),
+            name: "Test".into(),
+            symbol: None,
+            decimal_places: 2,
+            rounding_mode: RoundingMode::HalfUp,
+        };
+        let kept = uow
+            .repo::<Currency>()
+            .create(currency("XKA"))
+            .await
+            .unwrap();
+
+        let failed = uow
+            .savepoint(|uow| {
+                Box::pin(async move {
+                    uow.repo::<Currency>().create(currency("XKB")).await?;
+                    Err::<(), _>(AppError::App("refused".into()))
+                })
+            })
+            .await;
+        assert!(failed.is_err());
+        let saved = uow
+            .savepoint(|uow| {
+                Box::pin(async move { uow.repo::<Currency>().create(currency("XKC")).await })
+            })
+            .await
+            .unwrap();
+
+        let mut currencies = uow.repo::<Currency>();
+        assert!(currencies.get(kept.id).await.unwrap().is_some());
+        assert!(currencies.get(saved.id).await.unwrap().is_some());
+        let codes: Vec<String> = sqlx::query_scalar(
+            "SELECT code FROM currencies WHERE code IN ('XKA', 'XKB', 'XKC') ORDER BY code",
+        )
+        .fetch_all(uow.conn())
+        .await
+        .unwrap();
+        assert_eq!(codes, ["XKA", "XKC"]);
+    }
+}
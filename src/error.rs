@@ -1,4 +1,7 @@
 use thiserror::Error;
+use uuid::Uuid;
+
+use crate::db::money::Quantity;
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -8,6 +11,32 @@ pub enum AppError {
     #[error("Configuration error: {0}")]
     Config(#[from] config::ConfigError),
     
+    #[error("Migration error: {0}")]
+    Migration(String),
+
+    #[error("Database schema is at version {database} but this build only supports up to {supported}")]
+    SchemaAhead { database: i64, supported: i64 },
+
+    #[error("A user with email {0} already exists")]
+    DuplicateEmail(String),
+
+    #[error("{table} conflicts with an existing record ({constraint})")]
+    Conflict {
+        table: &'static str,
+        constraint: String,
+    },
+
+    #[error("Not enough stock: {on_hand} on hand, {requested} requested")]
+    InsufficientStock {
+        variant_id: Uuid,
+        warehouse_id: Uuid,
+        on_hand: Quantity,
+        requested: Quantity,
+    },
+
+    #[error("Invalid list query: {0}")]
+    InvalidQuery(String),
+
     #[error("Application error: {0}")]
     App(String),
 }
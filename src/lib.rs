@@ -0,0 +1,5 @@
+pub mod app;
+pub mod components;
+pub mod config;
+pub mod db;
+pub mod error;
@@ -1,60 +1,67 @@
-mod config;
-mod db;
-mod error;
-mod components;
-
 use std::path::PathBuf;
-use std::sync::Arc;
 
 use gpui::*;
-use gpui_component::{Theme, ThemeRegistry, TitleBar, *};
-use tokio::sync::RwLock;
+use gpui_component::{Theme, ThemeRegistry, *};
 
-use crate::error::AppError;
-use crate::app::AppState;
+use my_gpui_app::app::{AppState, AppView};
+use my_gpui_app::config::{self, DatabaseBackend};
+use my_gpui_app::db;
+use my_gpui_app::db::connection::{ConnectionManager, is_connection_error};
+use my_gpui_app::db::repositories::ListQuery;
+use my_gpui_app::db::storage::Store;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let config = config::load_config().unwrap_or_default();
     let db_config = config.database.clone();
 
-    let db = match db::Database::connect(&db_config).await {
-        Ok(database) => {
-            database.init_schema().await?;
+    let connection = ConnectionManager::new(db_config.clone());
 
-            let existing = database.get_all_users().await?;
-            if existing.is_empty() {
-                database
-                    .create_user(db::CreateUser {
-                        name: "John Doe".into(),
-                        email: "john@example.com".into(),
-                    })
-                    .await?;
-                database
-                    .create_user(db::CreateUser {
-                        name: "Jane Smith".into(),
-                        email: "jane@example.com".into(),
-                    })
-                    .await?;
-                database
-                    .create_user(db::CreateUser {
-                        name: "Bob Wilson".into(),
-                        email: "bob@example.com".into(),
-                    })
+    match connection.connect().await {
+        Ok((storage, report)) => {
+            println!("Database migrations: {}", report);
+
+            let existing =
+                Store::<db::User>::list(storage.as_ref(), &ListQuery::new().limit(1)).await?;
+            if existing.items.is_empty() {
+                for (email, name, role) in [
+                    ("john@example.com", "John Doe", db::UserRole::Admin),
+                    ("jane@example.com", "Jane Smith", db::UserRole::Accountant),
+                    ("bob@example.com", "Bob Wilson", db::UserRole::Sales),
+                ] {
+                    Store::<db::User>::create(
+                        storage.as_ref(),
+                        db::CreateUser {
+                            email: email.into(),
+                            name: name.into(),
+                            // "!" never matches a hash, so the account stays locked until a password is set
+                            password_hash: "!".into(),
+                            role,
+                        },
+                    )
                     .await?;
+                }
             }
 
-            let users = database.get_all_users().await?;
-            println!("Connected to database: {} with {} users", db_config.name, users.len());
-            Some(database)
+            match storage.backend() {
+                DatabaseBackend::Postgres => println!("Connected to database: {}", db_config.name),
+                DatabaseBackend::Sqlite => {
+                    println!("Opened local database: {}", db_config.sqlite_path)
+                }
+            }
         }
-        Err(e) => {
-            eprintln!("Failed to connect to database: {}", e);
-            None
+        Err(e) if is_connection_error(&e) => {
+            eprintln!(
+                "Failed to connect to database, retrying in background: {}",
+                e
+            )
         }
-    };
+        // Schema ahead of the app, checksum mismatch, failed migration: retrying won't help
+        Err(e) => return Err(e.into()),
+    }
 
-    let db = Arc::new(RwLock::new(db));
+    // Keeps reconnecting and health checking for as long as the app runs
+    connection.spawn();
 
     let app = Application::new().with_assets(gpui_component_assets::Assets);
 
@@ -75,8 +82,8 @@ async fn main() -> anyhow::Result<()> {
         });
 
         cx.open_window(WindowOptions::default(), |window, cx| {
-            let app_state = AppState::new(db.clone());
-            let view = cx.new(|_| AppView::new(app_state));
+            let app_state = AppState::new(connection.clone());
+            let view = cx.new(|cx| AppView::new(app_state, cx));
             cx.new(|cx| Root::new(view, window, cx))
         })
         .unwrap();